@@ -1,10 +1,22 @@
 #version 400
 
 in vec3 out_pos;
+in vec3 out_normal;
 in float visibility;
 
 out vec4 out_colour;
 
+//-----------------------
+
+const vec3 light_direction = vec3(0.3, 0.8, 0.5);
+const float ambient_light = 0.35;
+
+//-----------------------
+
 void main() {
-    out_colour = mix(vec4(0.1, 0.1, 0.1, 1.0), vec4(out_pos, 1.0), visibility);
+    // simple diffuse lighting from a fixed direction
+    float diffuse = max(dot(normalize(out_normal), normalize(light_direction)), 0.0);
+    vec3 lit_col = out_pos * (ambient_light + (1.0 - ambient_light) * diffuse);
+
+    out_colour = mix(vec4(0.1, 0.1, 0.1, 1.0), vec4(lit_col, 1.0), visibility);
 }
\ No newline at end of file
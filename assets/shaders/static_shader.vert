@@ -1,10 +1,12 @@
 #version 400
 
 in vec3 position;
+in vec3 normal;
 
 //-----------------------
 
 out vec3 out_pos;
+out vec3 out_normal;
 out float visibility;
 
 //-----------------------
@@ -12,10 +14,10 @@ out float visibility;
 uniform mat4 transform_matrix;
 uniform mat4 projection_matrix;
 uniform mat4 view_matrix;
+uniform float fog_density;
 
 //-----------------------
 
-const float fog_density = 0.1;
 const float fog_gradient = 2;
 
 //-----------------------
@@ -29,4 +31,5 @@ void main() {
 
     visibility = exp(-pow((camera_dist * fog_density), fog_gradient));
     out_pos = base_col;
+    out_normal = (transform_matrix * vec4(normal, 0.0)).xyz;
 }
\ No newline at end of file
@@ -1,24 +1,172 @@
 use terrainbasic::{
     entities::*,
     input::*,
-    models::raw_model::*,
     rendering::*,
     utils::*
 };
 
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
 use glutin::{
-    event::{Event, KeyboardInput, WindowEvent, ElementState},
+    event::{Event, KeyboardInput, WindowEvent, ElementState, VirtualKeyCode},
     event_loop::{ControlFlow, EventLoop}
 };
 
-use cgmath::{Vector3};
+const USAGE: &str = "usage: terrainbasic [options]
+
+view options:
+    --view-distance <i32>        radius in chunks loaded around the camera, from 1 to 32,
+                                 also changed while running with the + and - keys
+    --unload-margin <i32>        extra chunks past the view distance before chunks are
+                                 dropped, from 0 to 8
+    --lod-levels <u32>           coarser levels of detail past the view distance, each
+                                 reaching twice as far as the last (0 to turn off),
+                                 ignored by surface nets and dual contouring
+    --cache-mb <usize>           megabytes of built chunks kept after they leave the view
+    --no-occlusion               draws chunks even when rock hides them from the camera
+    --history-mb <usize>         megabytes of replaced densities kept to undo edits
+
+world directory options:
+    --world <path>               loads the edits and chunks saved in a directory, saving
+                                 back to it with F5 and on exit
+
+mesh options:
+    --triangulation-table <path> reads the marching cubes cases from a file instead of
+                                 the ones built in
+
+scheduling options:
+    --dispatch-budget <usize>    chunks sent to the workers per frame
+    --in-flight <usize>          most chunks the workers can be building at once
+    --upload-budget <usize>      chunks uploaded to the GPU per frame
+    --upload-millis <u64>        milliseconds that can be spent uploading per frame
+
+sculpting keys:
+    Q / E / R                    digs, fills or smooths a sphere in front of the camera
+    Z / Y                        undoes or redoes the last edit
+    F5                           saves the world when one was opened with --world
+";
+
+// settings read from the command line
+struct Options {
+    world_config: world_config::WorldConfig,
+    view_distance: i32,
+    unload_margin: i32,
+    lod_levels: u32,
+    cache_bytes: usize,
+    history_bytes: usize,
+    occlusion_culling: bool,
+    triangulation_table: Option<String>,
+    world_directory: Option<String>,
+    budget: chunk_scheduler::ChunkBudget
+}
+
+fn parse_options<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut options = Options {
+        world_config: world_config::WorldConfig::new(),
+        view_distance: constants::DEFAULT_VIEW_DISTANCE,
+        unload_margin: constants::DEFAULT_UNLOAD_MARGIN,
+        lod_levels: constants::DEFAULT_LOD_LEVELS,
+        cache_bytes: constants::DEFAULT_CHUNK_CACHE_BYTES,
+        history_bytes: constants::DEFAULT_EDIT_HISTORY_BYTES,
+        occlusion_culling: true,
+        triangulation_table: None,
+        world_directory: None,
+        budget: chunk_scheduler::ChunkBudget::new()
+    };
+
+    while let Some(flag) = args.next() {
+        // world options are handled by the config itself
+        if options.world_config.apply_flag(&flag, &mut args)? {
+            continue;
+        }
+
+        match flag.as_str() {
+            "--view-distance" => options.view_distance = parse_value(&flag, &mut args)?,
+            "--unload-margin" => options.unload_margin = parse_value(&flag, &mut args)?,
+            "--lod-levels" => options.lod_levels = parse_value(&flag, &mut args)?,
+            "--cache-mb" => options.cache_bytes = parse_megabytes(&flag, &mut args)?,
+            "--history-mb" => options.history_bytes = parse_megabytes(&flag, &mut args)?,
+            "--no-occlusion" => options.occlusion_culling = false,
+            "--world" => options.world_directory = Some(parse_value(&flag, &mut args)?),
+            "--triangulation-table" => options.triangulation_table = Some(parse_value(&flag, &mut args)?),
+            "--dispatch-budget" => options.budget.max_dispatches = parse_value(&flag, &mut args)?,
+            "--in-flight" => options.budget.max_in_flight = parse_value(&flag, &mut args)?,
+            "--upload-budget" => options.budget.max_uploads = parse_value(&flag, &mut args)?,
+            "--upload-millis" => {
+                options.budget.max_upload_time = Duration::from_millis(parse_value(&flag, &mut args)?);
+            }
+            _ => return Err(format!("unknown option '{}'", flag))
+        }
+    }
+
+    if options.view_distance < 1 || options.view_distance > constants::MAX_VIEW_DISTANCE {
+        return Err(format!(
+            "view distance must be between 1 and {}, got {}",
+            constants::MAX_VIEW_DISTANCE,
+            options.view_distance
+        ));
+    }
+
+    if options.unload_margin < 0 || options.unload_margin > constants::MAX_UNLOAD_MARGIN {
+        return Err(format!(
+            "unload margin must be between 0 and {}, got {}",
+            constants::MAX_UNLOAD_MARGIN,
+            options.unload_margin
+        ));
+    }
+
+    Ok(options)
+}
+
+// gets and parses the value that follows a flag
+fn parse_value<T: FromStr, I: Iterator<Item = String>>(flag: &str, args: &mut I) -> Result<T, String> {
+    let value = args.next().ok_or(format!("option '{}' needs a value", flag))?;
+
+    value.parse::<T>().map_err(|_| format!("invalid value '{}' for option '{}'", value, flag))
+}
+
+// gets a number of megabytes that follows a flag as bytes
+fn parse_megabytes<I: Iterator<Item = String>>(flag: &str, args: &mut I) -> Result<usize, String> {
+    let megabytes: usize = parse_value(flag, args)?;
+
+    megabytes.checked_mul(1024 * 1024).ok_or(format!("invalid value '{}' for option '{}'", megabytes, flag))
+}
+
+// saves the world, reporting how it went since it can't stop the demo
+fn save_world(chunk_manager: &mut chunk_manager::ChunkManager) {
+    match chunk_manager.save_world() {
+        Ok(region_count) => println!("saved {} region files", region_count),
+        Err(error) => eprintln!("{}", error)
+    }
+}
 
 fn main() {
+    // reads the settings from the command line so a seed always
+    // reproduces the same caves
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        println!("{}", world_config::WORLD_CONFIG_USAGE);
+        return;
+    }
+
+    let options = match parse_options(args.into_iter()) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}\n\n{}\n{}", error, USAGE, world_config::WORLD_CONFIG_USAGE);
+            std::process::exit(1);
+        }
+    };
+
     // first, creates an event loop
     let event_loop = EventLoop::new();
 
     // creates the window
-    let (context) = display_manager::create_display(&event_loop);
+    let context = display_manager::create_display(&event_loop);
 
     // creates a new loader
     let mut loader = loader::Loader::default();
@@ -32,32 +180,44 @@ fn main() {
     // creates a new input manager
     let mut input_manager = input_manager::InputManager::new();
 
-    // reads the triangulation table from the files and prepares conversion data
-    let tri_table = table_reader::TriangulationTable::new();
+    // gets the triangulation table and prepares conversion data, shared with
+    // the chunk workers if they mesh with marching cubes
+    let mut tri_table = match &options.triangulation_table {
+        Some(path) => match table_reader::TriangulationTable::from_file(Path::new(path)) {
+            Ok(tri_table) => tri_table,
+            Err(error) => {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+        },
+        None => table_reader::TriangulationTable::new()
+    };
 
-    // creates a model vector
-    let mut model_vector = Vec::<RawModel>::new();
+    if options.world_config.mesher_type == world_config::MesherType::MarchingCubes33 {
+        tri_table.load_extended();
+    }
 
-    // for x in 0..8 {
-    //     for y in 0..8 {
-    //         for z in 0..8 {
-    //             // creates a test chunk
-    //             let chunk = basic_marching_cubes::MCChunk::new(
-    //                 Vector3::new(x, y, z),
-    //                 &tri_table
-    //             );
+    let tri_table = Arc::new(tri_table);
 
-    //             // creates a model
-    //             let model = loader.load_to_vao(chunk.vertices, chunk.indices);
+    // creates a chunk manager for the configured world
+    let mut chunk_manager = chunk_manager::ChunkManager::new(options.world_config, tri_table);
+    chunk_manager.set_view_distance(options.view_distance);
+    chunk_manager.set_unload_margin(options.unload_margin);
+    chunk_manager.set_lod_levels(options.lod_levels);
+    chunk_manager.set_cache_byte_budget(options.cache_bytes);
+    chunk_manager.set_edit_history_byte_budget(options.history_bytes);
+    chunk_manager.set_occlusion_culling(options.occlusion_culling);
+    chunk_manager.set_budget(options.budget);
 
-    //             // adds the model to the model vector
-    //             model_vector.push(model);
-    //         }
-    //     }
-    // }
+    // loads the saved world before any chunks are built
+    let saves_world = options.world_directory.is_some();
 
-    // creates a chunk manager
-    let mut chunk_manager = chunk_manager::ChunkManager::new();
+    if let Some(directory) = &options.world_directory {
+        if let Err(error) = chunk_manager.open_world(Path::new(directory)) {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    }
 
     // creates an fps counter
     let mut tick_counter = fps::FPSLimiter::new();
@@ -65,7 +225,7 @@ fn main() {
     // runs the event loop
     event_loop.run(move |event, _, control_flow| {
         match event {
-            Event::LoopDestroyed => return,
+            Event::LoopDestroyed => (),
             Event::WindowEvent { event, .. } => match event {
                 WindowEvent::Resized(physical_size) => {
                     // resizes the viewport
@@ -79,6 +239,10 @@ fn main() {
                     }
                 },
                 WindowEvent::CloseRequested => {
+                    if saves_world {
+                        save_world(&mut chunk_manager);
+                    }
+
                     *control_flow = ControlFlow::Exit;
                     renderer.clean_up();
                 },
@@ -90,7 +254,48 @@ fn main() {
                             ..
                         },
                     ..
-                } => input_manager.register_key_press(virtual_code),
+                } => {
+                    // steps the view distance in and out
+                    match virtual_code {
+                        VirtualKeyCode::Equals | VirtualKeyCode::NumpadAdd => {
+                            chunk_manager.set_view_distance(chunk_manager.view_distance() + 1);
+                        },
+                        VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract => {
+                            chunk_manager.set_view_distance(chunk_manager.view_distance() - 1);
+                        },
+                        _ => ()
+                    }
+
+                    // sculpts with a sphere in front of the camera
+                    let sculpt_mode = match virtual_code {
+                        VirtualKeyCode::Q => Some(voxel_edits::BrushMode::Subtract),
+                        VirtualKeyCode::E => Some(voxel_edits::BrushMode::Add),
+                        VirtualKeyCode::R => Some(voxel_edits::BrushMode::Smooth),
+                        _ => None
+                    };
+
+                    if let Some(mode) = sculpt_mode {
+                        let shape = voxel_edits::BrushShape::Sphere {
+                            centre: camera.position + camera.forward() * constants::SCULPT_REACH,
+                            radius: constants::SCULPT_RADIUS
+                        };
+
+                        let outcome = chunk_manager.apply_brush(&voxel_edits::Brush::new(shape, mode));
+
+                        if !outcome.undoable {
+                            println!("the edit is too big for the undo history, so it and the edits before it can't be undone");
+                        }
+                    }
+
+                    match virtual_code {
+                        VirtualKeyCode::Z => { chunk_manager.undo(); },
+                        VirtualKeyCode::Y => { chunk_manager.redo(); },
+                        VirtualKeyCode::F5 if saves_world => save_world(&mut chunk_manager),
+                        _ => ()
+                    }
+
+                    input_manager.register_key_press(virtual_code)
+                },
                 WindowEvent::KeyboardInput {
                     input:
                         KeyboardInput {
@@ -110,35 +315,56 @@ fn main() {
                 // gets the current delta time
                 let delta_time = tick_counter.delta_time();
 
+                // runs the camera
+                camera.update(&mut input_manager, &delta_time);
+
+                // generates chunks
+                chunk_manager.update_chunks(&camera, &mut loader);
+
+                // saved chunks that couldn't be read are generated again instead
+                for error in chunk_manager.take_region_errors() {
+                    eprintln!("{}", error);
+                }
+
+                let models = chunk_manager.models();
+
+                // keeps the far plane and fog at the edge of the loaded chunks
+                renderer.set_view_distance(chunk_manager.view_distance_world());
+
+                // runs the renderer
+                let render_stats = renderer.render(&models, &mut camera);
+
                 // gets the fps count and init the number of polys in the models
                 let fps_count = tick_counter.fps().floor();
                 let mut poly_count: u32 = 0;
 
                 // loops over the models to add to the poly count
-                for model in &mut model_vector {
+                for model in models.iter() {
                     let model_poly_count = model.get_vertex_count()/3;
-                    poly_count = poly_count + (model_poly_count as u32);
+                    poly_count += model_poly_count as u32;
                 }
 
-                // sets the title string
+                let cache_stats = chunk_manager.cache_stats();
+
+                // sets the title string, the live counts show GPU memory being freed
                 let title_string = format!(
-                    "Terrain Test | FPS: {} | Polygons: {}",
+                    "Terrain Test | FPS: {} | View Distance: {} | Polygons: {} | Chunks Drawn: {} | Culled: {} | Occluded: {} | Pending: {} | Cache Hits: {} | Misses: {} | VAOs: {} | Buffers: {}",
                     fps_count,
-                    poly_count
+                    chunk_manager.view_distance(),
+                    poly_count,
+                    render_stats.drawn,
+                    render_stats.culled,
+                    chunk_manager.occluded_count(),
+                    chunk_manager.pending_count(),
+                    cache_stats.hits,
+                    cache_stats.misses,
+                    loader.live_vao_count(),
+                    loader.live_buffer_count()
                 );
 
                 // updates the title of the screen
                 context.window().set_title(&title_string);
 
-                // runs the camera
-                camera.update(&mut input_manager, &delta_time);
-
-                // generates chunks
-                model_vector = chunk_manager.generate_chunk_models(&camera, &mut loader, &tri_table);
-
-                // runs the renderer
-                renderer.render(&mut model_vector, &mut camera);
-
                 // swaps images on the swap chain
                 context.swap_buffers().unwrap();
 
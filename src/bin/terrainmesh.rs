@@ -0,0 +1,330 @@
+use terrainbasic::{
+    entities::*,
+    utils::*
+};
+
+use cgmath::Vector3;
+
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "usage: terrainmesh [options]
+
+builds the meshes of a box of chunks without opening a window, printing the
+triangles, vertices and time taken for each chunk and for the whole box
+
+region options:
+    --min <x,y,z>                first chunk of the box, counted in chunks (default 0,0,0)
+    --max <x,y,z>                last chunk of the box, included (default 3,3,3)
+    --box-min <x,y,z>            builds the chunks a world space box touches instead,
+    --box-max <x,y,z>            and only exports the surface inside it
+    --lod <u32>                  level of detail the chunks are built at, each level
+                                 making chunks twice as wide with the same samples
+
+output options:
+    --out <path>                 writes each chunk's mesh to its own file in a
+                                 directory, made if it doesn't exist
+    --format <format>            obj, ply or glb, the format of the files written
+                                 with --out (default obj)
+    --export <path>              welds every chunk into one mesh and writes it to a
+                                 .obj, .ply or .glb file
+    --colours                    gives exported vertices the demo's colours
+    --quiet                      only prints the totals
+
+mesh options:
+    --triangulation-table <path> reads the marching cubes cases from a file instead of
+                                 the ones built in
+";
+
+// settings read from the command line
+struct Options {
+    world_config: world_config::WorldConfig,
+    min_chunk: Vector3<i32>,
+    max_chunk: Vector3<i32>,
+    world_box: Option<(Vector3<f32>, Vector3<f32>)>,
+    lod: u32,
+    output_directory: Option<PathBuf>,
+    output_format: mesh_export::ExportFormat,
+    export_path: Option<PathBuf>,
+    colours: bool,
+    quiet: bool,
+    triangulation_table: Option<String>
+}
+
+// what building a single chunk produced
+struct ChunkStats {
+    triangles: usize,
+    vertices: usize,
+    time: Duration
+}
+
+fn parse_options<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut options = Options {
+        world_config: world_config::WorldConfig::new(),
+        min_chunk: Vector3::new(0, 0, 0),
+        max_chunk: Vector3::new(3, 3, 3),
+        world_box: None,
+        lod: 0,
+        output_directory: None,
+        output_format: mesh_export::ExportFormat::Obj,
+        export_path: None,
+        colours: false,
+        quiet: false,
+        triangulation_table: None
+    };
+
+    let mut box_min = None;
+    let mut box_max = None;
+
+    while let Some(flag) = args.next() {
+        // world options, like the seed, chunk bounds and isovalue, are handled
+        // by the config itself
+        if options.world_config.apply_flag(&flag, &mut args)? {
+            continue;
+        }
+
+        match flag.as_str() {
+            "--min" => options.min_chunk = parse_vector(&flag, &mut args)?,
+            "--max" => options.max_chunk = parse_vector(&flag, &mut args)?,
+            "--box-min" => box_min = Some(parse_vector(&flag, &mut args)?),
+            "--box-max" => box_max = Some(parse_vector(&flag, &mut args)?),
+            "--lod" => options.lod = parse_value(&flag, &mut args)?,
+            "--out" => options.output_directory = Some(parse_value(&flag, &mut args)?),
+            "--format" => options.output_format = mesh_export::ExportFormat::from_name(&next_value(&flag, &mut args)?)?,
+            "--export" => options.export_path = Some(parse_value(&flag, &mut args)?),
+            "--colours" => options.colours = true,
+            "--quiet" => options.quiet = true,
+            "--triangulation-table" => options.triangulation_table = Some(parse_value(&flag, &mut args)?),
+            _ => return Err(format!("unknown option '{}'", flag))
+        }
+    }
+
+    // the box decides which chunks are built, which are always full detail
+    match (box_min, box_max) {
+        (Some(min_corner), Some(max_corner)) => {
+            if options.lod != 0 {
+                return Err(String::from("a world space box is always built at full detail"));
+            }
+
+            let (min_chunk, max_chunk) = mesh_export::box_chunks(&options.world_config, min_corner, max_corner);
+
+            options.world_box = Some((min_corner, max_corner));
+            options.min_chunk = min_chunk;
+            options.max_chunk = max_chunk;
+        }
+        (None, None) => (),
+        _ => return Err(String::from("--box-min and --box-max have to be given together"))
+    }
+
+    if let Some(path) = &options.export_path {
+        mesh_export::ExportFormat::from_path(path)?;
+    }
+
+    if options.lod >= constants::MAX_LOD_LEVELS {
+        return Err(format!("level of detail must be less than {}, got {}", constants::MAX_LOD_LEVELS, options.lod));
+    }
+
+    for axis in 0..3 {
+        if options.min_chunk[axis] > options.max_chunk[axis] {
+            return Err(String::from("every coordinate of the box's minimum must be at most the same coordinate of its maximum"));
+        }
+    }
+
+    Ok(options)
+}
+
+// gets the value that follows a flag
+fn next_value<I: Iterator<Item = String>>(flag: &str, args: &mut I) -> Result<String, String> {
+    args.next().ok_or(format!("option '{}' needs a value", flag))
+}
+
+// gets and parses the value that follows a flag
+fn parse_value<T: FromStr, I: Iterator<Item = String>>(flag: &str, args: &mut I) -> Result<T, String> {
+    let value = next_value(flag, args)?;
+
+    value.parse::<T>().map_err(|_| format!("invalid value '{}' for option '{}'", value, flag))
+}
+
+// gets and parses the coordinates that follow a flag, written as x,y,z
+fn parse_vector<T: FromStr + Copy, I: Iterator<Item = String>>(flag: &str, args: &mut I) -> Result<Vector3<T>, String> {
+    let value: String = parse_value(flag, args)?;
+
+    let coords = value.split(',')
+        .map(|coord| coord.trim().parse::<T>())
+        .collect::<Result<Vec<T>, _>>()
+        .map_err(|_| format!("invalid value '{}' for option '{}', expected x,y,z", value, flag))?;
+
+    match coords.as_slice() {
+        [x, y, z] => Ok(Vector3::new(*x, *y, *z)),
+        _ => Err(format!("invalid value '{}' for option '{}', expected x,y,z", value, flag))
+    }
+}
+
+// welds chunks into one mesh, clipped to the world space box if there is one
+fn export_mesh(options: &Options, chunks: &[&basic_marching_cubes::MCChunk]) -> mesh_export::ExportMesh {
+    let mut mesh = match options.world_box {
+        Some((min_corner, max_corner)) => mesh_export::ExportMesh::from_chunks_in_box(chunks, min_corner, max_corner),
+        None => mesh_export::ExportMesh::from_chunks(chunks)
+    };
+
+    if options.colours {
+        mesh.colour_by_position();
+    }
+
+    mesh
+}
+
+fn millis(time: Duration) -> f64 {
+    time.as_secs_f64() * 1000.0
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        println!("{}", world_config::WORLD_CONFIG_USAGE);
+        return;
+    }
+
+    let options = match parse_options(args.into_iter()) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}\n\n{}\n{}", error, USAGE, world_config::WORLD_CONFIG_USAGE);
+            std::process::exit(1);
+        }
+    };
+
+    // gets the triangulation table the same way the demo does
+    let mut tri_table = match &options.triangulation_table {
+        Some(path) => match table_reader::TriangulationTable::from_file(Path::new(path)) {
+            Ok(tri_table) => tri_table,
+            Err(error) => {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+        },
+        None => table_reader::TriangulationTable::new()
+    };
+
+    if options.world_config.mesher_type == world_config::MesherType::MarchingCubes33 {
+        tri_table.load_extended();
+    }
+
+    if let Some(directory) = &options.output_directory {
+        if let Err(error) = std::fs::create_dir_all(directory) {
+            eprintln!("couldn't create '{}': {}", directory.display(), error);
+            std::process::exit(1);
+        }
+    }
+
+    let density = options.world_config.create_density_field();
+    let mesher = options.world_config.create_mesher(Arc::new(tri_table));
+
+    let mut total_triangles = 0;
+    let mut total_vertices = 0;
+    let mut total_time = Duration::new(0, 0);
+    let mut chunk_count = 0;
+    let mut slowest: Option<(Vector3<i32>, Duration)> = None;
+
+    // chunks are only kept when they're welded together at the end
+    let mut built_chunks = Vec::new();
+
+    let start = Instant::now();
+
+    for x in options.min_chunk.x..=options.max_chunk.x {
+        for y in options.min_chunk.y..=options.max_chunk.y {
+            for z in options.min_chunk.z..=options.max_chunk.z {
+                let offset = Vector3::new(x, y, z);
+
+                // times sampling and meshing together, as the demo's workers do both
+                let chunk_start = Instant::now();
+
+                let chunk = basic_marching_cubes::MCChunk::new(
+                    chunk_lod::ChunkKey::new(options.lod, offset),
+                    &options.world_config,
+                    density.as_ref(),
+                    mesher.as_ref()
+                );
+
+                let stats = ChunkStats {
+                    triangles: chunk.indices.len() / 3,
+                    vertices: chunk.vertices.len() / 3,
+                    time: chunk_start.elapsed()
+                };
+
+                if !options.quiet {
+                    println!(
+                        "chunk {:>4} {:>4} {:>4} | triangles {:>7} | vertices {:>7} | {:>8.3} ms",
+                        x, y, z,
+                        stats.triangles,
+                        stats.vertices,
+                        millis(stats.time)
+                    );
+                }
+
+                // chunks with nothing in them don't get a file
+                if let Some(directory) = &options.output_directory {
+                    let mesh = export_mesh(&options, &[&chunk]);
+
+                    if !mesh.indices.is_empty() {
+                        let path = directory.join(format!("chunk.{}.{}.{}.{}", x, y, z, options.output_format.extension()));
+
+                        if let Err(error) = mesh.write(&path, options.output_format) {
+                            eprintln!("{}", error);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+
+                if slowest.is_none_or(|(_, time)| stats.time > time) {
+                    slowest = Some((offset, stats.time));
+                }
+
+                total_triangles += stats.triangles;
+                total_vertices += stats.vertices;
+                total_time += stats.time;
+                chunk_count += 1;
+
+                if options.export_path.is_some() {
+                    built_chunks.push(chunk);
+                }
+            }
+        }
+    }
+
+    println!(
+        "{} chunks | triangles {} | vertices {} | building {:.3} ms, {:.3} ms per chunk | total {:.3} ms",
+        chunk_count,
+        total_triangles,
+        total_vertices,
+        millis(total_time),
+        millis(total_time) / chunk_count as f64,
+        millis(start.elapsed())
+    );
+
+    if let Some((offset, time)) = slowest {
+        println!("slowest chunk {} {} {} took {:.3} ms", offset.x, offset.y, offset.z, millis(time));
+    }
+
+    if let Some(path) = &options.export_path {
+        let chunks: Vec<&basic_marching_cubes::MCChunk> = built_chunks.iter().collect();
+        let mesh = export_mesh(&options, &chunks);
+
+        let format = mesh_export::ExportFormat::from_path(path).unwrap();
+
+        if let Err(error) = mesh.write(path, format) {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+
+        println!(
+            "exported {} triangles and {} welded vertices to '{}'",
+            mesh.triangle_count(),
+            mesh.vertex_count(),
+            path.display()
+        );
+    }
+}
@@ -1,210 +1,904 @@
 
 // generates for basic marching cubes
-use core::f64;
+use std::collections::HashMap;
+use std::sync::Arc;
 
 use cgmath::*;
 
-use ndarray::{Array3};
-
-use noise::{NoiseFn, Worley};
-
 use crate::{
-    entities::table_reader::TriangulationTable,
-    utils::constants::*
+    entities::{
+        chunk_lod::ChunkKey,
+        density_field::DensityField,
+        mesher::{Mask, Mesh, Mesher, SampledGrid},
+        occlusion::FaceConnectivity,
+        table_reader::{TriangulationTable, CUBE_CENTRE, CUBE_CORNERS, CUBE_EDGES, CUBE_FACES},
+        transition_cells::{TransitionSpace, TransitionStrip}
+    },
+    utils::{
+        chunk_space,
+        world_config::WorldConfig
+    }
 };
 
 //----------------------
 
-#[derive(Clone, Debug, Copy)]
-enum Mask {
-    MINUS = 0,
-    PLUS = 1
+// a border vertex that doesn't line up with the neighbouring chunk
+#[derive(Clone, Debug, PartialEq)]
+pub struct BorderCrack {
+    pub position: Vector3<f32>,
+    pub kind: BorderCrackKind
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BorderCrackKind {
+    MissingFromNeighbour,
+    MissingFromChunk,
+    NormalMismatch
 }
 
+// the classic marching cubes surface, using the triangulation table for the
+// triangles of each cube
+pub struct MarchingCubesMesher {
+    tri_table: Arc<TriangulationTable>,
+
+    // whether to use the marching cubes 33 cases, which decide ambiguous faces
+    // and the inside of the cube from the densities so the surface always
+    // follows the topology of the trilinear interpolation
+    use_mc33: bool
+}
+
+//----------------------
+
+// what a chunk keeps of its densities. a chunk with every sample, padding
+// included, on one side of the isovalue has no surface to mesh, so it only
+// keeps which side that is and how far past its border that was checked
+#[derive(Clone)]
+pub enum ChunkSamples {
+    Uniform { mask: Mask, padding: usize },
+    Mixed(SampledGrid)
+}
+
+// a chunk of the world, holding its samples and the mesh made from them by
+// whichever mesher the world is configured with
 pub struct MCChunk {
     pub vertices: Vec<f32>,
+    pub normals: Vec<f32>,
     pub indices: Vec<u32>,
 
-    grid: Array3<Mask>,
-    grid_weights: Array3<f32>,
+    samples: ChunkSamples,
+
+    // which faces can see each other through the chunk's air
+    connectivity: FaceConnectivity,
 
-    offset: Vector3<i32>,
+    // the transition cells along each face, in the order of the occlusion
+    // face directions, joining the mesh to finer chunks across that face.
+    // full detail chunks have none
+    transition_strips: Vec<TransitionStrip>,
 
-    spacing_per_vertex: f32,
+    key: ChunkKey,
     chunk_bounds: usize,
-    isovalue: f32,
+    spacing_per_vertex: f32
 }
 
 //----------------------
 
+impl ChunkSamples {
+    // gets how many samples past the chunk border were kept or checked
+    pub fn padding(&self) -> usize {
+        match self {
+            ChunkSamples::Uniform { padding, .. } => *padding,
+            ChunkSamples::Mixed(grid) => grid.padding()
+        }
+    }
+}
+
 impl MCChunk {
-    // generates a new MCChunk
+    // generates a new MCChunk, spreading its samples out for coarser levels of detail
     pub fn new(
-        offset: Vector3<i32>,
-        tri_table: &TriangulationTable
+        key: ChunkKey,
+        world_config: &WorldConfig,
+        density: &dyn DensityField,
+        mesher: &dyn Mesher
     ) -> MCChunk {
-        // returns a chunk with empty vertices and defaults
-        let mut mc_chunk = MCChunk {
+        // samples the density field with as much padding as the mesher needs
+        let grid = SampledGrid::sample(
+            key.offset,
+            world_config.chunk_bounds,
+            key.spacing(world_config.spacing_per_vertex),
+            world_config.isovalue,
+            mesher.padding(),
+            density
+        );
+
+        MCChunk::from_grid(key.lod, grid, mesher).with_transition_strips(world_config, density, mesher)
+    }
+
+    // meshes a chunk from densities that have already been sampled, which
+    // needs at least as much padding as the mesher reads
+    pub fn from_grid(lod: u32, grid: SampledGrid, mesher: &dyn Mesher) -> MCChunk {
+        assert!(grid.padding() >= mesher.padding(), "grid is padded less than the mesher needs");
+
+        let key = ChunkKey::new(lod, grid.offset);
+        let chunk_bounds = grid.chunk_bounds;
+        let spacing_per_vertex = grid.spacing_per_vertex;
+
+        // skips meshing chunks that can't have a surface in them
+        if let Some(mask) = grid.uniform_mask() {
+            return MCChunk::uniform(key, chunk_bounds, spacing_per_vertex, mask, grid.padding());
+        }
+
+        // works out which faces are joined by air for occlusion culling
+        let connectivity = FaceConnectivity::from_air(chunk_bounds, |x, y, z| {
+            !grid.is_solid(x as i32, y as i32, z as i32)
+        });
+
+        // generates vertices for this particular chunk
+        let mesh = mesher.mesh(&grid);
+
+        MCChunk {
+            vertices: mesh.vertices,
+            normals: mesh.normals,
+            indices: mesh.indices,
+
+            samples: ChunkSamples::Mixed(grid),
+
+            connectivity,
+
+            transition_strips: Vec::new(),
+
+            key,
+            chunk_bounds,
+            spacing_per_vertex
+        }
+    }
+
+    // builds a chunk from the samples kept by another chunk, such as ones
+    // saved to disk, which need at least as much padding as the mesher reads.
+    // the density field is still sampled for the transition cells
+    pub fn from_samples(
+        key: ChunkKey,
+        world_config: &WorldConfig,
+        samples: ChunkSamples,
+        density: &dyn DensityField,
+        mesher: &dyn Mesher
+    ) -> MCChunk {
+        let chunk = match samples {
+            ChunkSamples::Uniform { mask, padding } => {
+                assert!(padding >= mesher.padding(), "samples are padded less than the mesher needs");
+
+                MCChunk::uniform(
+                    key,
+                    world_config.chunk_bounds,
+                    key.spacing(world_config.spacing_per_vertex),
+                    mask,
+                    padding
+                )
+            }
+            ChunkSamples::Mixed(grid) => MCChunk::from_grid(key.lod, grid, mesher)
+        };
+
+        chunk.with_transition_strips(world_config, density, mesher)
+    }
+
+    // builds a chunk that's all air or all rock, which has no mesh of its own
+    fn uniform(
+        key: ChunkKey,
+        chunk_bounds: usize,
+        spacing_per_vertex: f32,
+        mask: Mask,
+        padding: usize
+    ) -> MCChunk {
+        MCChunk {
             vertices: Vec::new(),
+            normals: Vec::new(),
             indices: Vec::new(),
 
-            grid: Array3::<Mask>::from_elem((CHUNK_BOUNDS, CHUNK_BOUNDS, CHUNK_BOUNDS), Mask::MINUS),
-            grid_weights: Array3::<f32>::zeros((CHUNK_BOUNDS, CHUNK_BOUNDS, CHUNK_BOUNDS)),
+            samples: ChunkSamples::Uniform { mask, padding },
 
-            offset,
+            connectivity: if mask == Mask::Minus { FaceConnectivity::open() } else { FaceConnectivity::closed() },
 
-            spacing_per_vertex: SPACING_PER_VERTEX,
-            chunk_bounds: CHUNK_BOUNDS,
-            isovalue: TEST_ISOVALUE
-        };
+            transition_strips: Vec::new(),
 
-        // labels a set of isovertices using a sampler
-        mc_chunk.label_initial_vertices();
+            key,
+            chunk_bounds,
+            spacing_per_vertex
+        }
+    }
 
-        // generates vertices for this particular chunk
-        mc_chunk.generate_vertices(tri_table);
+    //--------------------------
 
-        mc_chunk
+    // adds the transition cells joining a coarser chunk to the level below it,
+    // which only works when the mesh ends on the chunk faces. uniform chunks
+    // get them too, since the finer level can still have a surface on the faces
+    fn with_transition_strips(mut self, world_config: &WorldConfig, density: &dyn DensityField, mesher: &dyn Mesher) -> MCChunk {
+        if self.key.lod > 0 && mesher.meshes_to_chunk_faces() {
+            self.transition_strips = TransitionStrip::build_all(self.key, world_config, &self.samples, density, mesher);
+        }
+
+        self
     }
 
     //--------------------------
 
-    // generates densities for the first vertices
-    fn label_initial_vertices(&mut self) {
-        // temp perlin sampler
-        let perlin = Worley::new();
-
-        // calculates an offset for the perlin based on the chunk
-        // spacing and the space between vertices
-        // (ugly f64 conversion, not sure if dirty code?)
-        let perlin_offset = Vector3::new(
-            self.offset.x as f64 * self.chunk_bounds as f64 - self.offset.x as f64,
-            self.offset.y as f64 * self.chunk_bounds as f64 - self.offset.y as f64,
-            self.offset.z as f64 * self.chunk_bounds as f64 - self.offset.z as f64,
-        );
+    // gets the offset of the chunk in chunk space, counted in chunks of its own size
+    pub fn offset(&self) -> Vector3<i32> {
+        self.key.offset
+    }
 
-        // iterates over the data to assign noise values
-        for x in 0..CHUNK_BOUNDS {
-            for y in 0..CHUNK_BOUNDS {
-                for z in 0..CHUNK_BOUNDS {
-                    // gets the perlin value for the point
-                    let perlin_value = perlin.get([
-                        (perlin_offset.x + x as f64)/CHUNK_BOUNDS as f64,
-                        (perlin_offset.y + y as f64)/CHUNK_BOUNDS as f64,
-                        (perlin_offset.z + z as f64)/CHUNK_BOUNDS as f64
-                    ]);
-
-                    // sets the weighted grid
-                    self.grid_weights[[x, y, z]] = perlin_value as f32;
-
-                    // if the perlin value is over the isovalue, set the mask
-                    // to PLUS, else to minus
-                    if perlin_value as f32 >= self.isovalue {
-                        self.grid[[x, y, z]] = Mask::PLUS;
-                    }
-                    else {
-                        self.grid[[x, y, z]] = Mask::MINUS;
+    pub fn key(&self) -> ChunkKey {
+        self.key
+    }
+
+    pub fn samples(&self) -> &ChunkSamples {
+        &self.samples
+    }
+
+    // gets the densities the chunk was meshed from, which uniform chunks don't keep
+    pub fn grid(&self) -> Option<&SampledGrid> {
+        match &self.samples {
+            ChunkSamples::Mixed(grid) => Some(grid),
+            ChunkSamples::Uniform { .. } => None
+        }
+    }
+
+    // gets whether the chunk is all air or all rock
+    pub fn is_uniform(&self) -> bool {
+        self.grid().is_none()
+    }
+
+    pub fn connectivity(&self) -> FaceConnectivity {
+        self.connectivity
+    }
+
+    // gets the chunk's mesh joined to the finer chunks across the faces in the
+    // mask, as vertices, normals and indices. the mesh is pulled back from
+    // those faces and the transition cells fill the gap
+    pub fn mesh_with_transitions(&self, transition_mask: u8) -> (Vec<f32>, Vec<f32>, Vec<u32>) {
+        let mut vertices = self.vertices.clone();
+        let mut normals = self.normals.clone();
+        let mut indices = self.indices.clone();
+
+        let space = TransitionSpace::new(self.key.offset, self.chunk_bounds, self.spacing_per_vertex, transition_mask);
+
+        let pull_back = |vertices: &mut [f32]| {
+            for vertex in vertices.chunks_mut(3) {
+                let position = space.pull_back(Vector3::new(vertex[0], vertex[1], vertex[2]));
+                vertex.copy_from_slice(&[position.x, position.y, position.z]);
+            }
+        };
+
+        pull_back(&mut vertices);
+
+        for (face, strip) in self.transition_strips.iter().enumerate() {
+            if transition_mask & (1 << face) == 0 {
+                continue;
+            }
+
+            let first_index = (vertices.len() / 3) as u32;
+            let inner_start = vertices.len() + strip.face_vertex_count * 3;
+
+            // the vertices on the face line up with the finer chunks, so only
+            // the ones from the chunk's own samples are pulled back
+            vertices.extend_from_slice(&strip.mesh.vertices);
+            pull_back(&mut vertices[inner_start..]);
+
+            normals.extend_from_slice(&strip.mesh.normals);
+
+            // vertices of the chunk's own on the edge of a face without
+            // transitions aren't pulled back, so they can land on a face
+            // vertex, leaving a triangle with nothing to draw
+            for triangle in strip.mesh.indices.chunks(3) {
+                let position = |index: u32| &vertices[(first_index + index) as usize * 3..(first_index + index) as usize * 3 + 3];
+
+                if (0..3).any(|corner| position(triangle[corner]) == position(triangle[(corner + 1) % 3])) {
+                    continue;
+                }
+
+                indices.extend(triangle.iter().map(|index| index + first_index));
+            }
+        }
+
+        (vertices, normals, indices)
+    }
+
+    // gets roughly how many bytes the chunk's densities and mesh take up
+    pub fn memory_size(&self) -> usize {
+        std::mem::size_of::<MCChunk>() +
+            std::mem::size_of_val(self.vertices.as_slice()) +
+            std::mem::size_of_val(self.normals.as_slice()) +
+            std::mem::size_of_val(self.indices.as_slice()) +
+            self.grid().map_or(0, |grid| grid.memory_size()) +
+            self.transition_strips.iter().map(|strip| strip.memory_size()).sum::<usize>()
+    }
+
+    // checks the vertices on the face shared with a neighbouring chunk, returning
+    // every vertex that doesn't have a bit for bit match on the other side
+    pub fn find_border_cracks(&self, neighbour: &MCChunk) -> Result<Vec<BorderCrack>, String> {
+        // the chunks have to be sampled the same way to share a border
+        if self.chunk_bounds != neighbour.chunk_bounds || self.spacing_per_vertex != neighbour.spacing_per_vertex {
+            return Err(String::from("chunks were generated with different bounds, spacing or levels of detail"));
+        }
+
+        // gets the single axis the chunks are stepped along
+        let step = neighbour.offset() - self.offset();
+        let step_sum = step.x.abs() + step.y.abs() + step.z.abs();
+
+        if step_sum != 1 {
+            return Err(format!(
+                "chunks at {:?} and {:?} aren't face neighbours",
+                self.offset(),
+                neighbour.offset()
+            ));
+        }
+
+        let axis = if step.x != 0 { 0 } else if step.y != 0 { 1 } else { 2 };
+
+        // the shared face sits at the start of whichever chunk is further along the axis
+        let upper_offset = if step[axis] > 0 { neighbour.offset() } else { self.offset() };
+        let plane_sample = chunk_space::chunk_origin_sample(upper_offset, self.chunk_bounds)[axis];
+        let plane = plane_sample as f32 * self.spacing_per_vertex;
+
+        // gets the vertices lying on the face from both sides
+        let chunk_border = self.border_vertices(axis, plane);
+        let neighbour_border = neighbour.border_vertices(axis, plane);
+
+        let mut cracks = Vec::new();
+
+        for (position, normal) in chunk_border.iter() {
+            match neighbour_border.get(position) {
+                Some(neighbour_normal) => {
+                    if neighbour_normal != normal {
+                        cracks.push(BorderCrack {
+                            position: MCChunk::vector_from_bits(position),
+                            kind: BorderCrackKind::NormalMismatch
+                        });
                     }
                 }
+                None => {
+                    cracks.push(BorderCrack {
+                        position: MCChunk::vector_from_bits(position),
+                        kind: BorderCrackKind::MissingFromNeighbour
+                    });
+                }
+            }
+        }
+
+        for position in neighbour_border.keys() {
+            if !chunk_border.contains_key(position) {
+                cracks.push(BorderCrack {
+                    position: MCChunk::vector_from_bits(position),
+                    kind: BorderCrackKind::MissingFromChunk
+                });
             }
         }
+
+        Ok(cracks)
     }
 
-    // generates vertices from the labelled vertices
-    fn generate_vertices(&mut self, tri_table: &TriangulationTable) {
+    // collects the bits of every vertex and normal lying exactly on an axis plane
+    fn border_vertices(&self, axis: usize, plane: f32) -> HashMap<[u32; 3], [u32; 3]> {
+        let mut border = HashMap::new();
 
-        // creates tables for vertices and indices
-        let mut vertices_table = Vec::<f32>::new();
-        let mut indices_table = Vec::<u32>::new();
+        for (vertex, normal) in self.vertices.chunks(3).zip(self.normals.chunks(3)) {
+            if vertex[axis].to_bits() == plane.to_bits() {
+                border.insert(
+                    [vertex[0].to_bits(), vertex[1].to_bits(), vertex[2].to_bits()],
+                    [normal[0].to_bits(), normal[1].to_bits(), normal[2].to_bits()]
+                );
+            }
+        }
 
-        // generates a full offset for the position of the cubes
-        let full_offset = Vector3::new(
-            (self.offset.x as f32 * self.chunk_bounds as f32 * self.spacing_per_vertex) - (self.offset.x as f32 * self.spacing_per_vertex),
-            (self.offset.y as f32 * self.chunk_bounds as f32 * self.spacing_per_vertex) - (self.offset.y as f32 * self.spacing_per_vertex),
-            (self.offset.z as f32 * self.chunk_bounds as f32 * self.spacing_per_vertex) - (self.offset.z as f32 * self.spacing_per_vertex)
-        );
+        border
+    }
+
+    fn vector_from_bits(bits: &[u32; 3]) -> Vector3<f32> {
+        Vector3::new(f32::from_bits(bits[0]), f32::from_bits(bits[1]), f32::from_bits(bits[2]))
+    }
+}
 
-        // temporary indexing thing 
-        let mut index: u32 = 0;
+//----------------------
+
+impl MarchingCubesMesher {
+    pub fn new(tri_table: Arc<TriangulationTable>) -> MarchingCubesMesher {
+        MarchingCubesMesher {
+            tri_table,
+            use_mc33: false
+        }
+    }
+
+    // creates a mesher using the marching cubes 33 cases, which have to have
+    // been loaded into the table
+    pub fn with_mc33(tri_table: Arc<TriangulationTable>) -> MarchingCubesMesher {
+        assert!(tri_table.has_extended(), "the marching cubes 33 table hasn't been loaded");
+
+        MarchingCubesMesher {
+            tri_table,
+            use_mc33: true
+        }
+    }
+
+    // picks the marching cubes 33 triangles for a cube. ambiguous faces go by
+    // the asymptotic decider, which only looks at the face so neighbouring
+    // cubes always agree, then any tunnel the case allows is checked for
+    fn mc33_triangles(&self, grid: &SampledGrid, cube: Vector3<i32>, cube_config: u8) -> &[u16] {
+        let values = CUBE_CORNERS.map(|corner| {
+            grid.weight(cube.x + corner[0], cube.y + corner[1], cube.z + corner[2]) - grid.isovalue
+        });
+
+        let mut face_bits = 0;
+
+        for (face, corners) in CUBE_FACES.iter().enumerate() {
+            let solid = corners.map(|corner| values[corner] >= 0.0);
+
+            if solid[0] != solid[2] || solid[1] != solid[3] || solid[0] == solid[1] {
+                continue;
+            }
+
+            if joined_across_face(&values, face) {
+                face_bits |= 1 << face;
+            }
+        }
+
+        // the table is checked for every case when it's loaded
+        let case = self.tri_table.extended_table.get(&(cube_config, face_bits)).unwrap();
+
+        for tunnel in case.tunnels.iter() {
+            if joined_through_cube(&values, tunnel.corners_a, tunnel.corners_b) {
+                return &tunnel.triangles;
+            }
+        }
+
+        &case.triangles
+    }
+
+    // gets the index of the vertex where the surface crosses one of a cube's
+    // edges, making it the first time the edge is crossed
+    fn edge_vertex(
+        &self,
+        grid: &SampledGrid,
+        mesh: &mut Mesh,
+        edge_cache: &mut HashMap<(usize, usize, usize, usize), u32>,
+        (x, y, z): (usize, usize, usize),
+        vert: u16
+    ) -> u32 {
+        // based on the indices, gets the corner configurations associated
+        // with the vert
+        let corner_a = *self.tri_table.corner_index_a_from_edge.get(vert as usize).unwrap();
+        let corner_b = *self.tri_table.corner_index_b_from_edge.get(vert as usize).unwrap();
+
+        // orders the corners so the edge is always walked from its lower
+        // corner, which keeps the interpolation identical for every cube
+        // that shares the edge
+        let (corner_low, corner_high) = if corner_a.sum() <= corner_b.sum() {
+            (corner_a, corner_b)
+        }
+        else {
+            (corner_b, corner_a)
+        };
+
+        // gets the grid position of the lower corner and the axis of the edge
+        let low_x = x + corner_low.x as usize;
+        let low_y = y + corner_low.y as usize;
+        let low_z = z + corner_low.z as usize;
+
+        let edge_axis = if corner_low.x != corner_high.x {
+            0
+        }
+        else if corner_low.y != corner_high.y {
+            1
+        }
+        else {
+            2
+        };
+
+        let edge_key = (low_x, low_y, low_z, edge_axis);
+
+        // reuses the vertex if the edge has already been crossed
+        if let Some(index) = edge_cache.get(&edge_key) {
+            return *index;
+        }
+
+        // gets the point the surface crosses the edge, with the gradient
+        // interpolated the same way for the normal
+        let low = Vector3::new(low_x as i32, low_y as i32, low_z as i32);
+
+        let (interp_value, vertex_point) = grid.edge_crossing(low, edge_axis);
+        let normal = grid.edge_normal(low, edge_axis, interp_value);
+
+        // pushes the vertex and caches it for the edge
+        let index = mesh.push_vertex(vertex_point, normal);
+        edge_cache.insert(edge_key, index);
+
+        index
+    }
+
+    // makes the vertex some marching cubes 33 cases put inside the cube, at
+    // the average of the edge crossings it's joined to
+    fn centre_vertex(
+        &self,
+        grid: &SampledGrid,
+        mesh: &mut Mesh,
+        edge_cache: &mut HashMap<(usize, usize, usize, usize), u32>,
+        cube: (usize, usize, usize),
+        cube_vertices: &[u16]
+    ) -> u32 {
+        let mut joined_edges: Vec<u16> = cube_vertices.chunks(3)
+            .filter(|triangle| triangle.contains(&CUBE_CENTRE))
+            .flat_map(|triangle| triangle.iter().copied().filter(|&vert| vert != CUBE_CENTRE))
+            .collect();
+
+        joined_edges.sort_unstable();
+        joined_edges.dedup();
+
+        let mut position = Vector3::new(0.0, 0.0, 0.0);
+        let mut normal = Vector3::new(0.0, 0.0, 0.0);
+
+        for vert in joined_edges.iter() {
+            let index = self.edge_vertex(grid, mesh, edge_cache, cube, *vert);
+
+            position += mesh.position(index);
+            normal += mesh.normal(index);
+        }
+
+        position /= joined_edges.len() as f32;
+
+        let normal = if normal.magnitude2() > 0.0 {
+            normal.normalize()
+        }
+        else {
+            Vector3::unit_y()
+        };
+
+        mesh.push_vertex(position, normal)
+    }
+}
+
+impl Mesher for MarchingCubesMesher {
+    // the gradients at the border samples reach one sample out
+    fn padding(&self) -> usize {
+        1
+    }
+
+    fn meshes_to_chunk_faces(&self) -> bool {
+        true
+    }
+
+    fn joins_rock_across_face(&self, values: &[f32; 8], face: usize) -> bool {
+        if self.use_mc33 {
+            return joined_across_face(values, face);
+        }
+
+        // plain marching cubes splits the face however the table's triangles
+        // for the cube do, which shows in the triangle edges along the face
+        // since they're the only ones with a single triangle
+        let cube_config = (0..8).fold(0, |config, corner| {
+            if values[corner] >= 0.0 { config | 1 << corner } else { config }
+        });
+
+        let mut edge_counts = HashMap::<(u16, u16), u32>::new();
+
+        for triangle in self.tri_table.table.get(&cube_config).unwrap().chunks(3) {
+            for corner in 0..3 {
+                let (a, b) = (triangle[corner], triangle[(corner + 1) % 3]);
+                *edge_counts.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+            }
+        }
+
+        let corners = CUBE_FACES[face];
+        let side = |a: usize, b: usize| {
+            CUBE_EDGES.iter().position(|&edge| edge == [a, b] || edge == [b, a]).unwrap() as u16
+        };
+
+        let (before, after) = (side(corners[0], corners[1]), side(corners[1], corners[2]));
+        let cuts_off_corner_one = edge_counts.get(&(before.min(after), before.max(after))) == Some(&1);
+
+        // the rock is joined when the corners cut off on their own are air
+        cuts_off_corner_one != (values[corners[1]] >= 0.0)
+    }
+
+    // generates vertices from the labelled samples
+    fn mesh(&self, grid: &SampledGrid) -> Mesh {
+        let tri_table = &self.tri_table;
+        let chunk_bounds = grid.chunk_bounds;
+
+        // creates a mesh for vertices, normals and indices
+        let mut mesh = Mesh::new();
+
+        // caches the index of the vertex generated on each grid edge, keyed by the
+        // lower corner of the edge and the axis it runs along, so that every
+        // triangle touching an edge shares the same vertex
+        let mut edge_cache = HashMap::<(usize, usize, usize, usize), u32>::new();
 
         // gets the cubes marching
-        for x in 0..(CHUNK_BOUNDS - 1) {
-            for y in 0..(CHUNK_BOUNDS - 1) {
-                for z in 0..(CHUNK_BOUNDS - 1) {
+        for x in 0..(chunk_bounds - 1) {
+            for y in 0..(chunk_bounds - 1) {
+                for z in 0..(chunk_bounds - 1) {
 
-                    // creates a bitwise representation of the cube that's 
+                    // creates a bitwise representation of the cube that's
                     // currently being marched on
                     let mut cube_config: u8 = 0;
 
                     // manual for now, replace later
-                    cube_config |= (self.grid[[x, y, z]] as u8) << 0;
-                    cube_config |= (self.grid[[x + 1, y, z]] as u8) << 1;
-                    cube_config |= (self.grid[[x + 1, y, z + 1]] as u8) << 2;
-                    cube_config |= (self.grid[[x, y, z + 1]] as u8) << 3;
-                    
-                    cube_config |= (self.grid[[x, y + 1, z]] as u8) << 4;
-                    cube_config |= (self.grid[[x + 1, y + 1, z]] as u8) << 5;
-                    cube_config |= (self.grid[[x + 1, y + 1, z + 1]] as u8) << 6;
-                    cube_config |= (self.grid[[x, y + 1, z + 1]] as u8) << 7;
-
-                    // if the cube offset is not 0 or 255 (empty or full), proceed
-                    if cube_config != 0 && cube_config != 255 {
-
-                        // gets the cube configuration vertices from the triangulation table
-                        let cube_vertices = tri_table.table.get(&(cube_config as u16)).unwrap();
-
-                        // loops over the cube vertices
-                        for vert in cube_vertices.iter() {
-                            // based on the indices, gets the corner configurations associated
-                            // with the vert
-                            let corner_a = *tri_table.corner_index_a_from_edge.get(*vert as usize).unwrap();
-                            let corner_b = *tri_table.corner_index_b_from_edge.get(*vert as usize).unwrap();
-
-                            // gets the sampled values at the corners that are being assessed
-                            let corner_a_weight = self.grid_weights[[
-                                x + corner_a.x as usize,
-                                y + corner_a.y as usize,
-                                z + corner_a.z as usize
-                            ]];
-
-                            let corner_b_weight = self.grid_weights[[
-                                x + corner_b.x as usize,
-                                y + corner_b.y as usize,
-                                z + corner_b.z as usize
-                            ]];
-
-                            // interpolates between the grid weights
-                            let interp_value = (self.isovalue - corner_a_weight) / (corner_b_weight - corner_a_weight);
-
-                            // gets an interpolated value between the two corners
-                            let interp_corners = corner_a + (interp_value * (corner_b - corner_a));
-
-                            // calculates the vertex point from a number of different factors
-                            let vertex_point = Vector3::new(
-                                ((x as f32 + interp_corners.x) * self.spacing_per_vertex) + full_offset.x,
-                                ((y as f32 + interp_corners.y) * self.spacing_per_vertex) + full_offset.y,
-                                ((z as f32 + interp_corners.z) * self.spacing_per_vertex) + full_offset.z
-                            );
-
-                            // adds the vertex point to the vertices
-                            vertices_table.push(vertex_point.x);
-                            vertices_table.push(vertex_point.y);
-                            vertices_table.push(vertex_point.z);
-
-                            // pushes the indices, increments
-                            indices_table.push(index);
-                            indices_table.push(index + 1);
-                            indices_table.push(index + 2);
-
-                            index = index + 3;
+                    cube_config |= grid.mask(x, y, z) as u8;
+                    cube_config |= (grid.mask(x + 1, y, z) as u8) << 1;
+                    cube_config |= (grid.mask(x + 1, y, z + 1) as u8) << 2;
+                    cube_config |= (grid.mask(x, y, z + 1) as u8) << 3;
+
+                    cube_config |= (grid.mask(x, y + 1, z) as u8) << 4;
+                    cube_config |= (grid.mask(x + 1, y + 1, z) as u8) << 5;
+                    cube_config |= (grid.mask(x + 1, y + 1, z + 1) as u8) << 6;
+                    cube_config |= (grid.mask(x, y + 1, z + 1) as u8) << 7;
+
+                    // if the cube offset is 0 or 255 (empty or full), skip it
+                    if cube_config == 0 || cube_config == 255 {
+                        continue;
+                    }
+
+                    // gets the cube configuration vertices from the triangulation table
+                    let cube_vertices = if self.use_mc33 {
+                        self.mc33_triangles(grid, Vector3::new(x as i32, y as i32, z as i32), cube_config)
+                    }
+                    else {
+                        tri_table.table.get(&(cube_config as u16)).unwrap()
+                    };
+
+                    // the centre of the cube is only made for the cases that
+                    // need it, and is never shared with another cube
+                    let mut centre_index = None;
+
+                    // loops over the cube vertices, each one being a corner of a triangle
+                    for vert in cube_vertices.iter() {
+                        let index = if *vert == CUBE_CENTRE {
+                            *centre_index.get_or_insert_with(|| {
+                                self.centre_vertex(grid, &mut mesh, &mut edge_cache, (x, y, z), cube_vertices)
+                            })
                         }
+                        else {
+                            self.edge_vertex(grid, &mut mesh, &mut edge_cache, (x, y, z), *vert)
+                        };
+
+                        mesh.indices.push(index);
                     }
                 }
             }
         }
 
-        // sets the vertices table
-        self.vertices = vertices_table;
-        self.indices = indices_table;
+        mesh
     }
-}
\ No newline at end of file
+}
+
+//----------------------
+
+// checks whether the rock corners of a face with rock on one diagonal and air
+// on the other are joined across it, from the densities at the cube's corners
+// relative to the isovalue. they're joined when their product beats the
+// product of the air corners, which only looks at the face so neighbouring
+// cubes always agree
+fn joined_across_face(values: &[f32; 8], face: usize) -> bool {
+    let corners = CUBE_FACES[face];
+
+    let product_a = values[corners[0]] * values[corners[2]];
+    let product_b = values[corners[1]] * values[corners[3]];
+    let (rock_product, air_product) = if values[corners[0]] >= 0.0 { (product_a, product_b) } else { (product_b, product_a) };
+
+    rock_product > air_product
+}
+
+// checks whether two groups of corners on the same side of the surface are
+// joined through the inside of a cube, from the densities at its corners
+// relative to the isovalue. the cube is sliced across each axis in turn, and
+// the groups are joined if some slice has a corner edge reaching each group
+// and the two edges are joined across the slice's diagonal
+fn joined_through_cube(values: &[f32; 8], corners_a: u8, corners_b: u8) -> bool {
+    let side = values[corners_a.trailing_zeros() as usize] >= 0.0;
+
+    let corner_at = |position: [i32; 3]| CUBE_CORNERS.iter().position(|&corner| corner == position).unwrap();
+
+    for axis in 0..3 {
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+
+        // gets the bottom and top corner of each edge along the axis, going
+        // around the slice
+        let columns = [(0, 0), (1, 0), (1, 1), (0, 1)].map(|(du, dv)| {
+            let mut bottom = [0; 3];
+            bottom[u] = du;
+            bottom[v] = dv;
+
+            let mut top = bottom;
+            top[axis] = 1;
+
+            (corner_at(bottom), corner_at(top))
+        });
+
+        let bottoms = columns.map(|(bottom, _)| values[bottom]);
+        let steps = columns.map(|(bottom, top)| values[top] - values[bottom]);
+        let value_at = |column: usize, t: f32| bottoms[column] + steps[column] * t;
+
+        // splits the slices up where the surface crosses the edges, since the
+        // sides of the slice's corners only change there
+        let mut cuts = vec![0.0, 1.0];
+
+        for column in 0..4 {
+            if steps[column] != 0.0 {
+                let t = -bottoms[column] / steps[column];
+
+                if t > 0.0 && t < 1.0 {
+                    cuts.push(t);
+                }
+            }
+        }
+
+        cuts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        for range in cuts.windows(2) {
+            let (start, end) = (range[0], range[1]);
+            let middle = (start + end) * 0.5;
+
+            let on_side = [0, 1, 2, 3].map(|column| (value_at(column, middle) >= 0.0) == side);
+
+            // gets which group each slice corner reaches along its edge
+            let reaches = |column: usize, corners: u8| {
+                let (bottom, top) = columns[column];
+                on_side[column] && (corners & (1 << bottom) != 0 || corners & (1 << top) != 0)
+            };
+
+            for column in 0..2 {
+                let opposite = column + 2;
+
+                let spans_groups = (reaches(column, corners_a) && reaches(opposite, corners_b)) ||
+                    (reaches(column, corners_b) && reaches(opposite, corners_a));
+
+                if !spans_groups || on_side[column + 1] || on_side[(column + 3) % 4] {
+                    continue;
+                }
+
+                // the diagonal is joined when its product beats the other
+                // diagonal's, which is a quadratic through the slices so is
+                // checked at the ends of the range and its turning point
+                let product_gap = |t: f32| {
+                    value_at(column, t) * value_at(opposite, t) - value_at(column + 1, t) * value_at((column + 3) % 4, t)
+                };
+
+                let curve = steps[column] * steps[opposite] - steps[column + 1] * steps[(column + 3) % 4];
+                let slope = bottoms[column] * steps[opposite] + bottoms[opposite] * steps[column] -
+                    bottoms[column + 1] * steps[(column + 3) % 4] - bottoms[(column + 3) % 4] * steps[column + 1];
+
+                let mut samples = vec![start, middle, end];
+
+                if curve != 0.0 {
+                    let turning_point = -slope / (2.0 * curve);
+
+                    if turning_point > start && turning_point < end {
+                        samples.push(turning_point);
+                    }
+                }
+
+                if samples.into_iter().any(|t| product_gap(t) > 0.0) {
+                    return true;
+                }
+            }
+        }
+    }
+
+    false
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ndarray::Array3;
+
+    // builds a full detail chunk of the default world with plain marching cubes
+    fn build_chunk(world_config: &WorldConfig, density: &dyn DensityField, offset: Vector3<i32>) -> MCChunk {
+        let mesher = MarchingCubesMesher::new(Arc::new(TriangulationTable::new()));
+
+        MCChunk::new(ChunkKey::base(offset), world_config, density, &mesher)
+    }
+
+    #[test]
+    fn neighbouring_chunks_have_no_border_cracks() {
+        let world_config = WorldConfig::new();
+        let density = world_config.create_density_field();
+
+        let centre = build_chunk(&world_config, density.as_ref(), Vector3::new(0, 0, 0));
+        assert!(!centre.indices.is_empty(), "the centre chunk should have a surface to check");
+
+        let mut shared_vertices = 0;
+
+        for axis in 0..3 {
+            for direction in [-1, 1].iter() {
+                let mut step = Vector3::new(0, 0, 0);
+                step[axis] = *direction;
+
+                let neighbour = build_chunk(&world_config, density.as_ref(), step);
+
+                assert_eq!(centre.find_border_cracks(&neighbour), Ok(vec![]));
+                assert_eq!(neighbour.find_border_cracks(&centre), Ok(vec![]));
+
+                let upper_offset = if *direction > 0 { step } else { Vector3::new(0, 0, 0) };
+                let plane = chunk_space::chunk_origin_world(upper_offset, world_config.chunk_bounds, world_config.spacing_per_vertex)[axis];
+
+                shared_vertices += centre.border_vertices(axis, plane).len();
+            }
+        }
+
+        // the check means nothing if the surface never crosses the faces
+        assert!(shared_vertices > 0);
+    }
+
+    #[test]
+    fn non_neighbouring_chunks_are_rejected() {
+        let world_config = WorldConfig::new();
+        let density = world_config.create_density_field();
+
+        let chunk = build_chunk(&world_config, density.as_ref(), Vector3::new(0, 0, 0));
+
+        for offset in [Vector3::new(0, 0, 0), Vector3::new(2, 0, 0), Vector3::new(1, 1, 0), Vector3::new(-1, 0, 1)].iter() {
+            let other = build_chunk(&world_config, density.as_ref(), *offset);
+
+            assert!(chunk.find_border_cracks(&other).is_err());
+        }
+
+        let mut coarse_config = world_config.clone();
+        coarse_config.spacing_per_vertex *= 2.0;
+
+        let coarse = build_chunk(&coarse_config, density.as_ref(), Vector3::new(1, 0, 0));
+
+        assert!(chunk.find_border_cracks(&coarse).is_err());
+    }
+
+    #[test]
+    fn marching_cubes_33_surfaces_are_closed_inside_the_chunk() {
+        let mut tri_table = TriangulationTable::new();
+        tri_table.load_extended();
+
+        let mesher = MarchingCubesMesher::with_mc33(Arc::new(tri_table));
+
+        let chunk_bounds = 6;
+        let padded_bounds = chunk_bounds + mesher.padding() * 2;
+
+        // a small linear congruential generator, so the grids are the same every run
+        let mut seed = 0x2545_f491_u64;
+        let mut random = move || {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+            ((seed >> 40) as f32 / (1 << 24) as f32) * 2.0 - 1.0
+        };
+
+        for _ in 0..200 {
+            let weights = Array3::from_shape_fn((padded_bounds, padded_bounds, padded_bounds), |_| random());
+            let grid = SampledGrid::from_weights(weights, mesher.padding(), Vector3::new(0, 0, 0), chunk_bounds, 1.0, 0.0);
+
+            let mesh = mesher.mesh(&grid);
+
+            // counts how many times each edge is walked in each direction
+            let mut edges = HashMap::<(u32, u32), usize>::new();
+
+            for triangle in mesh.indices.chunks(3) {
+                assert!(triangle[0] != triangle[1] && triangle[1] != triangle[2] && triangle[2] != triangle[0]);
+
+                for corner in 0..3 {
+                    *edges.entry((triangle[corner], triangle[(corner + 1) % 3])).or_insert(0) += 1;
+                }
+            }
+
+            // only edges lying in one of the chunk's faces can be open
+            let on_chunk_face = |a: u32, b: u32| {
+                let (a, b) = (mesh.position(a), mesh.position(b));
+
+                (0..3).any(|axis| {
+                    [0.0, (chunk_bounds - 1) as f32].iter().any(|&plane| a[axis] == plane && b[axis] == plane)
+                })
+            };
+
+            for (&(a, b), &count) in edges.iter() {
+                assert_eq!(count, 1, "edge {} {} is walked the same way by {} triangles", a, b, count);
+
+                if !on_chunk_face(a, b) {
+                    assert!(edges.contains_key(&(b, a)), "edge {} {} inside the chunk only has one triangle", a, b);
+                }
+            }
+        }
+    }
+}
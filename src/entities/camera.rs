@@ -29,12 +29,24 @@ impl Camera {
 
     // translates the camera
     pub fn translate(&mut self, translation: Vector3<f32>) {
-        self.position = self.position + translation;
+        self.position += translation;
     }
 
     // rotates the camera
     pub fn rotate(&mut self, rotation: Vector3<f32>) {
-        self.rotation = self.rotation + rotation;
+        self.rotation += rotation;
+    }
+
+    // gets the unit vector the camera is looking along
+    pub fn forward(&self) -> Vector3<f32> {
+        let pitch = Rad(self.rotation.x);
+        let yaw = Rad(self.rotation.y);
+
+        Vector3::new(
+            Rad::sin(yaw) * Rad::cos(pitch),
+            -Rad::sin(pitch),
+            -Rad::cos(yaw) * Rad::cos(pitch)
+        )
     }
 
     // obtains the camera's view matrix based on the cam position
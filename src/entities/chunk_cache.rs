@@ -0,0 +1,324 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::{
+    basic_marching_cubes::MCChunk,
+    chunk_lod::ChunkKey
+};
+
+//------------------------
+
+// how well the cache is doing, for tuning its budget
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+
+    pub entries: usize,
+    pub used_bytes: usize,
+    pub byte_budget: usize
+}
+
+struct CacheEntry {
+    chunk: MCChunk,
+    size: usize,
+    last_used: u64
+}
+
+// built chunks kept on the CPU after they leave the view, so coming back to
+// them doesn't mean generating them again. the least recently used chunks are
+// dropped once the cache grows past its byte budget
+pub struct ChunkCache {
+    entries: HashMap<ChunkKey, CacheEntry>,
+
+    // the entries ordered by when they were last used, oldest first
+    recency: BTreeMap<u64, ChunkKey>,
+    clock: u64,
+
+    used_bytes: usize,
+    byte_budget: usize,
+
+    hits: u64,
+    misses: u64,
+    evictions: u64
+}
+
+//------------------------
+
+impl ChunkCache {
+    pub fn new(byte_budget: usize) -> ChunkCache {
+        ChunkCache {
+            entries: HashMap::new(),
+
+            recency: BTreeMap::new(),
+            clock: 0,
+
+            used_bytes: 0,
+            byte_budget,
+
+            hits: 0,
+            misses: 0,
+            evictions: 0
+        }
+    }
+
+    pub fn byte_budget(&self) -> usize {
+        self.byte_budget
+    }
+
+    // changes the budget, dropping old chunks straight away if it shrank
+    pub fn set_byte_budget(&mut self, byte_budget: usize) {
+        self.byte_budget = byte_budget;
+        self.evict_to_fit(0);
+    }
+
+    pub fn contains(&self, offset: &ChunkKey) -> bool {
+        self.entries.contains_key(offset)
+    }
+
+    // adds a chunk as the most recently used, replacing any older copy.
+    // chunks bigger than the whole budget aren't kept
+    pub fn insert(&mut self, offset: ChunkKey, chunk: MCChunk) {
+        self.remove(&offset);
+
+        let size = chunk.memory_size();
+
+        if size > self.byte_budget {
+            return;
+        }
+
+        self.evict_to_fit(size);
+
+        let last_used = self.tick();
+
+        self.recency.insert(last_used, offset);
+        self.entries.insert(offset, CacheEntry {
+            chunk,
+            size,
+            last_used
+        });
+
+        self.used_bytes += size;
+    }
+
+    // takes a chunk out of the cache to be used, counting a hit if it was there
+    pub fn take(&mut self, offset: &ChunkKey) -> Option<MCChunk> {
+        let chunk = self.remove(offset);
+
+        if chunk.is_some() {
+            self.hits += 1;
+        }
+
+        chunk
+    }
+
+    // drops a chunk that's out of date, without counting it as a hit
+    pub fn discard(&mut self, offset: &ChunkKey) -> bool {
+        self.remove(offset).is_some()
+    }
+
+    // goes over the cached chunks without counting them as used
+    pub fn chunks(&self) -> impl Iterator<Item = (&ChunkKey, &MCChunk)> {
+        self.entries.iter().map(|(offset, entry)| (offset, &entry.chunk))
+    }
+
+    // counts a chunk that has to be generated again because it wasn't cached
+    pub fn record_miss(&mut self) {
+        self.misses += 1;
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            evictions: self.evictions,
+
+            entries: self.entries.len(),
+            used_bytes: self.used_bytes,
+            byte_budget: self.byte_budget
+        }
+    }
+
+    //------------------------
+
+    fn remove(&mut self, offset: &ChunkKey) -> Option<MCChunk> {
+        let entry = self.entries.remove(offset)?;
+
+        self.recency.remove(&entry.last_used);
+        self.used_bytes -= entry.size;
+
+        Some(entry.chunk)
+    }
+
+    // drops the least recently used chunks until the extra bytes fit the budget
+    fn evict_to_fit(&mut self, extra_bytes: usize) {
+        while self.used_bytes + extra_bytes > self.byte_budget {
+            let oldest = match self.recency.iter().next() {
+                Some((_, offset)) => *offset,
+                None => break
+            };
+
+            self.remove(&oldest);
+            self.evictions += 1;
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+}
+
+//------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::Vector3;
+    use std::sync::Arc;
+
+    use crate::{
+        entities::{
+            basic_marching_cubes::MarchingCubesMesher,
+            density_expr::DensityExpr,
+            density_field::DensityField,
+            table_reader::TriangulationTable
+        },
+        utils::world_config::WorldConfig
+    };
+
+    fn build_chunk(density: &dyn DensityField) -> MCChunk {
+        let world_config = WorldConfig::new();
+        let mesher = MarchingCubesMesher::new(Arc::new(TriangulationTable::new()));
+
+        MCChunk::new(ChunkKey::base(Vector3::new(0, 0, 0)), &world_config, density, &mesher)
+    }
+
+    // a chunk with a surface, and one that's all air and so much smaller. each
+    // is built the same every time, so every copy is the same size
+    fn mixed_chunk() -> MCChunk {
+        build_chunk(WorldConfig::new().create_density_field().as_ref())
+    }
+
+    fn air_chunk() -> MCChunk {
+        build_chunk(&DensityExpr::constant(-1.0))
+    }
+
+    fn key(x: i32) -> ChunkKey {
+        ChunkKey::base(Vector3::new(x, 0, 0))
+    }
+
+    #[test]
+    fn the_least_recently_used_chunk_is_evicted_first() {
+        let size = mixed_chunk().memory_size();
+
+        let mut cache = ChunkCache::new(size * 3);
+
+        for x in 0..3 {
+            cache.insert(key(x), mixed_chunk());
+        }
+
+        // inserting the first again makes it the most recently used
+        cache.insert(key(0), mixed_chunk());
+        cache.insert(key(3), mixed_chunk());
+
+        assert!(!cache.contains(&key(1)));
+        assert!(cache.contains(&key(0)) && cache.contains(&key(2)) && cache.contains(&key(3)));
+
+        cache.insert(key(4), mixed_chunk());
+
+        assert!(!cache.contains(&key(2)));
+
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.used_bytes, stats.evictions), (3, size * 3, 2));
+    }
+
+    #[test]
+    fn the_cache_stays_within_its_byte_budget() {
+        let (mixed_size, air_size) = (mixed_chunk().memory_size(), air_chunk().memory_size());
+        assert!(air_size * 4 < mixed_size);
+
+        // room for the mixed chunk and a couple of air chunks, but not two mixed ones
+        let mut cache = ChunkCache::new(mixed_size + air_size * 2);
+
+        cache.insert(key(0), air_chunk());
+        cache.insert(key(1), air_chunk());
+        cache.insert(key(2), mixed_chunk());
+
+        assert_eq!(cache.stats().entries, 3);
+        assert_eq!(cache.stats().evictions, 0);
+
+        cache.insert(key(3), mixed_chunk());
+
+        // both air chunks weren't enough room, so the old mixed chunk went too
+        let stats = cache.stats();
+
+        assert_eq!((stats.entries, stats.evictions), (1, 3));
+        assert!(cache.contains(&key(3)));
+        assert!(stats.used_bytes <= stats.byte_budget);
+    }
+
+    #[test]
+    fn chunks_bigger_than_the_budget_are_not_kept() {
+        let air_size = air_chunk().memory_size();
+
+        let mut cache = ChunkCache::new(mixed_chunk().memory_size() - 1);
+
+        cache.insert(key(0), air_chunk());
+        cache.insert(key(1), air_chunk());
+        cache.insert(key(2), mixed_chunk());
+
+        // the chunk that could never fit doesn't push out the ones that do
+        let stats = cache.stats();
+
+        assert!(!cache.contains(&key(2)));
+        assert_eq!((stats.entries, stats.used_bytes, stats.evictions), (2, air_size * 2, 0));
+    }
+
+    #[test]
+    fn shrinking_the_budget_evicts_straight_away() {
+        let size = mixed_chunk().memory_size();
+
+        let mut cache = ChunkCache::new(size * 4);
+
+        for x in 0..4 {
+            cache.insert(key(x), mixed_chunk());
+        }
+
+        cache.set_byte_budget(size + size / 2);
+
+        let stats = cache.stats();
+
+        assert_eq!((stats.entries, stats.used_bytes, stats.evictions), (1, size, 3));
+        assert!(cache.contains(&key(3)));
+
+        // growing it again doesn't bring anything back
+        cache.set_byte_budget(size * 4);
+        assert_eq!(cache.stats().entries, 1);
+
+        cache.set_byte_budget(0);
+        assert_eq!((cache.stats().entries, cache.stats().used_bytes), (0, 0));
+    }
+
+    #[test]
+    fn hits_and_misses_are_counted() {
+        let mut cache = ChunkCache::new(air_chunk().memory_size() * 4);
+
+        cache.insert(key(0), air_chunk());
+        cache.insert(key(1), air_chunk());
+
+        assert!(cache.take(&key(0)).is_some());
+        assert!(cache.take(&key(0)).is_none());
+        cache.record_miss();
+
+        // discarding an out of date chunk isn't a hit
+        assert!(cache.discard(&key(1)));
+        assert!(!cache.discard(&key(1)));
+
+        let stats = cache.stats();
+
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (1, 1, 0));
+        assert_eq!((stats.entries, stats.used_bytes), (0, 0));
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::{HashMap, HashSet};
+
+use cgmath::*;
+
+use crate::utils::chunk_space;
+
+use super::occlusion::FACE_DIRECTIONS;
+
+//------------------------
+
+// a chunk at a level of detail. a chunk at level L has the same number of
+// samples as a base chunk but spreads them 2^L times further apart, so it
+// covers 2^L base chunks along each axis and its offset counts in chunks of
+// its own size
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ChunkKey {
+    pub lod: u32,
+    pub offset: Vector3<i32>
+}
+
+//------------------------
+
+impl ChunkKey {
+    pub fn new(lod: u32, offset: Vector3<i32>) -> ChunkKey {
+        ChunkKey { lod, offset }
+    }
+
+    // a full detail chunk
+    pub fn base(offset: Vector3<i32>) -> ChunkKey {
+        ChunkKey::new(0, offset)
+    }
+
+    // gets how many base chunks the chunk covers along each axis
+    pub fn scale(&self) -> i32 {
+        1 << self.lod
+    }
+
+    // gets the distance between the chunk's samples
+    pub fn spacing(&self, base_spacing: f32) -> f32 {
+        base_spacing * self.scale() as f32
+    }
+
+    // gets the chunk one level coarser that contains this one
+    pub fn parent(&self) -> ChunkKey {
+        ChunkKey::new(
+            self.lod + 1,
+            Vector3::new(self.offset.x.div_euclid(2), self.offset.y.div_euclid(2), self.offset.z.div_euclid(2))
+        )
+    }
+
+    // gets the eight chunks one level finer that make up this one
+    pub fn children(&self) -> Vec<ChunkKey> {
+        if self.lod == 0 {
+            return Vec::new();
+        }
+
+        let mut children = Vec::with_capacity(8);
+
+        for x in 0..2 {
+            for y in 0..2 {
+                for z in 0..2 {
+                    children.push(ChunkKey::new(self.lod - 1, self.offset * 2 + Vector3::new(x, y, z)));
+                }
+            }
+        }
+
+        children
+    }
+
+    // gets the chunk of the same level across one of the faces, in the order
+    // of the occlusion face directions
+    pub fn neighbour(&self, face: usize) -> ChunkKey {
+        ChunkKey::new(self.lod, self.offset + FACE_DIRECTIONS[face])
+    }
+
+    // gets the squared distance in base chunks from a base chunk to the
+    // nearest base chunk this one covers
+    pub fn distance2_to(&self, base_chunk: Vector3<i32>) -> i64 {
+        let low = self.offset * self.scale();
+        let high = low + Vector3::new(self.scale() - 1, self.scale() - 1, self.scale() - 1);
+
+        (0..3).map(|axis| {
+            let gap = (low[axis] - base_chunk[axis]).max(base_chunk[axis] - high[axis]).max(0) as i64;
+
+            gap * gap
+        }).sum()
+    }
+}
+
+//------------------------
+
+// picks the chunks that cover the view around the camera's base chunk. full
+// detail chunks fill the view distance and each coarser level reaches twice
+// as far as the one before, with every coarse chunk near enough to the camera
+// split into its eight children like an octree. chunks are then split further
+// until the chunks across each face are at most one level finer
+pub fn select_chunks(camera_chunk: Vector3<i32>, view_distance: i32, lod_levels: u32) -> HashSet<ChunkKey> {
+    let mut selected = HashSet::new();
+
+    // the camera's chunk at the coarsest level
+    let top_scale = 1 << lod_levels;
+    let camera_top = Vector3::new(
+        camera_chunk.x.div_euclid(top_scale),
+        camera_chunk.y.div_euclid(top_scale),
+        camera_chunk.z.div_euclid(top_scale)
+    );
+
+    for x in (camera_top.x - view_distance)..=(camera_top.x + view_distance) {
+        for y in (camera_top.y - view_distance)..=(camera_top.y + view_distance) {
+            for z in (camera_top.z - view_distance)..=(camera_top.z + view_distance) {
+                let offset = Vector3::new(x, y, z);
+
+                if chunk_space::chunk_within_radius(offset, camera_top, view_distance) {
+                    select_recursive(ChunkKey::new(lod_levels, offset), camera_chunk, view_distance, &mut selected);
+                }
+            }
+        }
+    }
+
+    balance(&mut selected);
+
+    selected
+}
+
+fn select_recursive(key: ChunkKey, camera_chunk: Vector3<i32>, view_distance: i32, selected: &mut HashSet<ChunkKey>) {
+    // chunks are split while they're within reach of the next finer level
+    if key.lod > 0 {
+        let split_distance = view_distance as i64 * (1 << (key.lod - 1));
+
+        if key.distance2_to(camera_chunk) <= split_distance * split_distance {
+            for child in key.children() {
+                select_recursive(child, camera_chunk, view_distance, selected);
+            }
+
+            return;
+        }
+    }
+
+    selected.insert(key);
+}
+
+// splits chunks with a face against chunks two or more levels finer, since
+// transition cells only join a chunk to the level below it. splitting a chunk
+// can leave its own neighbours too coarse, so goes until nothing changes
+fn balance(selected: &mut HashSet<ChunkKey>) {
+    loop {
+        let too_coarse: Vec<ChunkKey> = selected.iter().copied().filter(|key| {
+            (0..6).any(|face| {
+                let neighbour = key.neighbour(face);
+
+                // the neighbour's children against the face are either selected
+                // or split up further
+                !selected.contains(&neighbour) && neighbour.children().into_iter().any(|child| {
+                    child.neighbour(face ^ 1).parent() == *key &&
+                        !selected.contains(&child) &&
+                        covered_by_descendants(child, selected)
+                })
+            })
+        }).collect();
+
+        if too_coarse.is_empty() {
+            return;
+        }
+
+        for key in too_coarse {
+            selected.remove(&key);
+            selected.extend(key.children());
+        }
+    }
+}
+
+// works out which faces of each selected chunk border finer chunks, as a bit
+// per face in the order of the occlusion face directions. the coarser side of
+// each border has the transition cells, so borders with coarser chunks and
+// the edge of the view are left out
+pub fn transition_masks(selected: &HashSet<ChunkKey>) -> HashMap<ChunkKey, u8> {
+    selected.iter().map(|key| {
+        let mut mask = 0;
+
+        for face in 0..6 {
+            let neighbour = key.neighbour(face);
+
+            if !selected.contains(&neighbour) && covered_by_descendants(neighbour, selected) {
+                mask |= 1 << face;
+            }
+        }
+
+        (*key, mask)
+    }).collect()
+}
+
+fn covered_by_descendants(key: ChunkKey, selected: &HashSet<ChunkKey>) -> bool {
+    key.children().iter().any(|child| selected.contains(child) || covered_by_descendants(*child, selected))
+}
@@ -1,133 +1,878 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
 
 use cgmath::*;
 
 use crate::{
     models::raw_model::RawModel,
     rendering::loader::Loader,
-    utils::constants::*
+    utils::{
+        chunk_space,
+        constants::*,
+        world_config::WorldConfig
+    }
 };
 
 use super::{
     basic_marching_cubes::MCChunk,
     camera::Camera,
-    table_reader::TriangulationTable
+    chunk_cache::{CacheStats, ChunkCache},
+    chunk_lod::{self, ChunkKey},
+    chunk_scheduler::{self, ChunkBudget, ChunkQueue},
+    chunk_workers::ChunkWorkerPool,
+    density_field::DensityField,
+    occlusion::{self, FaceConnectivity},
+    table_reader::TriangulationTable,
+    voxel_edits::{Brush, EditableDensity},
+    edit_history::EditHistory,
+    region_file::RegionStore,
+    basic_marching_cubes::ChunkSamples
 };
 
+// what applying a brush did, the chunks marked to be remeshed and whether the
+// edit can be undone, which it can't if it's too big for the edit history
+#[derive(Clone, Copy, Debug)]
+pub struct BrushOutcome {
+    pub marked_chunks: usize,
+    pub undoable: bool
+}
+
+// a mesh on the GPU along with the faces it was joined to finer levels of
+// detail on, and whether an edit has changed the chunk since.
+// outdated models are still drawn until their replacement is uploaded. chunks
+// with nothing to draw, like those that are all air, get no model at all
+struct ChunkModel {
+    model: Option<RawModel>,
+    transition_mask: u8,
+    outdated: bool
+}
+
+// the chunks picked around a camera chunk, which only change when the camera
+// crosses into another chunk or the view settings change
+struct ChunkSelection {
+    camera_chunk: Vector3<i32>,
+    view_distance: i32,
+    unload_margin: i32,
+    lod_levels: u32,
+
+    // the chunks covering the view with their transition masks, and every
+    // chunk close enough that its model is worth keeping
+    selected: HashMap<ChunkKey, u8>,
+    kept: HashSet<ChunkKey>
+}
+
 pub struct ChunkManager {
-    chunk_data: HashMap<Vector3<i32>, MCChunk>,
-    model_data: HashMap<Vector3<i32>, RawModel>
+    // built chunks waiting to be uploaded, and the cache that built chunks go
+    // to once they have a model or leave the view
+    ready_chunks: HashMap<ChunkKey, MCChunk>,
+    chunk_cache: ChunkCache,
+
+    // the meshes resident on the GPU, and the ones drawn last update
+    model_data: HashMap<ChunkKey, ChunkModel>,
+    drawn_chunks: Vec<ChunkKey>,
+
+    selection: Option<ChunkSelection>,
+
+    // which faces of each full detail chunk are joined by air, and the chunks
+    // that could be seen through open space within the radius walked around
+    // the camera's chunk last update
+    connectivity_data: HashMap<Vector3<i32>, FaceConnectivity>,
+    visible_chunks: HashSet<Vector3<i32>>,
+    occlusion_centre: Vector3<i32>,
+    occlusion_radius: i32,
+    occlusion_culling: bool,
+
+    // chunks that have been sent to the workers and haven't come back yet,
+    // and the ones among them that were edited while they were being built
+    pending_chunks: HashSet<ChunkKey>,
+    edited_pending_chunks: HashSet<ChunkKey>,
+    workers: ChunkWorkerPool,
+
+    // the density the workers sample, with any edits made to it, how far
+    // past its border a chunk's mesh reads samples, and whether the mesher's
+    // chunks can be joined to other levels of detail
+    density: Arc<EditableDensity>,
+    mesher_padding: usize,
+    mesher_joins_levels: bool,
+
+    // the edits that can be undone and redone
+    edit_history: EditHistory,
+
+    // the directory the world is saved to if one has been opened, and any
+    // problems reading it since they were last taken
+    region_store: Option<RegionStore>,
+    region_errors: Vec<String>,
+
+    world_config: Arc<WorldConfig>,
+
+    // the radius in chunks that is loaded around the camera's chunk, and how
+    // much further out loaded chunks can get before they're dropped
+    view_distance: i32,
+    unload_margin: i32,
+
+    // how many coarser levels of detail reach out past the full detail chunks
+    lod_levels: u32,
+
+    // how much chunk work can be done each frame
+    budget: ChunkBudget
 }
 
 //---------------------------
 
 impl ChunkManager {
-    pub fn new() -> ChunkManager {
+    // creates a chunk manager that generates the world described by the config
+    pub fn new(world_config: WorldConfig, tri_table: Arc<TriangulationTable>) -> ChunkManager {
+        let density = world_config.create_density_field();
+
+        ChunkManager::with_density(world_config, density, tri_table)
+    }
+
+    // creates a chunk manager for a config that samples a custom density field,
+    // such as a density expression, instead of the configured noise
+    pub fn with_density(
+        world_config: WorldConfig,
+        density: Box<dyn DensityField>,
+        tri_table: Arc<TriangulationTable>
+    ) -> ChunkManager {
+        // picks the mesher before the config is shared with the workers
+        let mesher = world_config.create_mesher(tri_table);
+        let mesher_padding = mesher.padding();
+        let mesher_joins_levels = mesher.meshes_to_chunk_faces();
+
+        // layers edits over the density so the world can be sculpted
+        let density = Arc::new(EditableDensity::new(density, world_config.spacing_per_vertex, world_config.isovalue));
+
+        let world_config = Arc::new(world_config);
+
+        // starts the workers that build chunks in the background
+        let workers = ChunkWorkerPool::new(
+            ChunkWorkerPool::default_worker_count(),
+            Arc::clone(&world_config),
+            Arc::clone(&density) as Arc<dyn DensityField>,
+            Arc::from(mesher)
+        );
+
         ChunkManager {
-            chunk_data: HashMap::new(),
-            model_data: HashMap::new()
+            ready_chunks: HashMap::new(),
+            chunk_cache: ChunkCache::new(DEFAULT_CHUNK_CACHE_BYTES),
+
+            model_data: HashMap::new(),
+            drawn_chunks: Vec::new(),
+
+            selection: None,
+
+            connectivity_data: HashMap::new(),
+            visible_chunks: HashSet::new(),
+            occlusion_centre: Vector3::new(0, 0, 0),
+            occlusion_radius: 0,
+            occlusion_culling: true,
+
+            pending_chunks: HashSet::new(),
+            edited_pending_chunks: HashSet::new(),
+            workers,
+
+            density,
+            mesher_padding,
+            mesher_joins_levels,
+
+            edit_history: EditHistory::new(DEFAULT_EDIT_HISTORY_BYTES),
+
+            region_store: None,
+            region_errors: Vec::new(),
+
+            world_config,
+
+            view_distance: DEFAULT_VIEW_DISTANCE,
+            unload_margin: DEFAULT_UNLOAD_MARGIN,
+
+            lod_levels: if mesher_joins_levels { DEFAULT_LOD_LEVELS } else { 0 },
+
+            budget: ChunkBudget::new()
         }
     }
 
-    pub fn generate_chunk_models(
-        &mut self, 
-        camera: &Camera, 
-        loader: &mut Loader, 
-        triangulation_table: &TriangulationTable
-    ) -> Vec<RawModel> {
+    pub fn world_config(&self) -> &WorldConfig {
+        &self.world_config
+    }
+
+    pub fn view_distance(&self) -> i32 {
+        self.view_distance
+    }
+
+    // changes how far out chunks are loaded, chunks past the new unload radius
+    // are dropped on the next update
+    pub fn set_view_distance(&mut self, view_distance: i32) {
+        self.view_distance = view_distance.clamp(1, MAX_VIEW_DISTANCE);
+    }
+
+    pub fn unload_margin(&self) -> i32 {
+        self.unload_margin
+    }
+
+    pub fn set_unload_margin(&mut self, unload_margin: i32) {
+        self.unload_margin = unload_margin.clamp(0, MAX_UNLOAD_MARGIN);
+    }
+
+    // gets the radius in chunks past which loaded chunks are dropped
+    pub fn unload_distance(&self) -> i32 {
+        self.view_distance + self.unload_margin
+    }
+
+    pub fn lod_levels(&self) -> u32 {
+        self.lod_levels
+    }
+
+    // changes how many coarser levels of detail are loaded, each one reaching
+    // twice as far as the last. transition cells need the mesh to end on the
+    // chunk faces, so meshers that don't only get full detail chunks
+    pub fn set_lod_levels(&mut self, lod_levels: u32) {
+        self.lod_levels = if self.mesher_joins_levels { lod_levels.min(MAX_LOD_LEVELS) } else { 0 };
+    }
+
+    // gets the view distance in world units, measured to the far side of the
+    // outermost loaded chunks
+    pub fn view_distance_world(&self) -> f32 {
+        let top_chunk = ChunkKey::new(self.lod_levels, Vector3::new(0, 0, 0));
+
+        (self.view_distance + 1) as f32 * chunk_space::chunk_world_size(
+            self.world_config.chunk_bounds,
+            top_chunk.spacing(self.world_config.spacing_per_vertex)
+        )
+    }
+
+    pub fn budget(&self) -> ChunkBudget {
+        self.budget
+    }
+
+    pub fn set_budget(&mut self, budget: ChunkBudget) {
+        self.budget = budget;
+    }
+
+    pub fn occlusion_culling(&self) -> bool {
+        self.occlusion_culling
+    }
+
+    // turns skipping chunks hidden behind rock on or off
+    pub fn set_occlusion_culling(&mut self, occlusion_culling: bool) {
+        self.occlusion_culling = occlusion_culling;
+    }
+
+    pub fn cache_byte_budget(&self) -> usize {
+        self.chunk_cache.byte_budget()
+    }
+
+    // changes how many bytes of built chunks are kept around on the CPU
+    pub fn set_cache_byte_budget(&mut self, byte_budget: usize) {
+        self.chunk_cache.set_byte_budget(byte_budget);
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        self.chunk_cache.stats()
+    }
+
+    pub fn edit_history_byte_budget(&self) -> usize {
+        self.edit_history.byte_budget()
+    }
+
+    // changes how many bytes of undo history are kept, forgetting the oldest
+    // edits if they no longer fit
+    pub fn set_edit_history_byte_budget(&mut self, byte_budget: usize) {
+        self.edit_history.set_byte_budget(byte_budget);
+    }
+
+    // gets the number of chunks the workers are still building
+    pub fn pending_count(&self) -> usize {
+        self.pending_chunks.len()
+    }
+
+    //---------------------------
+
+    // gets the models drawn for the view, leaving out full detail chunks that
+    // can't be seen through open space when occlusion culling is on. coarser
+    // chunks and any past the walked radius are always drawn
+    pub fn models(&self) -> Vec<&RawModel> {
+        self.drawn_chunks.iter()
+            .filter(|key| !self.is_occluded(key))
+            .filter_map(|key| self.model_data[key].model.as_ref())
+            .collect()
+    }
+
+    // gets the number of drawn chunks that were hidden by occlusion culling
+    pub fn occluded_count(&self) -> usize {
+        self.drawn_chunks.iter()
+            .filter(|key| self.is_occluded(key))
+            .count()
+    }
+
+    fn is_occluded(&self, key: &ChunkKey) -> bool {
+        self.occlusion_culling &&
+            key.lod == 0 &&
+            chunk_space::chunk_within_radius(key.offset, self.occlusion_centre, self.occlusion_radius) &&
+            !self.visible_chunks.contains(&key.offset)
+    }
+
+    // loads, builds and uploads chunks around the camera, dropping the ones
+    // it has moved away from and remeshing ones whose neighbours change level
+    pub fn update_chunks(
+        &mut self,
+        camera: &Camera,
+        loader: &mut Loader
+    ) {
         // gets the position of the camera
         let camera_position = camera.position;
 
         // based on the cam position, gets a gridspace that the camera is in
-        let camera_gridspace_f = (camera_position/SPACING_PER_VERTEX)/((CHUNK_BOUNDS - 1) as f32);
-
-        // floors the camera position
-        let camera_gridspace = Vector3::new(
-            camera_gridspace_f.x.floor() as i32,
-            camera_gridspace_f.y.floor() as i32,
-            camera_gridspace_f.z.floor() as i32
+        let camera_gridspace = chunk_space::world_to_chunk(
+            camera_position,
+            self.world_config.chunk_bounds,
+            self.world_config.spacing_per_vertex
         );
 
-        // cleans the models outside of the chunk space by removing their VAOs
-        for (offset, model) in self.model_data.iter_mut() {
-            if ChunkManager::check_offset_out_of_gridspace(offset, &camera_gridspace) {
-                model.clean_model();
-            }
+        let unload_distance = self.unload_distance();
+
+        // picks the chunks and levels of detail that cover the view
+        self.update_selection(camera_gridspace);
+
+        let selection = self.selection.take().unwrap();
+
+        // built chunks that never got uploaded are kept in the cache instead
+        let left_behind: Vec<ChunkKey> = self.ready_chunks.keys()
+            .filter(|key| !selection.kept.contains(key))
+            .copied()
+            .collect();
+
+        for key in left_behind {
+            let chunk = self.ready_chunks.remove(&key).unwrap();
+            self.chunk_cache.insert(key, chunk);
         }
 
-        // remove models outside of gridspace
-        self.model_data.retain(|key, value| {
-            !ChunkManager::check_offset_out_of_gridspace(key, &camera_gridspace)
+        self.connectivity_data.retain(|offset, _| {
+            chunk_space::chunk_within_radius(*offset, camera_gridspace, unload_distance)
         });
 
-        // repeat with chunks (TEMPORARY MEMORY SAVING)
-        self.chunk_data.retain(|key, value| {
-            !ChunkManager::check_offset_out_of_gridspace(key, &camera_gridspace)
-        });
+        // collects the chunks the workers have finished, caching any that the
+        // camera has already moved away from
+        for result in self.workers.receive_finished() {
+            self.pending_chunks.remove(&result.key);
+
+            // chunks edited while they were being built are built again
+            if self.edited_pending_chunks.remove(&result.key) {
+                continue;
+            }
+
+            if result.key.lod == 0 {
+                self.connectivity_data.insert(result.key.offset, result.chunk.connectivity());
+            }
 
-        // initialises a models vector
-        let mut models = Vec::<RawModel>::new();
-
-        // based on the camera's gridspace position, loop and generate new chunks
-        for x in (camera_gridspace.x - CHUNK_VIEW_LIMIT)..(camera_gridspace.x + CHUNK_VIEW_LIMIT) {
-            for y in (camera_gridspace.y - CHUNK_VIEW_LIMIT)..(camera_gridspace.y + CHUNK_VIEW_LIMIT) {
-                for z in (camera_gridspace.z - CHUNK_VIEW_LIMIT)..(camera_gridspace.z + CHUNK_VIEW_LIMIT) {
-
-                    // get offset for curr chunk
-                    let curr_offset = Vector3::new(x, y, z);
-
-                    // check if the chunk exists
-                    let get_chunk = self.chunk_data.get(&curr_offset);
-
-                    match get_chunk {
-                        Some(chunk) => {
-                            // check if the model exists
-                            let get_model = self.model_data.get(&curr_offset);
-
-                            match get_model {
-                                Some(model) => {
-                                    models.push(*model);
-                                }
-                                None => {
-                                    // uses the loader to create a model
-                                    let model = loader.load_to_vao(&chunk.vertices, &chunk.indices);
-                                    
-                                    models.push(model);
-
-                                    self.model_data.insert(curr_offset, model);
-                                }
-                            }
-                        }
-                        None => {
-                            // creates a new chunk
-                            let new_chunk = MCChunk::new(
-                                curr_offset,
-                                triangulation_table
-                            );
-
-                            // uses the loader to create a model
-                            let model = loader.load_to_vao(&new_chunk.vertices, &new_chunk.indices);
-                            
-                            models.push(model);
-
-                            self.chunk_data.insert(curr_offset, new_chunk);
-                            self.model_data.insert(curr_offset, model);
-                        }
+            if selection.kept.contains(&result.key) {
+                self.ready_chunks.insert(result.key, result.chunk);
+            }
+            else {
+                self.chunk_cache.insert(result.key, result.chunk);
+            }
+        }
+
+        // walks through the air from the camera to find the chunks that can be
+        // seen, reaching as far as loaded chunks are kept
+        if self.occlusion_culling {
+            let connectivity_data = &self.connectivity_data;
+
+            self.visible_chunks = occlusion::visible_chunks(camera_gridspace, unload_distance, |offset| {
+                connectivity_data.get(&offset).copied()
+            });
+
+            self.occlusion_centre = camera_gridspace;
+            self.occlusion_radius = unload_distance;
+        }
+
+        // works out what to draw. chunks still loading are stood in for by a
+        // coarser model covering them or the finer models they're replacing, so
+        // changing level of detail never leaves a hole
+        let mut stand_ins = HashSet::<ChunkKey>::new();
+        let mut drawn = HashSet::<ChunkKey>::new();
+
+        for key in selection.selected.keys() {
+            if self.model_data.contains_key(key) {
+                continue;
+            }
+
+            match self.loaded_ancestor(*key) {
+                Some(ancestor) => {
+                    stand_ins.insert(ancestor);
+                }
+                None => self.collect_loaded_descendants(*key, &mut drawn)
+            }
+        }
+
+        for key in selection.selected.keys() {
+            let covered = self.ancestors(*key).iter().any(|ancestor| stand_ins.contains(ancestor));
+
+            if self.model_data.contains_key(key) && !covered {
+                drawn.insert(*key);
+            }
+        }
+
+        drawn.extend(stand_ins.iter());
+
+        // remove models that are neither close enough to keep nor drawn, dropping
+        // a model frees its VAO and buffers
+        self.model_data.retain(|key, _| selection.kept.contains(key) || drawn.contains(key));
+
+        self.drawn_chunks = drawn.into_iter().collect();
+
+        // initialises queues for the chunks that still need work
+        let mut upload_queue = ChunkQueue::new();
+        let mut dispatch_queue = ChunkQueue::new();
+
+        let camera_forward = camera.forward();
+
+        // sorts the selected chunks by the work they need. chunks with a model
+        // for their current neighbours are done, chunks that are built or cached
+        // are waiting to be uploaded and anything else needs building unless it
+        // already is
+        for (key, transition_mask) in selection.selected.iter() {
+            if let Some(chunk_model) = self.model_data.get(key) {
+                if chunk_model.transition_mask == *transition_mask && !chunk_model.outdated {
+                    continue;
+                }
+            }
+
+            let priority = self.chunk_priority(*key, camera_position, camera_forward);
+
+            if self.ready_chunks.contains_key(key) || self.chunk_cache.contains(key) {
+                upload_queue.push(*key, priority);
+            }
+            else if !self.pending_chunks.contains(key) {
+                dispatch_queue.push(*key, priority);
+            }
+        }
+
+        // uploads the most important chunks until the budget for the frame runs
+        // out, which has to happen on this thread since it owns the GL context.
+        // at least one chunk is uploaded each frame so loading always progresses
+        let upload_start = Instant::now();
+        let mut upload_count = 0;
+
+        while upload_count == 0 || (upload_count < self.budget.max_uploads && upload_start.elapsed() < self.budget.max_upload_time) {
+            let curr_key = match upload_queue.pop() {
+                Some(key) => key,
+                None => break
+            };
+
+            // takes the chunk from the workers' results or the cache. chunks put
+            // back into a full cache earlier in the frame can evict ones queued
+            // after them, which are skipped here and built again next frame
+            let chunk = match self.ready_chunks.remove(&curr_key) {
+                Some(chunk) => chunk,
+                None => {
+                    let chunk = match self.chunk_cache.take(&curr_key) {
+                        Some(chunk) => chunk,
+                        None => continue
                     };
+
+                    if curr_key.lod == 0 {
+                        self.connectivity_data.insert(curr_key.offset, chunk.connectivity());
+                    }
+
+                    chunk
                 }
+            };
+
+            // uses the loader to create a model, joined to any finer neighbours
+            let transition_mask = selection.selected[&curr_key];
+            let (vertices, normals, indices) = chunk.mesh_with_transitions(transition_mask);
+
+            let model = if indices.is_empty() {
+                None
+            }
+            else {
+                Some(loader.load_to_vao(&vertices, &normals, &indices))
+            };
+
+            // any model it replaces is dropped here
+            self.model_data.insert(curr_key, ChunkModel {
+                model,
+                transition_mask,
+                outdated: false
+            });
+
+            // the chunk's data stays in the cache while there's room for it
+            self.chunk_cache.insert(curr_key, chunk);
+
+            upload_count += 1;
+        }
+
+        // sends the most important missing chunks off to the workers, keeping the
+        // worker queue short so newly important chunks don't wait behind old ones
+        let mut dispatch_count = 0;
+
+        while dispatch_count < self.budget.max_dispatches && self.pending_chunks.len() < self.budget.max_in_flight {
+            let curr_key = match dispatch_queue.pop() {
+                Some(key) => key,
+                None => break
+            };
+
+            self.pending_chunks.insert(curr_key);
+
+            // meshes the densities saved on disk rather than sampling them again
+            match self.take_saved_samples(curr_key) {
+                Some(samples) => self.workers.dispatch_samples(curr_key, samples),
+                None => self.workers.dispatch(curr_key)
+            }
+
+            self.chunk_cache.record_miss();
+
+            dispatch_count += 1;
+        }
+
+        self.selection = Some(selection);
+    }
+
+    //---------------------------
+
+    // applies a brush to the density and marks every chunk whose samples it
+    // changed, at every level of detail, to be remeshed. chunks that only
+    // share border samples with the edit are included, so the seams between
+    // them stay closed. gives back how many chunks were marked and whether
+    // the edit fit in the history to be undone
+    pub fn apply_brush(&mut self, brush: &Brush) -> BrushOutcome {
+        let operation = match self.density.apply_brush(brush) {
+            Some(operation) => operation,
+            None => return BrushOutcome { marked_chunks: 0, undoable: true }
+        };
+
+        let marked_chunks = self.mark_edited(operation.min_sample, operation.max_sample);
+        let undoable = self.edit_history.record(operation);
+
+        BrushOutcome { marked_chunks, undoable }
+    }
+
+    // takes back the most recent edit, giving back how many chunks have to
+    // be remeshed or None if there was nothing to undo
+    pub fn undo(&mut self) -> Option<usize> {
+        let operation = self.edit_history.undo()?;
+        self.density.undo(operation);
+
+        let (min_sample, max_sample) = (operation.min_sample, operation.max_sample);
+
+        Some(self.mark_edited(min_sample, max_sample))
+    }
+
+    // makes the most recently undone edit again
+    pub fn redo(&mut self) -> Option<usize> {
+        let operation = self.edit_history.redo()?;
+        self.density.redo(operation);
+
+        let (min_sample, max_sample) = (operation.min_sample, operation.max_sample);
+
+        Some(self.mark_edited(min_sample, max_sample))
+    }
+
+    // gets the number of full detail samples that have been edited
+    pub fn edited_sample_count(&self) -> usize {
+        self.density.edited_sample_count()
+    }
+
+    // throws away the built data of every chunk that reads a sample in the
+    // range, and flags their models to be replaced
+    fn mark_edited(&mut self, min_sample: Vector3<i32>, max_sample: Vector3<i32>) -> usize {
+        let cells = chunk_space::cells_per_chunk(self.world_config.chunk_bounds);
+        let padding = self.mesher_padding as i32;
+
+        let mut edited = HashSet::new();
+
+        for lod in 0..=MAX_LOD_LEVELS {
+            // coarser chunks only see the edited samples that land on their
+            // grid, plus the ones on the grid below theirs that their
+            // transition cells read a sample either side of their faces
+            edited.extend(chunks_reading(min_sample, max_sample, lod, lod, cells, padding));
+
+            if lod > 0 && self.mesher_joins_levels {
+                edited.extend(chunks_reading(min_sample, max_sample, lod - 1, lod, cells, 1));
+            }
+        }
+
+        let mut marked = 0;
+
+        for key in edited {
+            let mut touched = self.ready_chunks.remove(&key).is_some();
+            touched |= self.chunk_cache.discard(&key);
+
+            if let Some(region_store) = &mut self.region_store {
+                region_store.discard_samples(key);
+            }
+
+            if let Some(chunk_model) = self.model_data.get_mut(&key) {
+                chunk_model.outdated = true;
+                touched = true;
+            }
+
+            if self.pending_chunks.contains(&key) {
+                self.edited_pending_chunks.insert(key);
+                touched = true;
+            }
+
+            if touched {
+                marked += 1;
+            }
+        }
+
+        marked
+    }
+
+    //---------------------------
+
+    // opens a directory to save the world to, loading the edits already saved
+    // there. this has to happen before any chunks are built, as they'd be
+    // missing the loaded edits. regions that can't be read are reported along
+    // with the other region errors and skipped. gives back how many edited
+    // samples were loaded
+    pub fn open_world(&mut self, directory: &Path) -> Result<usize, String> {
+        let mut region_store = RegionStore::open(directory, &self.world_config)?;
+        let edits = region_store.read_edits(&mut self.region_errors)?;
+        let edit_count = edits.len();
+
+        self.density.load_edits(edits);
+        self.edit_history = EditHistory::new(self.edit_history.byte_budget());
+        self.region_store = Some(region_store);
+
+        Ok(edit_count)
+    }
+
+    // saves the edits and the sampled densities of every chunk still held on
+    // the CPU into the open world directory. gives back how many region files
+    // were written
+    pub fn save_world(&mut self) -> Result<usize, String> {
+        let region_store = self.region_store.as_mut().ok_or(String::from("no world directory has been opened"))?;
+
+        let samples: Vec<(ChunkKey, &ChunkSamples)> = self.ready_chunks.iter()
+            .chain(self.chunk_cache.chunks())
+            .map(|(key, chunk)| (*key, chunk.samples()))
+            .collect();
+
+        region_store.save(&self.density.edits(), &samples, &mut self.region_errors)
+    }
+
+    // takes the problems hit reading region files, each one only reported
+    // once when loading before the chunks it held are generated instead, and
+    // again on every save that leaves it as it is
+    pub fn take_region_errors(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.region_errors)
+    }
+
+    // gets a chunk's saved samples if there are any and they're padded
+    // enough for the mesher
+    fn take_saved_samples(&mut self, key: ChunkKey) -> Option<ChunkSamples> {
+        let region_store = self.region_store.as_mut()?;
+
+        match region_store.take_samples(key) {
+            Ok(samples) => samples.filter(|samples| samples.padding() >= self.mesher_padding),
+            Err(error) => {
+                self.region_errors.push(error);
+                None
             }
         }
+    }
+
+    // picks the chunks again if the camera has changed chunk or the view changed
+    fn update_selection(&mut self, camera_chunk: Vector3<i32>) {
+        if let Some(selection) = &self.selection {
+            if selection.camera_chunk == camera_chunk &&
+                selection.view_distance == self.view_distance &&
+                selection.unload_margin == self.unload_margin &&
+                selection.lod_levels == self.lod_levels {
+                return;
+            }
+        }
+
+        let selected = chunk_lod::select_chunks(camera_chunk, self.view_distance, self.lod_levels);
 
-        models
+        // the same selection made further out gives the chunks to hang on to,
+        // so the edge of the view doesn't load and unload as the camera moves
+        let mut kept = chunk_lod::select_chunks(camera_chunk, self.unload_distance(), self.lod_levels);
+        kept.extend(selected.iter());
+
+        self.selection = Some(ChunkSelection {
+            camera_chunk,
+            view_distance: self.view_distance,
+            unload_margin: self.unload_margin,
+            lod_levels: self.lod_levels,
+
+            selected: chunk_lod::transition_masks(&selected),
+            kept
+        });
     }
 
-    // gets whether the offset satisfies being within the gridspace
-    fn check_offset_out_of_gridspace(offset: &Vector3<i32>, camera_gridspace: &Vector3<i32>) -> bool {
-        offset.x > camera_gridspace.x + CHUNK_VIEW_LIMIT ||
-        offset.x < camera_gridspace.x - CHUNK_VIEW_LIMIT ||
-        offset.y > camera_gridspace.y + CHUNK_VIEW_LIMIT ||
-        offset.y < camera_gridspace.y - CHUNK_VIEW_LIMIT ||
-        offset.z > camera_gridspace.z + CHUNK_VIEW_LIMIT ||
-        offset.z < camera_gridspace.z - CHUNK_VIEW_LIMIT
+    // gets the coarser chunks containing a chunk, nearest first
+    fn ancestors(&self, key: ChunkKey) -> Vec<ChunkKey> {
+        let mut ancestors = Vec::new();
+        let mut ancestor = key;
+
+        while ancestor.lod < self.lod_levels {
+            ancestor = ancestor.parent();
+            ancestors.push(ancestor);
+        }
+
+        ancestors
+    }
+
+    // gets the nearest coarser chunk containing a chunk that has a model
+    fn loaded_ancestor(&self, key: ChunkKey) -> Option<ChunkKey> {
+        self.ancestors(key).into_iter().find(|ancestor| self.model_data.contains_key(ancestor))
     }
-}
\ No newline at end of file
+
+    // gathers the finer chunks inside a chunk that have models
+    fn collect_loaded_descendants(&self, key: ChunkKey, loaded: &mut HashSet<ChunkKey>) {
+        for child in key.children() {
+            if self.model_data.contains_key(&child) {
+                loaded.insert(child);
+            }
+            else {
+                self.collect_loaded_descendants(child, loaded);
+            }
+        }
+    }
+
+    // scores a chunk for scheduling, lower scores are handled first
+    fn chunk_priority(&self, key: ChunkKey, camera_position: Vector3<f32>, camera_forward: Vector3<f32>) -> f32 {
+        chunk_scheduler::chunk_priority(
+            key.offset,
+            camera_position,
+            camera_forward,
+            self.world_config.chunk_bounds,
+            key.spacing(self.world_config.spacing_per_vertex)
+        )
+    }
+}
+
+//---------------------------
+
+// gets the chunks at a level of detail that read a range of full detail
+// samples from the grid of another level, the same or one finer, reaching a
+// padding of that grid's samples past their border
+fn chunks_reading(
+    min_sample: Vector3<i32>,
+    max_sample: Vector3<i32>,
+    grid_lod: u32,
+    lod: u32,
+    cells: i32,
+    padding: i32
+) -> Vec<ChunkKey> {
+    // only the edited samples that land on the grid are seen
+    let scale = 1 << grid_lod;
+
+    let low = min_sample.map(|value| div_ceil(value, scale));
+    let high = max_sample.map(|value| value.div_euclid(scale));
+
+    if low.x > high.x || low.y > high.y || low.z > high.z {
+        return Vec::new();
+    }
+
+    // gets the chunks whose samples, padding included, reach the range, with
+    // the chunk size counted on the grid
+    let chunk_cells = cells << (lod - grid_lod);
+
+    let first = low.map(|value| div_ceil(value - padding - chunk_cells, chunk_cells));
+    let last = high.map(|value| (value + padding).div_euclid(chunk_cells));
+
+    let mut chunks = Vec::new();
+
+    for x in first.x..=last.x {
+        for y in first.y..=last.y {
+            for z in first.z..=last.z {
+                chunks.push(ChunkKey::new(lod, Vector3::new(x, y, z)));
+            }
+        }
+    }
+
+    chunks
+}
+
+// divides, rounding up rather than towards zero
+fn div_ceil(value: i32, divisor: i32) -> i32 {
+    -((-value).div_euclid(divisor))
+}
+
+//---------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the offsets of the chunks reading a range, sorted so they can be compared
+    fn offsets_reading(min_sample: Vector3<i32>, max_sample: Vector3<i32>, grid_lod: u32, lod: u32, padding: i32) -> Vec<[i32; 3]> {
+        let cells = chunk_space::cells_per_chunk(DEFAULT_CHUNK_BOUNDS);
+
+        let mut offsets: Vec<[i32; 3]> = chunks_reading(min_sample, max_sample, grid_lod, lod, cells, padding)
+            .iter()
+            .map(|key| {
+                assert_eq!(key.lod, lod);
+                key.offset.into()
+            })
+            .collect();
+
+        offsets.sort();
+        offsets
+    }
+
+    #[test]
+    fn a_sample_shared_by_two_chunks_marks_both() {
+        let cells = chunk_space::cells_per_chunk(DEFAULT_CHUNK_BOUNDS);
+        let sample = Vector3::new(cells, 2, 2);
+
+        assert_eq!(offsets_reading(sample, sample, 0, 0, 0), vec![[0, 0, 0], [1, 0, 0]]);
+
+        // one past the border is only read by the second chunk, until the
+        // first pads out to it
+        let inside = Vector3::new(cells + 1, 2, 2);
+
+        assert_eq!(offsets_reading(inside, inside, 0, 0, 0), vec![[1, 0, 0]]);
+        assert_eq!(offsets_reading(inside, inside, 0, 0, 1), vec![[0, 0, 0], [1, 0, 0]]);
+
+        // and the padding reaches past the other borders too
+        let corner = Vector3::new(cells + 1, 1, 1);
+
+        assert_eq!(offsets_reading(corner, corner, 0, 0, 1), vec![
+            [0, -1, -1], [0, -1, 0], [0, 0, -1], [0, 0, 0],
+            [1, -1, -1], [1, -1, 0], [1, 0, -1], [1, 0, 0]
+        ]);
+    }
+
+    #[test]
+    fn coarser_chunks_only_read_samples_on_their_grid() {
+        let cells = chunk_space::cells_per_chunk(DEFAULT_CHUNK_BOUNDS);
+
+        // odd samples fall between the samples of the level above
+        let odd = Vector3::new(3, 3, 3);
+        assert!(offsets_reading(odd, odd, 1, 1, 0).is_empty());
+
+        // a range reaching an even sample on every axis rounds in to it
+        assert_eq!(offsets_reading(Vector3::new(3, 3, 3), Vector3::new(4, 4, 4), 1, 1, 0), vec![[0, 0, 0]]);
+
+        // the coarse chunks' border is at twice the full detail chunks'
+        let border = Vector3::new(cells * 2, 2, 2);
+        assert_eq!(offsets_reading(border, border, 1, 1, 0), vec![[0, 0, 0], [1, 0, 0]]);
+
+        // negative samples round towards the grid sample above them
+        assert_eq!(offsets_reading(Vector3::new(-3, 2, 2), Vector3::new(-2, 2, 2), 1, 1, 0), vec![[-1, 0, 0]]);
+    }
+
+    #[test]
+    fn transition_cells_read_the_finer_samples_beside_their_faces() {
+        let cells = chunk_space::cells_per_chunk(DEFAULT_CHUNK_BOUNDS);
+
+        // a full detail sample just past a coarse chunk border, which neither
+        // coarse chunk samples but both read through their transition cells
+        let beside = Vector3::new(cells * 2 + 1, 2, 2);
+
+        assert!(offsets_reading(beside, beside, 1, 1, 0).is_empty());
+        assert_eq!(offsets_reading(beside, beside, 0, 1, 1), vec![[0, 0, 0], [1, 0, 0]]);
+
+        // further in it's only read by the chunk it's in
+        let further = Vector3::new(cells * 2 + 3, 2, 2);
+
+        assert_eq!(offsets_reading(further, further, 0, 1, 1), vec![[1, 0, 0]]);
+    }
+}
@@ -0,0 +1,189 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::time::Duration;
+
+use cgmath::*;
+
+use crate::utils::chunk_space;
+
+use super::chunk_lod::ChunkKey;
+
+//------------------------
+
+// how much chunk work is allowed each frame, so frame time stays flat while
+// the camera is moving
+#[derive(Clone, Copy, Debug)]
+pub struct ChunkBudget {
+    // chunks sent to the workers per frame, and the most that can be in flight
+    pub max_dispatches: usize,
+    pub max_in_flight: usize,
+
+    // chunks uploaded to the GPU per frame, and the time that can be spent on them
+    pub max_uploads: usize,
+    pub max_upload_time: Duration
+}
+
+// a chunk waiting for work, ordered so the most important chunk sits at the
+// top of a max heap
+struct ScheduledChunk {
+    priority: f32,
+    key: ChunkKey
+}
+
+// chunks waiting for work, nearest first and favouring the view direction
+pub struct ChunkQueue {
+    heap: BinaryHeap<ScheduledChunk>
+}
+
+//------------------------
+
+impl ChunkBudget {
+    pub fn new() -> ChunkBudget {
+        ChunkBudget {
+            max_dispatches: 8,
+            max_in_flight: 32,
+
+            max_uploads: 4,
+            max_upload_time: Duration::from_millis(4)
+        }
+    }
+}
+
+impl ChunkQueue {
+    pub fn new() -> ChunkQueue {
+        ChunkQueue {
+            heap: BinaryHeap::new()
+        }
+    }
+
+    pub fn push(&mut self, key: ChunkKey, priority: f32) {
+        self.heap.push(ScheduledChunk {
+            priority,
+            key
+        });
+    }
+
+    // takes the chunk with the lowest priority score
+    pub fn pop(&mut self) -> Option<ChunkKey> {
+        self.heap.pop().map(|scheduled| scheduled.key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+}
+
+//------------------------
+
+// scores a chunk by its distance from the camera in chunks, scaled up to
+// double for chunks directly behind the camera, where lower scores go first
+pub fn chunk_priority(
+    offset: Vector3<i32>,
+    camera_position: Vector3<f32>,
+    camera_forward: Vector3<f32>,
+    chunk_bounds: usize,
+    spacing_per_vertex: f32
+) -> f32 {
+    // gets the world size of a chunk and the position of its centre
+    let chunk_size = chunk_space::cells_per_chunk(chunk_bounds) as f32 * spacing_per_vertex;
+    let chunk_centre = chunk_space::chunk_origin_world(offset, chunk_bounds, spacing_per_vertex)
+        + Vector3::new(chunk_size, chunk_size, chunk_size) * 0.5;
+
+    let to_chunk = chunk_centre - camera_position;
+    let distance = to_chunk.magnitude();
+
+    // the chunk around the camera always goes first
+    if distance <= f32::EPSILON {
+        return 0.0;
+    }
+
+    // 1 when the chunk is straight ahead, -1 when it's straight behind
+    let facing = camera_forward.dot(to_chunk / distance);
+
+    (distance / chunk_size) * (1.5 - 0.5 * facing)
+}
+
+//------------------------
+
+impl PartialEq for ScheduledChunk {
+    fn eq(&self, other: &ScheduledChunk) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ScheduledChunk {}
+
+impl PartialOrd for ScheduledChunk {
+    fn partial_cmp(&self, other: &ScheduledChunk) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ScheduledChunk {
+    // reversed so that the lowest score comes out of the max heap first
+    fn cmp(&self, other: &ScheduledChunk) -> Ordering {
+        other.priority.total_cmp(&self.priority)
+    }
+}
+
+//------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHUNK_BOUNDS: usize = 9;
+
+    // scores a chunk for a camera in the middle of chunk (0, 0, 0), looking down +x
+    fn priority(x: i32, y: i32, z: i32) -> f32 {
+        chunk_priority(Vector3::new(x, y, z), Vector3::new(4.0, 4.0, 4.0), Vector3::unit_x(), CHUNK_BOUNDS, 1.0)
+    }
+
+    #[test]
+    fn nearer_chunks_and_chunks_ahead_score_lower() {
+        assert_eq!(priority(0, 0, 0), 0.0);
+        assert!((priority(1, 0, 0) - 1.0).abs() < 1e-5);
+        assert!((priority(0, 0, 1) - 1.5).abs() < 1e-5);
+        assert!((priority(-1, 0, 0) - 2.0).abs() < 1e-5);
+
+        assert!(priority(1, 0, 0) < priority(2, 0, 0));
+        assert!(priority(0, 1, 0) < priority(0, 2, 0));
+    }
+
+    #[test]
+    fn the_queue_hands_out_the_nearest_chunk_in_the_view_direction_first() {
+        let offsets = [
+            Vector3::new(-2, 0, 0),
+            Vector3::new(0, 0, 1),
+            Vector3::new(3, 0, 0),
+            Vector3::new(0, 0, 0),
+            Vector3::new(-1, 0, 0),
+            Vector3::new(1, 0, 0)
+        ];
+
+        let mut queue = ChunkQueue::new();
+
+        for offset in offsets.iter() {
+            queue.push(ChunkKey::new(0, *offset), priority(offset.x, offset.y, offset.z));
+        }
+
+        assert_eq!(queue.len(), offsets.len());
+
+        let order: Vec<Vector3<i32>> = std::iter::from_fn(|| queue.pop()).map(|key| key.offset).collect();
+
+        assert_eq!(order, vec![
+            Vector3::new(0, 0, 0),
+            Vector3::new(1, 0, 0),
+            Vector3::new(0, 0, 1),
+            Vector3::new(-1, 0, 0),
+            Vector3::new(3, 0, 0),
+            Vector3::new(-2, 0, 0)
+        ]);
+
+        assert!(queue.is_empty());
+    }
+}
\ No newline at end of file
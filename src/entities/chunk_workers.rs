@@ -0,0 +1,135 @@
+use std::sync::Arc;
+use std::thread;
+
+use crossbeam::channel::{self, Receiver, Sender};
+
+use crate::utils::world_config::WorldConfig;
+
+use super::{
+    basic_marching_cubes::{ChunkSamples, MCChunk},
+    chunk_lod::ChunkKey,
+    density_field::DensityField,
+    mesher::Mesher
+};
+
+//------------------------
+
+// a chunk for a worker to build, from samples that have already been taken
+// if there are any
+struct ChunkJob {
+    key: ChunkKey,
+    samples: Option<ChunkSamples>
+}
+
+// a chunk that a worker has finished sampling and meshing
+pub struct ChunkResult {
+    pub key: ChunkKey,
+    pub chunk: MCChunk
+}
+
+// a pool of background threads that build chunks, jobs go out over one
+// channel and finished chunks come back over another so that only the GL
+// upload is left for the render thread
+pub struct ChunkWorkerPool {
+    job_sender: Option<Sender<ChunkJob>>,
+    result_receiver: Receiver<ChunkResult>,
+
+    workers: Vec<thread::JoinHandle<()>>
+}
+
+//------------------------
+
+impl ChunkWorkerPool {
+    pub fn new(
+        worker_count: usize,
+        world_config: Arc<WorldConfig>,
+        density: Arc<dyn DensityField>,
+        mesher: Arc<dyn Mesher>
+    ) -> ChunkWorkerPool {
+        // creates the job and result channels
+        let (job_sender, job_receiver) = channel::unbounded::<ChunkJob>();
+        let (result_sender, result_receiver) = channel::unbounded::<ChunkResult>();
+
+        // spawns the workers, each taking jobs until the job channel closes
+        let workers = (0..worker_count.max(1)).map(|index| {
+            let job_receiver = job_receiver.clone();
+            let result_sender = result_sender.clone();
+
+            let world_config = Arc::clone(&world_config);
+            let density = Arc::clone(&density);
+            let mesher = Arc::clone(&mesher);
+
+            thread::Builder::new()
+                .name(format!("chunk-worker-{}", index))
+                .spawn(move || {
+                    for ChunkJob { key, samples } in job_receiver.iter() {
+                        let chunk = match samples {
+                            Some(samples) => MCChunk::from_samples(key, &world_config, samples, density.as_ref(), mesher.as_ref()),
+                            None => MCChunk::new(key, &world_config, density.as_ref(), mesher.as_ref())
+                        };
+
+                        // stops if the pool has been dropped
+                        if result_sender.send(ChunkResult { key, chunk }).is_err() {
+                            break;
+                        }
+                    }
+                })
+                .unwrap()
+        }).collect();
+
+        ChunkWorkerPool {
+            job_sender: Some(job_sender),
+            result_receiver,
+
+            workers
+        }
+    }
+
+    // gets a worker count that leaves one core free for the render thread
+    pub fn default_worker_count() -> usize {
+        thread::available_parallelism()
+            .map(|count| count.get().saturating_sub(1))
+            .unwrap_or(1)
+            .max(1)
+    }
+
+    //--------------------
+
+    // queues a chunk to be built by the next free worker
+    pub fn dispatch(&self, key: ChunkKey) {
+        self.send(ChunkJob { key, samples: None });
+    }
+
+    // queues a chunk to be built from samples that were already taken, such
+    // as ones loaded from disk
+    pub fn dispatch_samples(&self, key: ChunkKey, samples: ChunkSamples) {
+        self.send(ChunkJob { key, samples: Some(samples) });
+    }
+
+    fn send(&self, job: ChunkJob) {
+        if let Some(job_sender) = &self.job_sender {
+            job_sender.send(job).unwrap();
+        }
+    }
+
+    // takes every chunk that has finished since the last call, without blocking
+    pub fn receive_finished(&self) -> Vec<ChunkResult> {
+        self.result_receiver.try_iter().collect()
+    }
+}
+
+//------------------------
+
+impl Drop for ChunkWorkerPool {
+    fn drop(&mut self) {
+        // closes the job channel so the workers stop once they're idle
+        self.job_sender = None;
+
+        // drains the results so no worker is left waiting, then joins them
+        while self.result_receiver.try_recv().is_ok() {}
+
+        for worker in self.workers.drain(..) {
+            worker.join().ok();
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+//------------------------
+
+// densities are rounded to steps of a 4096th, far finer than the surface can
+// show, so that those within the range 16 bits of steps cover can be kept as
+// a step count
+const STEPS_PER_UNIT: f32 = 4096.0;
+
+//------------------------
+
+// the densities of a grid, kept as small as they can be. a chunk's border
+// samples have to match its neighbour's exactly, so every density is rounded
+// to the same steps on its own, whatever the rest of the chunk holds, and
+// nothing is lost after that. chunks of a single density keep one value,
+// chunks with few distinct densities, such as the flat cells of worley noise,
+// keep each distinct density once with a byte per sample picking one, smooth
+// fields keep 16 bits of steps per sample, and anything with densities past
+// the steps' range keeps every density
+#[derive(Clone, Debug)]
+pub enum CompactWeights {
+    Constant { value: f32, len: usize },
+    Palette { palette: Vec<f32>, indices: Vec<u8> },
+    Quantised(Vec<i16>),
+    Full(Vec<f32>)
+}
+
+//------------------------
+
+impl CompactWeights {
+    // packs the densities, picking whichever layout they fit in best
+    pub fn new(values: Vec<f32>) -> CompactWeights {
+        let values: Vec<f32> = values.into_iter().map(round_density).collect();
+
+        let mut palette = Vec::new();
+        let mut palette_lookup = HashMap::<u32, u8>::new();
+        let mut indices = Vec::with_capacity(values.len());
+
+        for value in values.iter() {
+            let index = match palette_lookup.get(&value.to_bits()) {
+                Some(index) => *index,
+                None => {
+                    // more densities than a byte can pick between are kept as steps
+                    if palette.len() > u8::MAX as usize {
+                        return CompactWeights::quantised(values);
+                    }
+
+                    palette.push(*value);
+                    palette_lookup.insert(value.to_bits(), (palette.len() - 1) as u8);
+
+                    (palette.len() - 1) as u8
+                }
+            };
+
+            indices.push(index);
+        }
+
+        match palette.len() {
+            0 => CompactWeights::Full(values),
+            1 => CompactWeights::Constant { value: palette[0], len: values.len() },
+            _ => CompactWeights::Palette { palette, indices }
+        }
+    }
+
+    // keeps the densities as steps, or as they are if any are out of the steps' range
+    fn quantised(values: Vec<f32>) -> CompactWeights {
+        match values.iter().map(|value| density_steps(*value)).collect::<Option<Vec<i16>>>() {
+            Some(steps) => CompactWeights::Quantised(steps),
+            None => CompactWeights::Full(values)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            CompactWeights::Constant { len, .. } => *len,
+            CompactWeights::Palette { indices, .. } => indices.len(),
+            CompactWeights::Quantised(steps) => steps.len(),
+            CompactWeights::Full(values) => values.len()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> f32 {
+        match self {
+            CompactWeights::Constant { value, .. } => *value,
+            CompactWeights::Palette { palette, indices } => palette[indices[index] as usize],
+            CompactWeights::Quantised(steps) => steps[index] as f32 / STEPS_PER_UNIT,
+            CompactWeights::Full(values) => values[index]
+        }
+    }
+
+    // unpacks every density, in the order they were packed
+    pub fn to_vec(&self) -> Vec<f32> {
+        (0..self.len()).map(|index| self.get(index)).collect()
+    }
+
+    // gets roughly how many bytes the densities take up
+    pub fn memory_size(&self) -> usize {
+        match self {
+            CompactWeights::Constant { .. } => std::mem::size_of::<f32>(),
+            CompactWeights::Palette { palette, indices } => {
+                std::mem::size_of_val(palette.as_slice()) + std::mem::size_of_val(indices.as_slice())
+            }
+            CompactWeights::Quantised(steps) => std::mem::size_of_val(steps.as_slice()),
+            CompactWeights::Full(values) => std::mem::size_of_val(values.as_slice())
+        }
+    }
+}
+
+//------------------------
+
+// rounds a density to the nearest step, the way every packed density is, so
+// anything sampled outside a grid can still line up with it. densities past
+// the steps' range, and nans, are left as they are
+pub fn round_density(value: f32) -> f32 {
+    match density_steps(value) {
+        Some(steps) => steps as f32 / STEPS_PER_UNIT,
+        None => value
+    }
+}
+
+// gets the number of steps nearest a density, if it's within their range
+fn density_steps(value: f32) -> Option<i16> {
+    let steps = (value * STEPS_PER_UNIT).round();
+
+    if steps >= i16::MIN as f32 && steps <= i16::MAX as f32 {
+        Some(steps as i16)
+    }
+    else {
+        None
+    }
+}
+
+//------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a smooth field like the noise the caves are sampled from, with a
+    // different density for every sample
+    fn smooth_values(len: usize) -> Vec<f32> {
+        (0..len).map(|index| (index as f32 * 0.37).sin() * 0.9 + (index as f32 * 0.011).cos() * 0.3).collect()
+    }
+
+    #[test]
+    fn smooth_fields_are_kept_as_steps_within_tolerance() {
+        let values = smooth_values(10 * 10 * 10);
+        let weights = CompactWeights::new(values.clone());
+
+        assert!(matches!(weights, CompactWeights::Quantised(_)));
+        assert_eq!(weights.len(), values.len());
+
+        for (index, value) in values.iter().enumerate() {
+            assert!((weights.get(index) - value).abs() <= 0.5 / STEPS_PER_UNIT, "density {} came back as {}", value, weights.get(index));
+        }
+
+        assert_eq!(weights.memory_size() * 2, std::mem::size_of_val(values.as_slice()));
+    }
+
+    #[test]
+    fn packing_unpacked_densities_changes_nothing() {
+        // reloaded chunks are packed again, and have to match the neighbours that weren't
+        let weights = CompactWeights::new(smooth_values(1000));
+        let repacked = CompactWeights::new(weights.to_vec());
+
+        let bits = |weights: &CompactWeights| weights.to_vec().iter().map(|weight| weight.to_bits()).collect::<Vec<u32>>();
+
+        assert_eq!(bits(&weights), bits(&repacked));
+    }
+
+    #[test]
+    fn a_density_rounds_the_same_in_every_layout() {
+        let shared = 0.123_456_7;
+
+        // a constant chunk, a few densities and a smooth field all holding the same density
+        let constant = CompactWeights::new(vec![shared; 8]);
+        let palette = CompactWeights::new(vec![shared, -0.5, 0.25, shared]);
+
+        let mut smooth = smooth_values(1000);
+        smooth[0] = shared;
+        let smooth = CompactWeights::new(smooth);
+
+        assert!(matches!(constant, CompactWeights::Constant { .. }));
+        assert!(matches!(palette, CompactWeights::Palette { .. }));
+
+        let rounded = round_density(shared);
+
+        for weights in [&constant, &palette, &smooth].iter() {
+            assert_eq!(weights.get(0).to_bits(), rounded.to_bits());
+        }
+    }
+
+    #[test]
+    fn flat_and_out_of_range_densities_keep_their_layouts() {
+        let flat: Vec<f32> = (0..1000).map(|index| (index / 100) as f32 * 0.25).collect();
+        let flat_weights = CompactWeights::new(flat.clone());
+
+        assert!(matches!(flat_weights, CompactWeights::Palette { .. }));
+        assert_eq!(flat_weights.to_vec(), flat);
+        assert!(flat_weights.memory_size() < std::mem::size_of_val(flat.as_slice()) / 3);
+
+        // one density past what the steps can count keeps every density
+        let mut wide = smooth_values(1000);
+        wide[500] = 1000.0;
+
+        let wide_weights = CompactWeights::new(wide.clone());
+
+        assert!(matches!(wide_weights, CompactWeights::Full(_)));
+        assert_eq!(wide_weights.get(500), 1000.0);
+        assert_eq!(wide_weights.get(0), round_density(wide[0]));
+    }
+}
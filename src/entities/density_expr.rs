@@ -0,0 +1,381 @@
+use std::ops::{Add, Mul};
+
+use cgmath::*;
+
+use noise::{Seedable, Worley};
+
+// noise 0.7 glob-exports two structs named Perlin, both are 3d perlin noise
+use noise::Perlin;
+
+use super::density_field::{DensityField, NoiseDensity};
+
+//------------------------
+
+// offsets used to read three unrelated values out of one warp field
+const WARP_OFFSET_Y: f64 = 31.7;
+const WARP_OFFSET_Z: f64 = 73.1;
+
+//------------------------
+
+// a density field written as a tree of building blocks, so cave looks can be
+// described as data rather than as changes to the mesher
+pub enum DensityExpr {
+    // any other density field, usually a noise generator
+    Field(Box<dyn DensityField>),
+
+    // the same density everywhere
+    Constant(f32),
+
+    // combines two fields sample by sample
+    Add(Box<DensityExpr>, Box<DensityExpr>),
+    Multiply(Box<DensityExpr>, Box<DensityExpr>),
+    Min(Box<DensityExpr>, Box<DensityExpr>),
+    Max(Box<DensityExpr>, Box<DensityExpr>),
+
+    // a min that blends the two fields where they're within `smoothness` of each other
+    SmoothMin {
+        a: Box<DensityExpr>,
+        b: Box<DensityExpr>,
+        smoothness: f32
+    },
+
+    // changes the output of a field
+    Scale {
+        source: Box<DensityExpr>,
+        scale: f32
+    },
+    Offset {
+        source: Box<DensityExpr>,
+        offset: f32
+    },
+    Clamp {
+        source: Box<DensityExpr>,
+        min: f32,
+        max: f32
+    },
+
+    // changes where a field is sampled
+    ScaleDomain {
+        source: Box<DensityExpr>,
+        scale: Vector3<f64>
+    },
+    TranslateDomain {
+        source: Box<DensityExpr>,
+        translation: Vector3<f64>
+    },
+
+    // moves each sample position by the values of another field before sampling
+    DomainWarp {
+        source: Box<DensityExpr>,
+        warp: Box<DensityExpr>,
+        strength: f64
+    },
+
+    // layers octaves of a field, each at a higher frequency and lower amplitude
+    Fractal {
+        source: Box<DensityExpr>,
+        octaves: u32,
+        lacunarity: f64,
+        persistence: f32
+    },
+
+    // grows by `gradient` for every unit below `height`, and shrinks above it
+    HeightGradient {
+        height: f64,
+        gradient: f32
+    }
+}
+
+//------------------------
+
+impl DensityExpr {
+    pub fn field<F: DensityField + 'static>(field: F) -> DensityExpr {
+        DensityExpr::Field(Box::new(field))
+    }
+
+    pub fn constant(value: f32) -> DensityExpr {
+        DensityExpr::Constant(value)
+    }
+
+    pub fn height_gradient(height: f64, gradient: f32) -> DensityExpr {
+        DensityExpr::HeightGradient {
+            height,
+            gradient
+        }
+    }
+
+    //--------------------
+
+    pub fn min(self, other: DensityExpr) -> DensityExpr {
+        DensityExpr::Min(Box::new(self), Box::new(other))
+    }
+
+    pub fn max(self, other: DensityExpr) -> DensityExpr {
+        DensityExpr::Max(Box::new(self), Box::new(other))
+    }
+
+    pub fn smooth_min(self, other: DensityExpr, smoothness: f32) -> DensityExpr {
+        DensityExpr::SmoothMin {
+            a: Box::new(self),
+            b: Box::new(other),
+            smoothness
+        }
+    }
+
+    pub fn scale(self, scale: f32) -> DensityExpr {
+        DensityExpr::Scale {
+            source: Box::new(self),
+            scale
+        }
+    }
+
+    pub fn offset(self, offset: f32) -> DensityExpr {
+        DensityExpr::Offset {
+            source: Box::new(self),
+            offset
+        }
+    }
+
+    pub fn clamp(self, min: f32, max: f32) -> DensityExpr {
+        DensityExpr::Clamp {
+            source: Box::new(self),
+            min,
+            max
+        }
+    }
+
+    pub fn scale_domain(self, scale: Vector3<f64>) -> DensityExpr {
+        DensityExpr::ScaleDomain {
+            source: Box::new(self),
+            scale
+        }
+    }
+
+    pub fn translate_domain(self, translation: Vector3<f64>) -> DensityExpr {
+        DensityExpr::TranslateDomain {
+            source: Box::new(self),
+            translation
+        }
+    }
+
+    pub fn warp(self, warp: DensityExpr, strength: f64) -> DensityExpr {
+        DensityExpr::DomainWarp {
+            source: Box::new(self),
+            warp: Box::new(warp),
+            strength
+        }
+    }
+
+    pub fn fractal(self, octaves: u32, lacunarity: f64, persistence: f32) -> DensityExpr {
+        DensityExpr::Fractal {
+            source: Box::new(self),
+            octaves,
+            lacunarity,
+            persistence
+        }
+    }
+
+    //--------------------
+
+    // worley tunnels, warped by low frequency perlin, with a denser floor. the
+    // warp is seeded one past the tunnels so the two don't line up
+    pub fn warped_tunnels(seed: u32, frequency: f64) -> DensityExpr {
+        let warp = DensityExpr::field(NoiseDensity::new(
+            Perlin::new().set_seed(seed.wrapping_add(1)),
+            frequency * 0.25
+        ));
+
+        DensityExpr::field(NoiseDensity::new(Worley::new().set_seed(seed), frequency)).warp(warp, 4.0)
+            + DensityExpr::height_gradient(-16.0, 0.02).clamp(0.0, 1.0)
+    }
+}
+
+//------------------------
+
+// adding or multiplying expressions combines them sample by sample
+impl Add for DensityExpr {
+    type Output = DensityExpr;
+
+    fn add(self, other: DensityExpr) -> DensityExpr {
+        DensityExpr::Add(Box::new(self), Box::new(other))
+    }
+}
+
+impl Mul for DensityExpr {
+    type Output = DensityExpr;
+
+    fn mul(self, other: DensityExpr) -> DensityExpr {
+        DensityExpr::Multiply(Box::new(self), Box::new(other))
+    }
+}
+
+//------------------------
+
+impl DensityField for DensityExpr {
+    fn sample(&self, world_pos: Vector3<f64>) -> f32 {
+        match self {
+            DensityExpr::Field(field) => field.sample(world_pos),
+
+            DensityExpr::Constant(value) => *value,
+
+            DensityExpr::Add(a, b) => a.sample(world_pos) + b.sample(world_pos),
+            DensityExpr::Multiply(a, b) => a.sample(world_pos) * b.sample(world_pos),
+            DensityExpr::Min(a, b) => a.sample(world_pos).min(b.sample(world_pos)),
+            DensityExpr::Max(a, b) => a.sample(world_pos).max(b.sample(world_pos)),
+
+            DensityExpr::SmoothMin { a, b, smoothness } => {
+                let a_value = a.sample(world_pos);
+                let b_value = b.sample(world_pos);
+
+                // polynomial smooth min, which falls back to a plain min when
+                // there's nothing to smooth over
+                if *smoothness <= 0.0 {
+                    return a_value.min(b_value);
+                }
+
+                let blend = (0.5 + 0.5 * (b_value - a_value) / smoothness).clamp(0.0, 1.0);
+
+                b_value + (a_value - b_value) * blend - smoothness * blend * (1.0 - blend)
+            }
+
+            DensityExpr::Scale { source, scale } => source.sample(world_pos) * scale,
+            DensityExpr::Offset { source, offset } => source.sample(world_pos) + offset,
+            DensityExpr::Clamp { source, min, max } => source.sample(world_pos).max(*min).min(*max),
+
+            DensityExpr::ScaleDomain { source, scale } => {
+                source.sample(world_pos.mul_element_wise(*scale))
+            }
+            DensityExpr::TranslateDomain { source, translation } => {
+                source.sample(world_pos + translation)
+            }
+
+            DensityExpr::DomainWarp { source, warp, strength } => {
+                // reads a displacement for each axis from offset positions in the warp field
+                let displacement = Vector3::new(
+                    warp.sample(world_pos) as f64,
+                    warp.sample(world_pos + Vector3::new(WARP_OFFSET_Y, WARP_OFFSET_Y, WARP_OFFSET_Y)) as f64,
+                    warp.sample(world_pos + Vector3::new(WARP_OFFSET_Z, WARP_OFFSET_Z, WARP_OFFSET_Z)) as f64
+                );
+
+                source.sample(world_pos + displacement * *strength)
+            }
+
+            DensityExpr::Fractal { source, octaves, lacunarity, persistence } => {
+                let mut total = 0.0;
+                let mut frequency = 1.0;
+                let mut amplitude = 1.0;
+
+                for _ in 0..*octaves {
+                    total += source.sample(world_pos * frequency) * amplitude;
+
+                    frequency *= lacunarity;
+                    amplitude *= persistence;
+                }
+
+                total
+            }
+
+            DensityExpr::HeightGradient { height, gradient } => {
+                (*height - world_pos.y) as f32 * gradient
+            }
+        }
+    }
+}
+
+//------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a field whose density is one of the coordinates of the sample, so tests
+    // can see exactly where an expression sampled it
+    struct AxisField(usize);
+
+    impl DensityField for AxisField {
+        fn sample(&self, world_pos: Vector3<f64>) -> f32 {
+            world_pos[self.0] as f32
+        }
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn smooth_min_blends_only_near_values() {
+        let origin = Vector3::new(0.0, 0.0, 0.0);
+
+        // far apart values give the plain min
+        let apart = DensityExpr::constant(0.0).smooth_min(DensityExpr::constant(10.0), 1.0);
+        assert!(close(apart.sample(origin), 0.0));
+
+        // equal values are pulled below both by a quarter of the smoothness
+        let equal = DensityExpr::constant(1.0).smooth_min(DensityExpr::constant(1.0), 1.0);
+        assert!(close(equal.sample(origin), 0.75));
+
+        // and without any smoothness it's a plain min again
+        let sharp = DensityExpr::constant(1.0).smooth_min(DensityExpr::constant(1.0), 0.0);
+        assert!(close(sharp.sample(origin), 1.0));
+    }
+
+    #[test]
+    fn domain_warp_reads_each_axis_at_its_own_offset() {
+        let sample_at = |x: f64, axis: usize| {
+            DensityExpr::field(AxisField(axis))
+                .warp(DensityExpr::field(AxisField(0)), 2.0)
+                .sample(Vector3::new(x, 0.0, 0.0))
+        };
+
+        // each axis is pushed by twice the warp field read at that axis's offset
+        assert!(close(sample_at(1.0, 0), 1.0 + 2.0));
+        assert!(close(sample_at(1.0, 1), ((1.0 + WARP_OFFSET_Y) * 2.0) as f32));
+        assert!(close(sample_at(1.0, 2), ((1.0 + WARP_OFFSET_Z) * 2.0) as f32));
+    }
+
+    #[test]
+    fn fractal_sums_octaves_at_rising_frequency() {
+        let position = Vector3::new(1.0, 0.0, 0.0);
+
+        // 1 * 1 + 2 * 0.5 + 4 * 0.25
+        let fractal = DensityExpr::field(AxisField(0)).fractal(3, 2.0, 0.5);
+        assert!(close(fractal.sample(position), 3.0));
+
+        let empty = DensityExpr::field(AxisField(0)).fractal(0, 2.0, 0.5);
+        assert!(close(empty.sample(position), 0.0));
+    }
+
+    #[test]
+    fn clamp_limits_the_density() {
+        let clamped = DensityExpr::field(AxisField(0)).clamp(-1.0, 1.0);
+
+        assert!(close(clamped.sample(Vector3::new(-5.0, 0.0, 0.0)), -1.0));
+        assert!(close(clamped.sample(Vector3::new(0.5, 0.0, 0.0)), 0.5));
+        assert!(close(clamped.sample(Vector3::new(5.0, 0.0, 0.0)), 1.0));
+    }
+
+    #[test]
+    fn height_gradient_is_solid_below_its_height() {
+        let gradient = DensityExpr::height_gradient(10.0, 0.5);
+
+        assert!(close(gradient.sample(Vector3::new(3.0, 10.0, -2.0)), 0.0));
+        assert!(close(gradient.sample(Vector3::new(0.0, 8.0, 0.0)), 1.0));
+        assert!(close(gradient.sample(Vector3::new(0.0, 12.0, 0.0)), -1.0));
+    }
+
+    #[test]
+    fn warped_tunnels_follow_the_seed() {
+        let positions: Vec<Vector3<f64>> = (0..32)
+            .map(|i| Vector3::new(i as f64 * 3.7, i as f64 * -1.3, i as f64 * 2.1))
+            .collect();
+
+        let samples = |seed: u32| -> Vec<f32> {
+            let tunnels = DensityExpr::warped_tunnels(seed, 0.125);
+
+            positions.iter().map(|&position| tunnels.sample(position)).collect()
+        };
+
+        assert_eq!(samples(7), samples(7));
+        assert_ne!(samples(7), samples(8));
+    }
+}
\ No newline at end of file
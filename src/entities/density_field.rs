@@ -0,0 +1,90 @@
+use cgmath::*;
+
+use noise::{
+    Billow,
+    Fbm,
+    NoiseFn,
+    OpenSimplex,
+    RidgedMulti,
+    Worley
+};
+
+// noise 0.7 glob-exports two structs named Perlin (the lint is allowed in
+// lib.rs), both of them are 3d perlin gradient noise
+use noise::Perlin;
+
+//------------------------
+
+// anything that can give a density for a point in the world, where samples
+// at or above the isovalue are solid and samples below it are open air
+pub trait DensityField: Send + Sync {
+    fn sample(&self, world_pos: Vector3<f64>) -> f32;
+}
+
+//------------------------
+
+// wraps a noise function so it can be used as a density field, scaling
+// world positions by a frequency before they're passed to the noise
+pub struct NoiseDensity<N> {
+    pub noise: N,
+    pub frequency: f64
+}
+
+//------------------------
+
+impl<N: NoiseFn<[f64; 3]>> NoiseDensity<N> {
+    pub fn new(noise: N, frequency: f64) -> NoiseDensity<N> {
+        NoiseDensity {
+            noise,
+            frequency
+        }
+    }
+}
+
+impl NoiseDensity<Perlin> {
+    pub fn perlin(frequency: f64) -> NoiseDensity<Perlin> {
+        NoiseDensity::new(Perlin::new(), frequency)
+    }
+}
+
+impl NoiseDensity<OpenSimplex> {
+    pub fn open_simplex(frequency: f64) -> NoiseDensity<OpenSimplex> {
+        NoiseDensity::new(OpenSimplex::new(), frequency)
+    }
+}
+
+impl NoiseDensity<Worley> {
+    pub fn worley(frequency: f64) -> NoiseDensity<Worley> {
+        NoiseDensity::new(Worley::new(), frequency)
+    }
+}
+
+impl NoiseDensity<Billow> {
+    pub fn billow(frequency: f64) -> NoiseDensity<Billow> {
+        NoiseDensity::new(Billow::new(), frequency)
+    }
+}
+
+impl NoiseDensity<RidgedMulti> {
+    pub fn ridged_multi(frequency: f64) -> NoiseDensity<RidgedMulti> {
+        NoiseDensity::new(RidgedMulti::new(), frequency)
+    }
+}
+
+impl NoiseDensity<Fbm> {
+    pub fn fbm(frequency: f64) -> NoiseDensity<Fbm> {
+        NoiseDensity::new(Fbm::new(), frequency)
+    }
+}
+
+//------------------------
+
+impl<N: NoiseFn<[f64; 3]> + Send + Sync> DensityField for NoiseDensity<N> {
+    fn sample(&self, world_pos: Vector3<f64>) -> f32 {
+        self.noise.get([
+            world_pos.x * self.frequency,
+            world_pos.y * self.frequency,
+            world_pos.z * self.frequency
+        ]) as f32
+    }
+}
\ No newline at end of file
@@ -0,0 +1,248 @@
+use cgmath::*;
+
+use super::{
+    mesher::{Mesh, Mesher, SampledGrid},
+    surface_nets::{cell_crossing_edges, mesh_dual_grid}
+};
+
+//----------------------
+
+// eigenvalues this far below the largest are treated as zero, so flat and
+// creased surfaces don't push the vertex off along the directions the
+// normals don't pin down
+const QEF_TRUNCATION: f32 = 0.1;
+
+// dual contouring, connecting cells the same way as surface nets but placing
+// each vertex where it best fits the planes through the edge crossings, which
+// keeps sharp corners and creases that averaging rounds off
+pub struct DualContouringMesher;
+
+//----------------------
+
+impl DualContouringMesher {
+    pub fn new() -> DualContouringMesher {
+        DualContouringMesher
+    }
+}
+
+impl Mesher for DualContouringMesher {
+    // the edge normals are interpolated from gradients, which reach one sample
+    // further than the cells do
+    fn padding(&self) -> usize {
+        2
+    }
+
+    fn meshes_to_chunk_faces(&self) -> bool {
+        false
+    }
+
+    fn mesh(&self, grid: &SampledGrid) -> Mesh {
+        mesh_dual_grid(grid, |cell| {
+            let mut points = Vec::new();
+            let mut normals = Vec::new();
+
+            for (low, axis, point) in cell_crossing_edges(grid, cell) {
+                let interp_value = grid.edge_crossing(low, axis).0;
+
+                points.push(point);
+                normals.push(grid.edge_normal(low, axis, interp_value));
+            }
+
+            let position = solve_qef(
+                &points,
+                &normals,
+                grid.sample_to_world(cell),
+                grid.sample_to_world(cell + Vector3::new(1, 1, 1))
+            );
+
+            let normal = normals.iter().fold(Vector3::new(0.0, 0.0, 0.0), |sum, normal| sum + normal);
+
+            let normal = if normal.magnitude2() > 0.0 {
+                normal.normalize()
+            }
+            else {
+                Vector3::unit_y()
+            };
+
+            (position, normal)
+        })
+    }
+}
+
+//----------------------
+
+// finds the point closest to every plane through a crossing, falling back to
+// the average of the crossings if the fit lands outside the cell
+pub fn solve_qef(
+    points: &[Vector3<f32>],
+    normals: &[Vector3<f32>],
+    cell_min: Vector3<f32>,
+    cell_max: Vector3<f32>
+) -> Vector3<f32> {
+    let mass_point = points.iter().fold(Vector3::new(0.0, 0.0, 0.0), |sum, point| sum + point) / points.len() as f32;
+
+    // builds the normal equations around the mass point, which keeps the
+    // numbers small and makes the truncated directions fall back to it
+    let mut ata = [[0.0_f32; 3]; 3];
+    let mut atb = Vector3::new(0.0, 0.0, 0.0);
+
+    for (point, normal) in points.iter().zip(normals.iter()) {
+        let distance = normal.dot(point - mass_point);
+
+        for row in 0..3 {
+            for column in 0..3 {
+                ata[row][column] += normal[row] * normal[column];
+            }
+
+            atb[row] += normal[row] * distance;
+        }
+    }
+
+    // solves with the pseudo inverse from the eigen decomposition
+    let (eigenvalues, eigenvectors) = symmetric_eigen(ata);
+    let largest = eigenvalues.iter().fold(0.0_f32, |largest, value| largest.max(value.abs()));
+
+    let mut solution = Vector3::new(0.0, 0.0, 0.0);
+
+    for (index, eigenvalue) in eigenvalues.iter().enumerate() {
+        if eigenvalue.abs() <= largest * QEF_TRUNCATION || *eigenvalue == 0.0 {
+            continue;
+        }
+
+        let eigenvector = Vector3::new(eigenvectors[0][index], eigenvectors[1][index], eigenvectors[2][index]);
+
+        solution += eigenvector * (eigenvector.dot(atb) / eigenvalue);
+    }
+
+    let position = mass_point + solution;
+
+    let inside = (0..3).all(|axis| position[axis] >= cell_min[axis] && position[axis] <= cell_max[axis]);
+
+    if inside { position } else { mass_point }
+}
+
+// gets the eigenvalues and eigenvectors of a symmetric matrix with jacobi
+// rotations, the eigenvectors being the columns of the second matrix
+fn symmetric_eigen(matrix: [[f32; 3]; 3]) -> ([f32; 3], [[f32; 3]; 3]) {
+    let mut a = matrix;
+    let mut vectors = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+    for _ in 0..8 {
+        for &(p, q) in [(0, 1), (0, 2), (1, 2)].iter() {
+            if a[p][q].abs() < 1e-12 {
+                continue;
+            }
+
+            // gets the rotation that zeroes the off diagonal element
+            let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+            let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+            let c = 1.0 / (t * t + 1.0).sqrt();
+            let s = t * c;
+
+            // applies the rotation to the columns then the rows
+            for row in a.iter_mut() {
+                let (row_p, row_q) = (row[p], row[q]);
+                row[p] = c * row_p - s * row_q;
+                row[q] = s * row_p + c * row_q;
+            }
+
+            let (row_p, row_q) = (a[p], a[q]);
+
+            for (column, (p_value, q_value)) in row_p.iter().zip(row_q.iter()).enumerate() {
+                a[p][column] = c * p_value - s * q_value;
+                a[q][column] = s * p_value + c * q_value;
+            }
+
+            for row in vectors.iter_mut() {
+                let (row_p, row_q) = (row[p], row[q]);
+                row[p] = c * row_p - s * row_q;
+                row[q] = s * row_p + c * row_q;
+            }
+        }
+    }
+
+    ([a[0][0], a[1][1], a[2][2]], vectors)
+}
+
+//----------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Vector3<f32>, b: Vector3<f32>, tolerance: f32) -> bool {
+        (a - b).magnitude() < tolerance
+    }
+
+    #[test]
+    fn symmetric_eigen_decomposes_the_matrix() {
+        let matrix = [[2.0, 1.0, 0.0], [1.0, 2.0, 0.5], [0.0, 0.5, 5.0]];
+        let (eigenvalues, eigenvectors) = symmetric_eigen(matrix);
+
+        let column = |index: usize| Vector3::new(eigenvectors[0][index], eigenvectors[1][index], eigenvectors[2][index]);
+        let multiply = |vector: Vector3<f32>| Vector3::new(
+            matrix[0][0] * vector.x + matrix[0][1] * vector.y + matrix[0][2] * vector.z,
+            matrix[1][0] * vector.x + matrix[1][1] * vector.y + matrix[1][2] * vector.z,
+            matrix[2][0] * vector.x + matrix[2][1] * vector.y + matrix[2][2] * vector.z
+        );
+
+        for (index, eigenvalue) in eigenvalues.iter().enumerate() {
+            let vector = column(index);
+
+            // each column is a unit eigenvector at right angles to the others
+            assert!((vector.magnitude() - 1.0).abs() < 1e-5);
+            assert!(close(multiply(vector), vector * *eigenvalue, 1e-4));
+
+            for other in (index + 1)..3 {
+                assert!(vector.dot(column(other)).abs() < 1e-5);
+            }
+        }
+
+        // the trace is the sum of the eigenvalues
+        assert!((eigenvalues.iter().sum::<f32>() - 9.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn qef_recovers_a_sharp_box_corner() {
+        let corner = Vector3::new(0.3, 0.6, 0.45);
+
+        // crossings on each of the three faces meeting at the corner, none of them on it
+        let mut points = Vec::new();
+        let mut normals = Vec::new();
+
+        for axis in 0..3 {
+            let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+
+            for &(du, dv) in [(-0.2, -0.1), (-0.05, -0.25)].iter() {
+                let mut point = corner;
+                point[u] += du;
+                point[v] += dv;
+
+                let mut normal = Vector3::new(0.0, 0.0, 0.0);
+                normal[axis] = 1.0;
+
+                points.push(point);
+                normals.push(normal);
+            }
+        }
+
+        let cell_min = Vector3::new(0.0, 0.0, 0.0);
+        let cell_max = Vector3::new(1.0, 1.0, 1.0);
+
+        let mass_point = points.iter().fold(Vector3::new(0.0, 0.0, 0.0), |sum, point| sum + point) / points.len() as f32;
+        assert!(!close(mass_point, corner, 0.05), "the average of the crossings shouldn't already be the corner");
+
+        assert!(close(solve_qef(&points, &normals, cell_min, cell_max), corner, 1e-5));
+
+        // a flat surface only pins down the distance along its normal, the
+        // rest falls back to the average of the crossings
+        let flat_points = [Vector3::new(0.2, 0.5, 0.1), Vector3::new(0.8, 0.5, 0.3), Vector3::new(0.5, 0.5, 0.8)];
+        let flat_normals = [Vector3::unit_y(); 3];
+
+        assert!(close(solve_qef(&flat_points, &flat_normals, cell_min, cell_max), Vector3::new(0.5, 0.5, 0.4), 1e-5));
+
+        // and a fit that lands outside the cell falls back to the average too
+        let small_cell = Vector3::new(0.25, 0.25, 0.25);
+        assert!(close(solve_qef(&points, &normals, cell_min, small_cell), mass_point, 1e-6));
+    }
+}
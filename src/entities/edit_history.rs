@@ -0,0 +1,166 @@
+use std::collections::VecDeque;
+
+use super::voxel_edits::EditOperation;
+
+//------------------------
+
+// the edits that can be undone and redone. the oldest edits are forgotten
+// once the history grows past its byte budget, and an edit bigger than the
+// whole budget can't be undone at all, nor can anything before it
+pub struct EditHistory {
+    // the edits that have been made, oldest first
+    undo_stack: VecDeque<EditOperation>,
+
+    // the edits that have been undone, most recently undone last
+    redo_stack: Vec<EditOperation>,
+
+    used_bytes: usize,
+    byte_budget: usize
+}
+
+//------------------------
+
+impl EditHistory {
+    pub fn new(byte_budget: usize) -> EditHistory {
+        EditHistory {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+
+            used_bytes: 0,
+            byte_budget
+        }
+    }
+
+    pub fn byte_budget(&self) -> usize {
+        self.byte_budget
+    }
+
+    // changes the budget, forgetting old edits straight away if it shrank
+    pub fn set_byte_budget(&mut self, byte_budget: usize) {
+        self.byte_budget = byte_budget;
+        self.forget_to_fit(0);
+    }
+
+    pub fn used_bytes(&self) -> usize {
+        self.used_bytes
+    }
+
+    pub fn undo_count(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    pub fn redo_count(&self) -> usize {
+        self.redo_stack.len()
+    }
+
+    // records a new edit, which means the undone edits can't be redone any
+    // more, and gives back whether it can be undone. the older edits are
+    // forgotten along with an edit too big to keep, as undoing them would put
+    // back densities over samples it changed without taking it back first
+    pub fn record(&mut self, operation: EditOperation) -> bool {
+        self.used_bytes -= self.redo_stack.drain(..).map(|undone| undone.memory_size()).sum::<usize>();
+
+        let size = operation.memory_size();
+
+        if size > self.byte_budget {
+            self.undo_stack.clear();
+            self.used_bytes = 0;
+
+            return false;
+        }
+
+        self.forget_to_fit(size);
+
+        self.undo_stack.push_back(operation);
+        self.used_bytes += size;
+
+        true
+    }
+
+    // takes the most recent edit to be undone, keeping it to be redone
+    pub fn undo(&mut self) -> Option<&EditOperation> {
+        let operation = self.undo_stack.pop_back()?;
+        self.redo_stack.push(operation);
+
+        self.redo_stack.last()
+    }
+
+    // takes the most recently undone edit to be made again
+    pub fn redo(&mut self) -> Option<&EditOperation> {
+        let operation = self.redo_stack.pop()?;
+        self.undo_stack.push_back(operation);
+
+        self.undo_stack.back()
+    }
+
+    //------------------------
+
+    // forgets edits until the extra bytes fit the budget, the ones waiting to
+    // be redone first as they're the least likely to be wanted, then the oldest
+    fn forget_to_fit(&mut self, extra_bytes: usize) {
+        while self.used_bytes + extra_bytes > self.byte_budget {
+            let forgotten = if !self.redo_stack.is_empty() {
+                self.redo_stack.remove(0)
+            }
+            else {
+                match self.undo_stack.pop_front() {
+                    Some(operation) => operation,
+                    None => break
+                }
+            };
+
+            self.used_bytes -= forgotten.memory_size();
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Vector3;
+
+    use super::*;
+    use crate::entities::voxel_edits::SampleChange;
+
+    // an edit changing a line of samples along x
+    fn operation(sample_count: i32) -> EditOperation {
+        EditOperation {
+            changes: (0..sample_count).map(|x| SampleChange {
+                sample: Vector3::new(x, 0, 0),
+                old_value: None,
+                new_value: 1.0
+            }).collect(),
+            min_sample: Vector3::new(0, 0, 0),
+            max_sample: Vector3::new(sample_count - 1, 0, 0)
+        }
+    }
+
+    #[test]
+    fn edits_within_the_budget_are_recorded() {
+        let mut history = EditHistory::new(operation(4).memory_size() * 2);
+
+        assert!(history.record(operation(4)));
+        assert!(history.record(operation(4)));
+        assert_eq!(history.undo_count(), 2);
+
+        // a third edit forgets the oldest to fit
+        assert!(history.record(operation(4)));
+        assert_eq!(history.undo_count(), 2);
+        assert_eq!(history.used_bytes(), operation(4).memory_size() * 2);
+    }
+
+    #[test]
+    fn an_edit_too_big_to_record_forgets_the_history() {
+        let mut history = EditHistory::new(operation(4).memory_size() * 2);
+
+        history.record(operation(4));
+        history.record(operation(4));
+        history.undo();
+
+        assert!(!history.record(operation(1000)));
+        assert_eq!(history.undo_count(), 0);
+        assert_eq!(history.redo_count(), 0);
+        assert_eq!(history.used_bytes(), 0);
+        assert!(history.undo().is_none());
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use cgmath::*;
+
+use super::mesher::{Mesh, Mesher, SampledGrid};
+
+//----------------------
+
+// the axis orders that split a cube into six tetrahedra around its diagonal.
+// each one walks from the lowest corner to the highest a step along each axis
+// in turn, so every face of every cube is split along the diagonal from its
+// lowest corner and neighbouring cubes always agree on how they're split
+const TETRAHEDRON_AXES: [[usize; 3]; 6] = [
+    [0, 1, 2], [0, 2, 1],
+    [1, 0, 2], [1, 2, 0],
+    [2, 0, 1], [2, 1, 0]
+];
+
+// marching tetrahedra, cutting each cube into tetrahedra which can only be
+// crossed by the surface in one way each, so there are no ambiguous cases to
+// leave holes and no table is needed. it gives more triangles than marching
+// cubes, with vertices on the cube diagonals as well as the edges
+pub struct MarchingTetrahedraMesher;
+
+//----------------------
+
+impl MarchingTetrahedraMesher {
+    pub fn new() -> MarchingTetrahedraMesher {
+        MarchingTetrahedraMesher
+    }
+}
+
+impl Mesher for MarchingTetrahedraMesher {
+    // the gradients at the border samples reach one sample out
+    fn padding(&self) -> usize {
+        1
+    }
+
+    fn meshes_to_chunk_faces(&self) -> bool {
+        true
+    }
+
+    fn splits_faces_diagonally(&self) -> bool {
+        true
+    }
+
+    fn mesh(&self, grid: &SampledGrid) -> Mesh {
+        let cells = grid.chunk_bounds as i32 - 1;
+
+        let mut mesh = Mesh::new();
+
+        // caches the index of the vertex on each edge, keyed by the lower sample
+        // of the edge and the step to the higher one
+        let mut edge_cache = HashMap::<([i32; 3], [i32; 3]), u32>::new();
+
+        for x in 0..cells {
+            for y in 0..cells {
+                for z in 0..cells {
+                    let cube = Vector3::new(x, y, z);
+
+                    for axes in TETRAHEDRON_AXES.iter() {
+                        // gets the corners of the tetrahedron, each one a step
+                        // further along than the last
+                        let mut corners = [cube; 4];
+
+                        for (step, &axis) in axes.iter().enumerate() {
+                            corners[step + 1] = corners[step];
+                            corners[step + 1][axis] += 1;
+                        }
+
+                        let solid = corners.map(|corner| grid.is_solid(corner.x, corner.y, corner.z));
+                        let solid_count = solid.iter().filter(|&&solid| solid).count();
+
+                        if solid_count == 0 || solid_count == 4 {
+                            continue;
+                        }
+
+                        // gets the edges the surface crosses, in order around the
+                        // surface. a lone corner on either side is cut off by a
+                        // triangle, and two on each side are split by a quad
+                        let (inside, outside): (Vec<usize>, Vec<usize>) = if solid_count == 2 {
+                            (0..4).partition(|&corner| solid[corner])
+                        }
+                        else {
+                            (0..4).partition(|&corner| solid[corner] == (solid_count == 1))
+                        };
+
+                        let crossed_edges = if inside.len() == 1 {
+                            vec![(inside[0], outside[0]), (inside[0], outside[1]), (inside[0], outside[2])]
+                        }
+                        else {
+                            vec![
+                                (inside[0], outside[0]),
+                                (inside[0], outside[1]),
+                                (inside[1], outside[1]),
+                                (inside[1], outside[0])
+                            ]
+                        };
+
+                        let polygon: Vec<u32> = crossed_edges.iter().map(|&(a, b)| {
+                            // walks the edge from the lower corner, which comes
+                            // first in the tetrahedron
+                            let (low, high) = (corners[a.min(b)], corners[a.max(b)]);
+                            let step = high - low;
+
+                            *edge_cache.entry(([low.x, low.y, low.z], [step.x, step.y, step.z])).or_insert_with(|| {
+                                let (interp_value, vertex_point) = grid.crossing_between(low, high);
+                                let normal = grid.normal_between(low, high, interp_value);
+
+                                mesh.push_vertex(vertex_point, normal)
+                            })
+                        }).collect();
+
+                        // faces the triangles from the rock to the air
+                        let solid_centre = average_corner(&corners, &solid, true);
+                        let air_centre = average_corner(&corners, &solid, false);
+                        let outward = air_centre - solid_centre;
+
+                        let first = mesh.position(polygon[0]);
+
+                        let facing = (1..(polygon.len() - 1)).fold(Vector3::new(0.0, 0.0, 0.0), |sum, corner| {
+                            let a = mesh.position(polygon[corner]) - first;
+                            let b = mesh.position(polygon[corner + 1]) - first;
+
+                            sum + a.cross(b)
+                        });
+
+                        let flip = facing.dot(outward) < 0.0;
+
+                        for corner in 1..(polygon.len() - 1) {
+                            if flip {
+                                mesh.indices.extend_from_slice(&[polygon[0], polygon[corner + 1], polygon[corner]]);
+                            }
+                            else {
+                                mesh.indices.extend_from_slice(&[polygon[0], polygon[corner], polygon[corner + 1]]);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        mesh
+    }
+}
+
+//----------------------
+
+// gets the middle of the corners on one side of the surface
+fn average_corner(corners: &[Vector3<i32>; 4], solid: &[bool; 4], side: bool) -> Vector3<f32> {
+    let mut sum = Vector3::new(0.0, 0.0, 0.0);
+    let mut count = 0.0;
+
+    for (corner, &corner_solid) in corners.iter().zip(solid.iter()) {
+        if corner_solid == side {
+            sum += Vector3::new(corner.x as f32, corner.y as f32, corner.z as f32);
+            count += 1.0;
+        }
+    }
+
+    sum / count
+}
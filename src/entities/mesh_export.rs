@@ -0,0 +1,619 @@
+use cgmath::{InnerSpace, Vector3};
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::utils::{chunk_space, world_config::WorldConfig};
+
+use super::basic_marching_cubes::MCChunk;
+
+//------------------------
+
+// the files meshes can be exported to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Obj,
+    Ply,
+    Glb
+}
+
+// a single mesh made from any number of chunks, with the vertices along
+// their borders welded together so it's one closed surface
+pub struct ExportMesh {
+    pub vertices: Vec<f32>,
+    pub normals: Vec<f32>,
+
+    // an rgb colour for every vertex, between 0 and 1, if the mesh has them
+    pub colours: Option<Vec<f32>>,
+
+    pub indices: Vec<u32>
+}
+
+//------------------------
+
+impl ExportFormat {
+    // reads a format from its name, which is also its file extension
+    pub fn from_name(name: &str) -> Result<ExportFormat, String> {
+        match name {
+            "obj" => Ok(ExportFormat::Obj),
+            "ply" => Ok(ExportFormat::Ply),
+            "glb" => Ok(ExportFormat::Glb),
+            _ => Err(format!("unknown format '{}'", name))
+        }
+    }
+
+    // picks the format from a file's extension
+    pub fn from_path(path: &Path) -> Result<ExportFormat, String> {
+        let extension = path.extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or("");
+
+        ExportFormat::from_name(&extension.to_lowercase())
+            .map_err(|_| format!("can't tell the format of '{}', expected a .obj, .ply or .glb file", path.display()))
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Obj => "obj",
+            ExportFormat::Ply => "ply",
+            ExportFormat::Glb => "glb"
+        }
+    }
+}
+
+impl ExportMesh {
+    // welds the meshes of a set of chunks into one
+    pub fn from_chunks(chunks: &[&MCChunk]) -> ExportMesh {
+        ExportMesh::weld(chunks, None)
+    }
+
+    // welds the meshes of a set of chunks into one, keeping only the triangles
+    // with their centre inside a world space box
+    pub fn from_chunks_in_box(chunks: &[&MCChunk], min_corner: Vector3<f32>, max_corner: Vector3<f32>) -> ExportMesh {
+        ExportMesh::weld(chunks, Some((min_corner, max_corner)))
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len() / 3
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    // colours every vertex the way the demo's shader does before lighting,
+    // by its position, so exports look like the caves on screen
+    pub fn colour_by_position(&mut self) {
+        let colours = self.vertices.iter()
+            .map(|coord| ((coord + 12.8) / 25.6).clamp(0.0, 1.0))
+            .collect();
+
+        self.colours = Some(colours);
+    }
+
+    // writes the mesh to a file in the given format
+    pub fn write(&self, path: &Path, format: ExportFormat) -> Result<(), String> {
+        if self.indices.is_empty() {
+            return Err(format!("there's no surface to write to '{}'", path.display()));
+        }
+
+        let file = File::create(path).map_err(|error| format!("couldn't create '{}': {}", path.display(), error))?;
+        let mut writer = BufWriter::new(file);
+
+        let result = match format {
+            ExportFormat::Obj => self.write_obj(&mut writer),
+            ExportFormat::Ply => self.write_ply(&mut writer),
+            ExportFormat::Glb => self.write_glb(&mut writer)
+        };
+
+        result
+            .and_then(|_| writer.flush())
+            .map_err(|error| format!("couldn't write '{}': {}", path.display(), error))
+    }
+
+    //------------------------
+
+    // merges vertices at exactly the same position, which neighbouring chunks
+    // always give the vertices along the face they share, averaging their
+    // normals. triangles left with two corners in the same place are dropped
+    fn weld(chunks: &[&MCChunk], clip_box: Option<(Vector3<f32>, Vector3<f32>)>) -> ExportMesh {
+        let mut mesh = ExportMesh {
+            vertices: Vec::new(),
+            normals: Vec::new(),
+            colours: None,
+            indices: Vec::new()
+        };
+
+        let mut welded = HashMap::<[u32; 3], u32>::new();
+
+        for chunk in chunks.iter() {
+            assert_eq!(chunk.vertices.len(), chunk.normals.len(), "chunk has a different number of normals and vertices");
+
+            let position = |index: u32| {
+                let start = index as usize * 3;
+
+                Vector3::new(chunk.vertices[start], chunk.vertices[start + 1], chunk.vertices[start + 2])
+            };
+
+            // the welded vertex each of the chunk's vertices became, once it's used
+            let mut remapped: Vec<Option<u32>> = vec![None; chunk.vertices.len() / 3];
+
+            for triangle in chunk.indices.chunks(3) {
+                if let Some((min_corner, max_corner)) = clip_box {
+                    let centre = (position(triangle[0]) + position(triangle[1]) + position(triangle[2])) / 3.0;
+
+                    let inside = (0..3).all(|axis| centre[axis] >= min_corner[axis] && centre[axis] <= max_corner[axis]);
+
+                    if !inside {
+                        continue;
+                    }
+                }
+
+                let corners: Vec<u32> = triangle.iter().map(|index| {
+                    if let Some(welded_index) = remapped[*index as usize] {
+                        return welded_index;
+                    }
+
+                    let vertex = position(*index);
+                    let next_index = mesh.vertex_count() as u32;
+
+                    let welded_index = *welded.entry([vertex.x.to_bits(), vertex.y.to_bits(), vertex.z.to_bits()])
+                        .or_insert_with(|| {
+                            mesh.vertices.extend_from_slice(&[vertex.x, vertex.y, vertex.z]);
+                            mesh.normals.extend_from_slice(&[0.0, 0.0, 0.0]);
+
+                            next_index
+                        });
+
+                    // sums the normals of every copy of the vertex, normalising them at the end
+                    for axis in 0..3 {
+                        mesh.normals[welded_index as usize * 3 + axis] += chunk.normals[*index as usize * 3 + axis];
+                    }
+
+                    remapped[*index as usize] = Some(welded_index);
+
+                    welded_index
+                }).collect();
+
+                if corners[0] == corners[1] || corners[1] == corners[2] || corners[2] == corners[0] {
+                    continue;
+                }
+
+                mesh.indices.extend_from_slice(&corners);
+            }
+        }
+
+        for normal in mesh.normals.chunks_mut(3) {
+            let summed = Vector3::new(normal[0], normal[1], normal[2]);
+
+            if summed.magnitude2() > 0.0 {
+                let normalised = summed.normalize();
+                normal.copy_from_slice(&[normalised.x, normalised.y, normalised.z]);
+            }
+        }
+
+        mesh
+    }
+
+    // writes the mesh as a Wavefront OBJ file, with colours after each vertex's
+    // position as blender and meshlab read them
+    fn write_obj<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writeln!(writer, "# exported from terrainbasic")?;
+
+        for (index, vertex) in self.vertices.chunks(3).enumerate() {
+            match &self.colours {
+                Some(colours) => {
+                    let colour = &colours[index * 3..index * 3 + 3];
+
+                    writeln!(writer, "v {} {} {} {} {} {}", vertex[0], vertex[1], vertex[2], colour[0], colour[1], colour[2])?;
+                }
+                None => writeln!(writer, "v {} {} {}", vertex[0], vertex[1], vertex[2])?
+            }
+        }
+
+        for normal in self.normals.chunks(3) {
+            writeln!(writer, "vn {} {} {}", normal[0], normal[1], normal[2])?;
+        }
+
+        // obj counts vertices from one
+        for triangle in self.indices.chunks(3) {
+            let (a, b, c) = (triangle[0] + 1, triangle[1] + 1, triangle[2] + 1);
+
+            writeln!(writer, "f {}//{} {}//{} {}//{}", a, a, b, b, c, c)?;
+        }
+
+        Ok(())
+    }
+
+    // writes the mesh as a little endian binary PLY file, with colours as bytes
+    fn write_ply<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writeln!(writer, "ply")?;
+        writeln!(writer, "format binary_little_endian 1.0")?;
+        writeln!(writer, "comment exported from terrainbasic")?;
+        writeln!(writer, "element vertex {}", self.vertex_count())?;
+
+        for property in ["x", "y", "z", "nx", "ny", "nz"].iter() {
+            writeln!(writer, "property float {}", property)?;
+        }
+
+        if self.colours.is_some() {
+            for property in ["red", "green", "blue"].iter() {
+                writeln!(writer, "property uchar {}", property)?;
+            }
+        }
+
+        writeln!(writer, "element face {}", self.triangle_count())?;
+        writeln!(writer, "property list uchar uint vertex_indices")?;
+        writeln!(writer, "end_header")?;
+
+        for index in 0..self.vertex_count() {
+            for value in self.vertices[index * 3..index * 3 + 3].iter().chain(self.normals[index * 3..index * 3 + 3].iter()) {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+
+            if let Some(colours) = &self.colours {
+                let colour: Vec<u8> = colours[index * 3..index * 3 + 3].iter()
+                    .map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)
+                    .collect();
+
+                writer.write_all(&colour)?;
+            }
+        }
+
+        for triangle in self.indices.chunks(3) {
+            writer.write_all(&[3])?;
+
+            for index in triangle.iter() {
+                writer.write_all(&index.to_le_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // writes the mesh as a binary glTF 2.0 file, a json chunk describing one
+    // mesh followed by a binary chunk holding its buffers back to back
+    fn write_glb<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        const ARRAY_BUFFER: u32 = 34962;
+        const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+        const FLOAT: u32 = 5126;
+        const UNSIGNED_INT: u32 = 5125;
+
+        let mut binary = Vec::new();
+        let mut buffer_views = Vec::new();
+        let mut accessors = Vec::new();
+        let mut attributes = Vec::new();
+
+        // every buffer holds 4 byte values, so each starts aligned as glTF requires
+        let mut push_buffer = |binary: &mut Vec<u8>, bytes: Vec<u8>, target: u32| {
+            buffer_views.push(format!(
+                "{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{},\"target\":{}}}",
+                binary.len(),
+                bytes.len(),
+                target
+            ));
+            binary.extend(bytes);
+
+            buffer_views.len() - 1
+        };
+
+        // positions need their bounds written out
+        let mut min_corner = [f32::MAX; 3];
+        let mut max_corner = [f32::MIN; 3];
+
+        for vertex in self.vertices.chunks(3) {
+            for axis in 0..3 {
+                min_corner[axis] = min_corner[axis].min(vertex[axis]);
+                max_corner[axis] = max_corner[axis].max(vertex[axis]);
+            }
+        }
+
+        let mut vec3_attributes = vec![("POSITION", &self.vertices), ("NORMAL", &self.normals)];
+
+        if let Some(colours) = &self.colours {
+            vec3_attributes.push(("COLOR_0", colours));
+        }
+
+        for (name, values) in vec3_attributes {
+            let bytes = values.iter().flat_map(|value| value.to_le_bytes().to_vec()).collect();
+            let view = push_buffer(&mut binary, bytes, ARRAY_BUFFER);
+
+            let bounds = if name == "POSITION" {
+                format!(
+                    ",\"min\":[{},{},{}],\"max\":[{},{},{}]",
+                    min_corner[0], min_corner[1], min_corner[2],
+                    max_corner[0], max_corner[1], max_corner[2]
+                )
+            }
+            else {
+                String::new()
+            };
+
+            accessors.push(format!(
+                "{{\"bufferView\":{},\"componentType\":{},\"count\":{},\"type\":\"VEC3\"{}}}",
+                view,
+                FLOAT,
+                self.vertex_count(),
+                bounds
+            ));
+            attributes.push(format!("\"{}\":{}", name, accessors.len() - 1));
+        }
+
+        let bytes = self.indices.iter().flat_map(|index| index.to_le_bytes().to_vec()).collect();
+        let view = push_buffer(&mut binary, bytes, ELEMENT_ARRAY_BUFFER);
+
+        accessors.push(format!(
+            "{{\"bufferView\":{},\"componentType\":{},\"count\":{},\"type\":\"SCALAR\"}}",
+            view,
+            UNSIGNED_INT,
+            self.indices.len()
+        ));
+
+        let json = format!(
+            concat!(
+                "{{\"asset\":{{\"version\":\"2.0\",\"generator\":\"terrainbasic\"}},",
+                "\"scene\":0,\"scenes\":[{{\"nodes\":[0]}}],\"nodes\":[{{\"mesh\":0}}],",
+                "\"meshes\":[{{\"primitives\":[{{\"attributes\":{{{}}},\"indices\":{},\"mode\":4}}]}}],",
+                "\"accessors\":[{}],\"bufferViews\":[{}],\"buffers\":[{{\"byteLength\":{}}}]}}"
+            ),
+            attributes.join(","),
+            accessors.len() - 1,
+            accessors.join(","),
+            buffer_views.join(","),
+            binary.len()
+        );
+
+        // chunks are padded to 4 bytes, json with spaces and binary with zeros
+        let mut json = json.into_bytes();
+
+        while json.len() % 4 != 0 {
+            json.push(b' ');
+        }
+
+        while binary.len() % 4 != 0 {
+            binary.push(0);
+        }
+
+        let total_length = 12 + 8 + json.len() + 8 + binary.len();
+
+        writer.write_all(b"glTF")?;
+        writer.write_all(&2u32.to_le_bytes())?;
+        writer.write_all(&(total_length as u32).to_le_bytes())?;
+
+        writer.write_all(&(json.len() as u32).to_le_bytes())?;
+        writer.write_all(b"JSON")?;
+        writer.write_all(&json)?;
+
+        writer.write_all(&(binary.len() as u32).to_le_bytes())?;
+        writer.write_all(b"BIN\0")?;
+        writer.write_all(&binary)?;
+
+        Ok(())
+    }
+}
+
+//------------------------
+
+// gets the first and last full detail chunks a world space box touches
+pub fn box_chunks(world_config: &WorldConfig, min_corner: Vector3<f32>, max_corner: Vector3<f32>) -> (Vector3<i32>, Vector3<i32>) {
+    (
+        chunk_space::world_to_chunk(min_corner, world_config.chunk_bounds, world_config.spacing_per_vertex),
+        chunk_space::world_to_chunk(max_corner, world_config.chunk_bounds, world_config.spacing_per_vertex)
+    )
+}
+
+
+//------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    use crate::entities::{
+        basic_marching_cubes::MarchingCubesMesher,
+        chunk_lod::ChunkKey,
+        table_reader::TriangulationTable
+    };
+
+    fn build_chunk(world_config: &WorldConfig, offset: Vector3<i32>) -> MCChunk {
+        let density = world_config.create_density_field();
+        let mesher = MarchingCubesMesher::new(Arc::new(TriangulationTable::new()));
+
+        MCChunk::new(ChunkKey::base(offset), world_config, density.as_ref(), &mesher)
+    }
+
+    // a chunk holding a hand made mesh, every vertex facing up
+    fn chunk_with_mesh(vertices: &[[f32; 3]], indices: &[u32]) -> MCChunk {
+        let mut chunk = build_chunk(&WorldConfig::new(), Vector3::new(0, 0, 0));
+
+        chunk.vertices = vertices.iter().flat_map(|vertex| vertex.to_vec()).collect();
+        chunk.normals = vertices.iter().flat_map(|_| vec![0.0, 1.0, 0.0]).collect();
+        chunk.indices = indices.to_vec();
+
+        chunk
+    }
+
+    // a small mesh to write out, a square made of two triangles
+    fn square_mesh() -> ExportMesh {
+        let chunk = chunk_with_mesh(
+            &[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 0.0, 1.0], [0.0, 0.0, 1.0]],
+            &[0, 2, 1, 0, 3, 2]
+        );
+
+        ExportMesh::from_chunks(&[&chunk])
+    }
+
+    // writes a mesh to a file and reads back its bytes
+    fn written_bytes(mesh: &ExportMesh, format: ExportFormat, name: &str) -> Vec<u8> {
+        let path = std::env::temp_dir().join(format!("zendemo-export-{}-{}.{}", name, std::process::id(), format.extension()));
+
+        mesh.write(&path, format).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        bytes
+    }
+
+    fn u32_at(bytes: &[u8], start: usize) -> u32 {
+        u32::from_le_bytes([bytes[start], bytes[start + 1], bytes[start + 2], bytes[start + 3]])
+    }
+
+    #[test]
+    fn neighbouring_chunks_share_one_set_of_border_vertices() {
+        let world_config = WorldConfig::new();
+        let chunks = [build_chunk(&world_config, Vector3::new(0, 0, 0)), build_chunk(&world_config, Vector3::new(1, 0, 0))];
+
+        let plane = chunk_space::chunk_world_size(world_config.chunk_bounds, world_config.spacing_per_vertex);
+
+        // the positions used by each chunk's triangles, and the ones on the face they share
+        let positions = |chunk: &MCChunk| -> HashSet<[u32; 3]> {
+            chunk.indices.iter()
+                .map(|index| {
+                    let start = *index as usize * 3;
+                    [chunk.vertices[start].to_bits(), chunk.vertices[start + 1].to_bits(), chunk.vertices[start + 2].to_bits()]
+                })
+                .collect()
+        };
+        let on_plane = |positions: &HashSet<[u32; 3]>| -> HashSet<[u32; 3]> {
+            positions.iter().filter(|position| f32::from_bits(position[0]) == plane).copied().collect()
+        };
+
+        let (first, second) = (positions(&chunks[0]), positions(&chunks[1]));
+
+        assert!(!on_plane(&first).is_empty(), "the surface should cross the shared face");
+        assert_eq!(on_plane(&first), on_plane(&second));
+
+        let mesh = ExportMesh::from_chunks(&[&chunks[0], &chunks[1]]);
+        let welded: Vec<[u32; 3]> = mesh.vertices.chunks(3)
+            .map(|vertex| [vertex[0].to_bits(), vertex[1].to_bits(), vertex[2].to_bits()])
+            .collect();
+
+        // every position is kept once, the shared ones included
+        assert_eq!(welded.iter().copied().collect::<HashSet<[u32; 3]>>().len(), welded.len());
+        assert_eq!(welded.len(), first.union(&second).count());
+        assert_eq!(welded.iter().filter(|position| f32::from_bits(position[0]) == plane).count(), on_plane(&first).len());
+    }
+
+    #[test]
+    fn welded_vertices_average_their_normals() {
+        let mut first = chunk_with_mesh(&[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]], &[0, 2, 1]);
+        let mut second = chunk_with_mesh(&[[1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 1.0]], &[0, 1, 2]);
+
+        first.normals[3..6].copy_from_slice(&[1.0, 0.0, 0.0]);
+        second.normals[0..3].copy_from_slice(&[0.0, 1.0, 0.0]);
+
+        let mesh = ExportMesh::from_chunks(&[&first, &second]);
+
+        assert_eq!(mesh.vertex_count(), 4);
+        assert_eq!(mesh.triangle_count(), 2);
+
+        // the vertex at 1 0 0 is where both chunks put one
+        let shared = mesh.vertices.chunks(3).position(|vertex| vertex == [1.0, 0.0, 0.0]).unwrap();
+        let normal = Vector3::new(mesh.normals[shared * 3], mesh.normals[shared * 3 + 1], mesh.normals[shared * 3 + 2]);
+
+        assert!((normal - Vector3::new(1.0, 1.0, 0.0).normalize()).magnitude() < 1e-6);
+    }
+
+    #[test]
+    fn triangles_with_two_corners_in_one_place_are_dropped() {
+        // vertices 1 and 3 are different vertices in the same place
+        let chunk = chunk_with_mesh(
+            &[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]],
+            &[0, 2, 1, 0, 1, 3]
+        );
+
+        let mesh = ExportMesh::from_chunks(&[&chunk]);
+
+        assert_eq!(mesh.vertex_count(), 3);
+        assert_eq!(mesh.indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn clipping_keeps_triangles_centred_in_the_box() {
+        let chunk = chunk_with_mesh(
+            &[
+                [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0],
+                [10.0, 0.0, 0.0], [11.0, 0.0, 0.0], [10.0, 0.0, 1.0],
+                [1.0, 0.0, 0.5], [3.5, 0.0, 0.5], [1.0, 0.0, 1.5]
+            ],
+            &[0, 2, 1, 3, 5, 4, 6, 8, 7]
+        );
+
+        let mesh = ExportMesh::from_chunks_in_box(&[&chunk], Vector3::new(-1.0, -1.0, -1.0), Vector3::new(2.0, 1.0, 2.0));
+
+        // the second is outside, and the third reaches out of the box but its centre's in
+        assert_eq!(mesh.triangle_count(), 2);
+        assert_eq!(mesh.vertex_count(), 6);
+        assert!(mesh.vertices.chunks(3).all(|vertex| vertex[0] < 10.0));
+    }
+
+    #[test]
+    fn ply_files_have_the_layout_their_header_gives() {
+        let mut mesh = square_mesh();
+
+        for coloured in [false, true].iter() {
+            if *coloured {
+                mesh.colour_by_position();
+            }
+
+            let bytes = written_bytes(&mesh, ExportFormat::Ply, if *coloured { "coloured" } else { "plain" });
+
+            let end = b"end_header\n";
+            let body_start = bytes.windows(end.len()).position(|window| window == end).unwrap() + end.len();
+            let header = String::from_utf8(bytes[..body_start].to_vec()).unwrap();
+
+            assert!(header.starts_with("ply\nformat binary_little_endian 1.0\n"));
+            assert!(header.contains("element vertex 4\n"));
+            assert!(header.contains("element face 2\n"));
+            assert_eq!(header.contains("property uchar red"), *coloured);
+
+            // six floats for each vertex and three colour bytes if there are
+            // colours, then a count byte and three indices for each triangle
+            let vertex_size = 6 * 4 + if *coloured { 3 } else { 0 };
+
+            assert_eq!(bytes.len() - body_start, 4 * vertex_size + 2 * (1 + 3 * 4));
+
+            let first_face = body_start + 4 * vertex_size;
+            assert_eq!(bytes[first_face], 3);
+            assert_eq!(u32_at(&bytes, first_face + 1), mesh.indices[0]);
+        }
+    }
+
+    #[test]
+    fn glb_files_have_aligned_chunks_and_the_right_length() {
+        let mut mesh = square_mesh();
+        mesh.colour_by_position();
+
+        let bytes = written_bytes(&mesh, ExportFormat::Glb, "layout");
+
+        // the header, then the json and binary chunks, each starting with its length and type
+        assert_eq!(&bytes[0..4], b"glTF");
+        assert_eq!(u32_at(&bytes, 4), 2);
+        assert_eq!(u32_at(&bytes, 8) as usize, bytes.len());
+
+        let json_length = u32_at(&bytes, 12) as usize;
+        assert_eq!(&bytes[16..20], b"JSON");
+        assert!(json_length.is_multiple_of(4));
+
+        let binary_start = 20 + json_length;
+        let binary_length = u32_at(&bytes, binary_start) as usize;
+
+        assert_eq!(&bytes[binary_start + 4..binary_start + 8], b"BIN\0");
+        assert!(binary_length.is_multiple_of(4));
+        assert_eq!(binary_start + 8 + binary_length, bytes.len());
+
+        // three vec3 attributes for each vertex and an index for each corner
+        assert_eq!(binary_length, 3 * 4 * 3 * 4 + 6 * 4);
+
+        let json = String::from_utf8(bytes[20..binary_start].to_vec()).unwrap();
+
+        assert!(json.contains(&format!("\"buffers\":[{{\"byteLength\":{}}}]", binary_length)));
+        assert!(json.contains("\"COLOR_0\""));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,321 @@
+use cgmath::*;
+
+use ndarray::Array3;
+
+use crate::utils::chunk_space;
+
+use super::{
+    compact_grid::CompactWeights,
+    density_field::DensityField
+};
+
+//----------------------
+
+// which side of the surface a sample is on, air below the isovalue and rock
+// at or above it
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum Mask {
+    Minus = 0,
+    Plus = 1
+}
+
+// a triangle mesh with a normal for every vertex
+#[derive(Clone, Debug, Default)]
+pub struct Mesh {
+    pub vertices: Vec<f32>,
+    pub normals: Vec<f32>,
+    pub indices: Vec<u32>
+}
+
+// the densities sampled for a chunk. the weights are padded on every side so
+// that meshers can look past the chunk border, for gradients or for cells
+// that straddle it, and get exactly what the neighbouring chunk sees
+#[derive(Clone)]
+pub struct SampledGrid {
+    weights: CompactWeights,
+    padding: usize,
+
+    pub offset: Vector3<i32>,
+    pub chunk_bounds: usize,
+    pub spacing_per_vertex: f32,
+    pub isovalue: f32
+}
+
+// turns a sampled grid into a mesh. the mesh of every chunk has to line up
+// with its neighbours, so meshers only work from global sample positions
+pub trait Mesher: Send + Sync {
+    // how many samples past the chunk border the mesher reads
+    fn padding(&self) -> usize;
+
+    // whether the edge of the mesh lies on the chunk faces, which transition
+    // cells need to join the chunk to finer levels of detail
+    fn meshes_to_chunk_faces(&self) -> bool;
+
+    // whether the mesh cuts the squares on the chunk faces in two along the
+    // diagonal from their lowest corner, which transition cells then have to follow
+    fn splits_faces_diagonally(&self) -> bool {
+        false
+    }
+
+    // whether the mesh joins the rock corners of a cube face with rock on one
+    // diagonal and air on the other. the cube's densities are given relative
+    // to the isovalue in the order of the cube corners, and the face in the
+    // order of the cube faces. only asked of meshers that end on the chunk
+    // faces without splitting them diagonally
+    fn joins_rock_across_face(&self, _values: &[f32; 8], _face: usize) -> bool {
+        false
+    }
+
+    fn mesh(&self, grid: &SampledGrid) -> Mesh;
+}
+
+//----------------------
+
+impl Mesh {
+    pub fn new() -> Mesh {
+        Mesh::default()
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len() / 3
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    // adds a vertex, giving back its index
+    pub fn push_vertex(&mut self, position: Vector3<f32>, normal: Vector3<f32>) -> u32 {
+        let index = self.vertex_count() as u32;
+
+        self.vertices.extend_from_slice(&[position.x, position.y, position.z]);
+        self.normals.extend_from_slice(&[normal.x, normal.y, normal.z]);
+
+        index
+    }
+
+    pub fn position(&self, index: u32) -> Vector3<f32> {
+        let start = index as usize * 3;
+
+        Vector3::new(self.vertices[start], self.vertices[start + 1], self.vertices[start + 2])
+    }
+
+    pub fn normal(&self, index: u32) -> Vector3<f32> {
+        let start = index as usize * 3;
+
+        Vector3::new(self.normals[start], self.normals[start + 1], self.normals[start + 2])
+    }
+}
+
+//----------------------
+
+impl SampledGrid {
+    // samples a chunk's densities at global sample positions, including the
+    // padding past each edge
+    pub fn sample(
+        offset: Vector3<i32>,
+        chunk_bounds: usize,
+        spacing_per_vertex: f32,
+        isovalue: f32,
+        padding: usize,
+        density: &dyn DensityField
+    ) -> SampledGrid {
+        let padded_bounds = chunk_bounds + padding * 2;
+
+        let mut weights = Vec::with_capacity(padded_bounds * padded_bounds * padded_bounds);
+
+        for x in 0..padded_bounds {
+            for y in 0..padded_bounds {
+                for z in 0..padded_bounds {
+                    // gets the global sample for the point, shifted back by the padding
+                    let local = Vector3::new(x as i32, y as i32, z as i32) - Vector3::new(1, 1, 1) * padding as i32;
+                    let sample = chunk_space::local_to_global_sample(offset, local, chunk_bounds);
+
+                    // gets the density value at the sample's world position
+                    weights.push(density.sample(
+                        chunk_space::global_sample_to_density_pos(sample, spacing_per_vertex)
+                    ));
+                }
+            }
+        }
+
+        SampledGrid {
+            weights: CompactWeights::new(weights),
+            padding,
+
+            offset,
+            chunk_bounds,
+            spacing_per_vertex,
+            isovalue
+        }
+    }
+
+    // wraps weights that have already been sampled, padding included, so the
+    // same data can be given to different meshers
+    pub fn from_weights(
+        weights: Array3<f32>,
+        padding: usize,
+        offset: Vector3<i32>,
+        chunk_bounds: usize,
+        spacing_per_vertex: f32,
+        isovalue: f32
+    ) -> SampledGrid {
+        assert_eq!(weights.dim(), (chunk_bounds + padding * 2, chunk_bounds + padding * 2, chunk_bounds + padding * 2));
+
+        SampledGrid {
+            weights: CompactWeights::new(weights.iter().copied().collect()),
+            padding,
+
+            offset,
+            chunk_bounds,
+            spacing_per_vertex,
+            isovalue
+        }
+    }
+
+    pub fn padding(&self) -> usize {
+        self.padding
+    }
+
+    // gets every sampled weight, padding included, in the same order as the
+    // weights the grid was made from
+    pub fn weights(&self) -> &CompactWeights {
+        &self.weights
+    }
+
+    // gets the label of a sample inside the chunk, plus if the density value
+    // is at or over the isovalue and minus otherwise
+    pub fn mask(&self, x: usize, y: usize, z: usize) -> Mask {
+        if self.is_solid(x as i32, y as i32, z as i32) {
+            Mask::Plus
+        }
+        else {
+            Mask::Minus
+        }
+    }
+
+    // gets which side of the isovalue every sample is on, padding included,
+    // or None if they're on both sides and there could be a surface
+    pub fn uniform_mask(&self) -> Option<Mask> {
+        let solid = self.weights.get(0) >= self.isovalue;
+
+        if (1..self.weights.len()).all(|index| (self.weights.get(index) >= self.isovalue) == solid) {
+            Some(if solid { Mask::Plus } else { Mask::Minus })
+        }
+        else {
+            None
+        }
+    }
+
+    // gets the sampled weight at a local position, which can reach into the padding
+    pub fn weight(&self, x: i32, y: i32, z: i32) -> f32 {
+        let padding = self.padding as i32;
+        let padded_bounds = (self.chunk_bounds + self.padding * 2) as i32;
+
+        let index = ((x + padding) * padded_bounds + (y + padding)) * padded_bounds + (z + padding);
+
+        self.weights.get(index as usize)
+    }
+
+    pub fn is_solid(&self, x: i32, y: i32, z: i32) -> bool {
+        self.weight(x, y, z) >= self.isovalue
+    }
+
+    // gets the density gradient at a sample using central differences, which
+    // needs one more sample of padding than the sample is from the chunk
+    pub fn gradient(&self, x: i32, y: i32, z: i32) -> Vector3<f32> {
+        // the scale of the difference doesn't matter since the result is only
+        // used as a direction
+        Vector3::new(
+            self.weight(x + 1, y, z) - self.weight(x - 1, y, z),
+            self.weight(x, y + 1, z) - self.weight(x, y - 1, z),
+            self.weight(x, y, z + 1) - self.weight(x, y, z - 1)
+        )
+    }
+
+    // gets the global sample coordinate of a local sample
+    pub fn global_sample(&self, local: Vector3<i32>) -> Vector3<i32> {
+        chunk_space::local_to_global_sample(self.offset, local, self.chunk_bounds)
+    }
+
+    // gets the world position of a local sample
+    pub fn sample_to_world(&self, local: Vector3<i32>) -> Vector3<f32> {
+        chunk_space::global_sample_to_world(self.global_sample(local), self.spacing_per_vertex)
+    }
+
+    // gets where the surface crosses the edge leaving a local sample along an
+    // axis, as the interpolation along the edge and the world position
+    pub fn edge_crossing(&self, low: Vector3<i32>, axis: usize) -> (f32, Vector3<f32>) {
+        let mut high = low;
+        high[axis] += 1;
+
+        self.crossing_between(low, high)
+    }
+
+    // gets the normal at an edge crossing from the gradients at each end,
+    // flipped so it points away from the solid side
+    pub fn edge_normal(&self, low: Vector3<i32>, axis: usize, interp_value: f32) -> Vector3<f32> {
+        let mut high = low;
+        high[axis] += 1;
+
+        self.normal_between(low, high, interp_value)
+    }
+
+    // gets where the surface crosses the line between two local samples, which
+    // can be diagonal. the line has to be walked from the same end every time
+    // it's used for the point to come out the same
+    pub fn crossing_between(&self, low: Vector3<i32>, high: Vector3<i32>) -> (f32, Vector3<f32>) {
+        crossing_point(
+            self.global_sample(low),
+            high - low,
+            (self.weight(low.x, low.y, low.z), self.weight(high.x, high.y, high.z)),
+            self.isovalue,
+            self.spacing_per_vertex
+        )
+    }
+
+    pub fn normal_between(&self, low: Vector3<i32>, high: Vector3<i32>, interp_value: f32) -> Vector3<f32> {
+        interpolated_normal(self.gradient(low.x, low.y, low.z), self.gradient(high.x, high.y, high.z), interp_value)
+    }
+
+    // gets roughly how many bytes the samples take up
+    pub fn memory_size(&self) -> usize {
+        self.weights.memory_size()
+    }
+}
+
+//----------------------
+
+// gets where the surface crosses the line from a global sample to the sample
+// a step away, given the weights at each end, as the interpolation along the
+// line and the world position. anything sampling the same densities gets the
+// same bits back, whichever grid they were read from
+pub fn crossing_point(
+    low_sample: Vector3<i32>,
+    step: Vector3<i32>,
+    (low_weight, high_weight): (f32, f32),
+    isovalue: f32,
+    spacing_per_vertex: f32
+) -> (f32, Vector3<f32>) {
+    // interpolates between the grid weights
+    let interp_value = (isovalue - low_weight) / (high_weight - low_weight);
+
+    // calculates the point from the global sample at the lower corner, so
+    // neighbouring chunks place shared points at exactly the same position
+    let vertex_point = chunk_space::step_point_to_world(low_sample, step, interp_value, spacing_per_vertex);
+
+    (interp_value, vertex_point)
+}
+
+// gets the normal at a crossing from the gradients at each end of its line,
+// flipped so it points away from the solid side
+pub fn interpolated_normal(gradient_low: Vector3<f32>, gradient_high: Vector3<f32>, interp_value: f32) -> Vector3<f32> {
+    let gradient = gradient_low + (interp_value * (gradient_high - gradient_low));
+
+    if gradient.magnitude2() > 0.0 {
+        -gradient.normalize()
+    }
+    else {
+        Vector3::unit_y()
+    }
+}
\ No newline at end of file
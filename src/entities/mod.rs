@@ -1,4 +1,21 @@
 pub mod basic_marching_cubes;
 pub mod camera;
 pub mod table_reader;
-pub mod chunk_manager;
\ No newline at end of file
+pub mod chunk_manager;
+pub mod density_field;
+pub mod density_expr;
+pub mod chunk_workers;
+pub mod chunk_scheduler;
+pub mod occlusion;
+pub mod chunk_cache;
+pub mod chunk_lod;
+pub mod mesher;
+pub mod surface_nets;
+pub mod dual_contouring;
+pub mod marching_tetrahedra;
+pub mod voxel_edits;
+pub mod edit_history;
+pub mod region_file;
+pub mod compact_grid;
+pub mod mesh_export;
+pub mod transition_cells;
\ No newline at end of file
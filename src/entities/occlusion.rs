@@ -0,0 +1,264 @@
+use std::collections::{HashSet, VecDeque};
+
+use cgmath::*;
+
+use crate::utils::chunk_space;
+
+//----------------------
+
+// the six faces of a chunk in the order -x, +x, -y, +y, -z, +z, so a face's
+// opposite is found by flipping the lowest bit
+pub const FACE_DIRECTIONS: [Vector3<i32>; 6] = [
+    Vector3::new(-1, 0, 0),
+    Vector3::new(1, 0, 0),
+    Vector3::new(0, -1, 0),
+    Vector3::new(0, 1, 0),
+    Vector3::new(0, 0, -1),
+    Vector3::new(0, 0, 1)
+];
+
+// which faces of a chunk can see each other through the air inside it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FaceConnectivity {
+    // bit j of faces[i] is set when face i is joined to face j by air
+    faces: [u8; 6]
+}
+
+//----------------------
+
+impl FaceConnectivity {
+    // every face sees every other face, used for chunks that aren't known yet
+    pub fn open() -> FaceConnectivity {
+        FaceConnectivity {
+            faces: [0b111111; 6]
+        }
+    }
+
+    // no face sees any other, as for a chunk of solid rock
+    pub fn closed() -> FaceConnectivity {
+        FaceConnectivity {
+            faces: [0; 6]
+        }
+    }
+
+    // flood fills the air samples of a chunk with the given number of samples
+    // along each axis, joining every face that each pocket of air touches
+    pub fn from_air<F: Fn(usize, usize, usize) -> bool>(chunk_bounds: usize, is_air: F) -> FaceConnectivity {
+        let mut connectivity = FaceConnectivity::closed();
+
+        let index = |x: usize, y: usize, z: usize| (x * chunk_bounds + y) * chunk_bounds + z;
+        let mut visited = vec![false; chunk_bounds * chunk_bounds * chunk_bounds];
+
+        let mut stack = Vec::<[usize; 3]>::new();
+
+        // only pockets that touch the border can join faces, so the fill
+        // starts from the samples on the faces
+        for x in 0..chunk_bounds {
+            for y in 0..chunk_bounds {
+                for z in 0..chunk_bounds {
+                    if FaceConnectivity::touched_faces([x, y, z], chunk_bounds) == 0 ||
+                        visited[index(x, y, z)] ||
+                        !is_air(x, y, z) {
+                        continue;
+                    }
+
+                    // fills the pocket, gathering the faces it touches
+                    let mut pocket_faces = 0_u8;
+
+                    visited[index(x, y, z)] = true;
+                    stack.push([x, y, z]);
+
+                    while let Some(sample) = stack.pop() {
+                        pocket_faces |= FaceConnectivity::touched_faces(sample, chunk_bounds);
+
+                        for direction in FACE_DIRECTIONS.iter() {
+                            let next = [
+                                sample[0] as i32 + direction.x,
+                                sample[1] as i32 + direction.y,
+                                sample[2] as i32 + direction.z
+                            ];
+
+                            if next.iter().any(|&n| n < 0 || n >= chunk_bounds as i32) {
+                                continue;
+                            }
+
+                            let (nx, ny, nz) = (next[0] as usize, next[1] as usize, next[2] as usize);
+
+                            if !visited[index(nx, ny, nz)] && is_air(nx, ny, nz) {
+                                visited[index(nx, ny, nz)] = true;
+                                stack.push([nx, ny, nz]);
+                            }
+                        }
+                    }
+
+                    // every face the pocket touches can see all the others
+                    for face in 0..6 {
+                        if pocket_faces & (1 << face) != 0 {
+                            connectivity.faces[face] |= pocket_faces;
+                        }
+                    }
+                }
+            }
+        }
+
+        connectivity
+    }
+
+    // gets whether something entering through one face can leave through another
+    pub fn connects(&self, from_face: usize, to_face: usize) -> bool {
+        self.faces[from_face] & (1 << to_face) != 0
+    }
+
+    // gets the faces a sample lies on as a bit set
+    fn touched_faces(sample: [usize; 3], chunk_bounds: usize) -> u8 {
+        let mut faces = 0_u8;
+
+        for (axis, &position) in sample.iter().enumerate() {
+            if position == 0 {
+                faces |= 1 << (axis * 2);
+            }
+
+            if position == chunk_bounds - 1 {
+                faces |= 1 << (axis * 2 + 1);
+            }
+        }
+
+        faces
+    }
+}
+
+//----------------------
+
+// gets the face on the other side of a face
+pub fn opposite_face(face: usize) -> usize {
+    face ^ 1
+}
+
+// walks outwards from the camera's chunk through faces joined by air, giving
+// every chunk within the radius that could be seen through open space.
+// the walk never turns back towards the camera, and chunks without known
+// connectivity are treated as open so nothing still loading gets hidden
+pub fn visible_chunks<F: Fn(Vector3<i32>) -> Option<FaceConnectivity>>(
+    camera_chunk: Vector3<i32>,
+    radius: i32,
+    connectivity: F
+) -> HashSet<Vector3<i32>> {
+    let mut visible = HashSet::new();
+
+    // chunks are only walked once per face they're entered through
+    let mut entered = HashSet::<(Vector3<i32>, usize)>::new();
+
+    // each step holds the chunk, the face it was entered through and the
+    // directions taken so far
+    let mut queue = VecDeque::<(Vector3<i32>, Option<usize>, u8)>::new();
+    queue.push_back((camera_chunk, None, 0));
+
+    while let Some((offset, entry_face, directions_taken)) = queue.pop_front() {
+        visible.insert(offset);
+
+        let chunk_connectivity = connectivity(offset).unwrap_or_else(FaceConnectivity::open);
+
+        for (face, direction) in FACE_DIRECTIONS.iter().enumerate() {
+            // doesn't walk back the way the camera's view came
+            if directions_taken & (1 << opposite_face(face)) != 0 {
+                continue;
+            }
+
+            // the camera can see out of every face of its own chunk
+            if let Some(entry_face) = entry_face {
+                if !chunk_connectivity.connects(entry_face, face) {
+                    continue;
+                }
+            }
+
+            let next = offset + direction;
+
+            if !chunk_space::chunk_within_radius(next, camera_chunk, radius) {
+                continue;
+            }
+
+            let next_entry = opposite_face(face);
+
+            if entered.insert((next, next_entry)) {
+                queue.push_back((next, Some(next_entry), directions_taken | (1 << face)));
+            }
+        }
+    }
+
+    visible
+}
+
+//----------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHUNK_BOUNDS: usize = 8;
+
+    // a chunk of rock with a tunnel of air running through the middle along the x axis
+    fn tunnel() -> FaceConnectivity {
+        FaceConnectivity::from_air(CHUNK_BOUNDS, |_, y, z| (3..5).contains(&y) && (3..5).contains(&z))
+    }
+
+    #[test]
+    fn air_joins_only_the_faces_it_touches() {
+        let rock = FaceConnectivity::from_air(CHUNK_BOUNDS, |_, _, _| false);
+        assert_eq!(rock, FaceConnectivity::closed());
+
+        let air = FaceConnectivity::from_air(CHUNK_BOUNDS, |_, _, _| true);
+        assert_eq!(air, FaceConnectivity::open());
+
+        let tunnel = tunnel();
+
+        assert!(tunnel.connects(0, 1) && tunnel.connects(1, 0));
+
+        for face in 2..6 {
+            assert!(!tunnel.connects(0, face) && !tunnel.connects(face, 1));
+        }
+
+        // a cave that doesn't reach the border can't join anything
+        let cave = FaceConnectivity::from_air(CHUNK_BOUNDS, |x, y, z| [x, y, z].iter().all(|&n| (2..6).contains(&n)));
+        assert_eq!(cave, FaceConnectivity::closed());
+    }
+
+    #[test]
+    fn a_solid_wall_hides_the_chunks_behind_it() {
+        // every chunk two along x is rock, the rest are open
+        let visible = visible_chunks(Vector3::new(0, 0, 0), 4, |offset| {
+            if offset.x == 2 { Some(FaceConnectivity::closed()) } else { Some(FaceConnectivity::open()) }
+        });
+
+        assert!(visible.contains(&Vector3::new(1, 0, 0)));
+        assert!(visible.contains(&Vector3::new(-3, 1, 0)));
+
+        // the wall itself can be seen, but nothing past it
+        assert!(visible.contains(&Vector3::new(2, 0, 0)));
+        assert!(visible.iter().all(|offset| offset.x <= 2));
+    }
+
+    #[test]
+    fn a_tunnel_through_the_wall_shows_the_chunks_behind_it() {
+        let visible = visible_chunks(Vector3::new(0, 0, 0), 4, |offset| {
+            match (offset.x, offset.y, offset.z) {
+                (2, 0, 0) => Some(tunnel()),
+                (2, _, _) => Some(FaceConnectivity::closed()),
+                _ => Some(FaceConnectivity::open())
+            }
+        });
+
+        // the view comes out of the tunnel and spreads out beyond the wall
+        assert!(visible.contains(&Vector3::new(3, 0, 0)));
+        assert!(visible.contains(&Vector3::new(4, 0, 0)));
+        assert!(visible.contains(&Vector3::new(3, 1, 0)));
+    }
+
+    #[test]
+    fn chunks_not_built_yet_are_treated_as_open() {
+        let visible = visible_chunks(Vector3::new(0, 0, 0), 3, |offset| {
+            if offset.x == 1 { None } else { Some(FaceConnectivity::open()) }
+        });
+
+        assert!(visible.contains(&Vector3::new(3, 0, 0)));
+    }
+}
@@ -0,0 +1,988 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use cgmath::*;
+use ndarray::Array3;
+
+use crate::utils::{
+    chunk_space,
+    constants::*,
+    world_config::WorldConfig
+};
+
+use super::{
+    chunk_lod::ChunkKey,
+    basic_marching_cubes::ChunkSamples,
+    mesher::{Mask, SampledGrid}
+};
+
+//------------------------
+
+// every region file starts with these, followed by the version it was
+// written with. the version goes up whenever the layout changes, and older
+// versions can still be read. version 2 added uniform chunks
+const REGION_MAGIC: &[u8; 4] = b"ZRGN";
+const REGION_VERSION: u16 = 2;
+
+// the kinds of entry a region file holds
+const EDITS_ENTRY: u8 = 0;
+const GRID_ENTRY: u8 = 1;
+const UNIFORM_ENTRY: u8 = 2;
+
+// how a grid's densities are stored, packed unless that would be bigger
+const RAW_DENSITIES: u8 = 0;
+const PACKED_DENSITIES: u8 = 1;
+
+// what's saved in one region file, the edited samples of the full detail
+// chunks in the region and the samples kept by any built chunks
+#[derive(Default)]
+pub struct RegionData {
+    pub edits: Vec<(Vector3<i32>, f32)>,
+    pub samples: HashMap<ChunkKey, ChunkSamples>
+}
+
+// a directory of region files, each holding a cube of REGION_CHUNKS full
+// detail chunks along each axis. coarser chunks are kept in the region their
+// lowest full detail chunk is in. every file starts with the options that
+// decide the density, so a world can't be loaded into different caves.
+//
+// the edits are all read up front since any chunk could share samples with
+// them, the chunk samples are read a region at a time as chunks in it are needed
+pub struct RegionStore {
+    directory: PathBuf,
+    world_config: WorldConfig,
+    header: String,
+
+    // samples read from disk that haven't been used yet, and the regions read
+    loaded_samples: HashMap<ChunkKey, ChunkSamples>,
+    read_regions: HashSet<Vector3<i32>>,
+
+    // chunks whose saved samples have been edited since they were saved
+    discarded_samples: HashSet<ChunkKey>
+}
+
+//------------------------
+
+impl RegionStore {
+    // opens a world directory, creating it if it doesn't exist yet
+    pub fn open(directory: &Path, world_config: &WorldConfig) -> Result<RegionStore, String> {
+        fs::create_dir_all(directory)
+            .map_err(|error| format!("couldn't create world directory '{}': {}", directory.display(), error))?;
+
+        Ok(RegionStore {
+            directory: directory.to_path_buf(),
+            world_config: world_config.clone(),
+            header: world_config.density_args(),
+
+            loaded_samples: HashMap::new(),
+            read_regions: HashSet::new(),
+
+            discarded_samples: HashSet::new()
+        })
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    // gets the region a chunk is stored in
+    pub fn region_of(key: ChunkKey) -> Vector3<i32> {
+        (key.offset * key.scale()).map(|value| value.div_euclid(REGION_CHUNKS))
+    }
+
+    // gets the region a chunk read from a file is stored in, or None if its
+    // level of detail or offset is too big for it to be in any region
+    fn checked_region_of(key: ChunkKey) -> Option<Vector3<i32>> {
+        if key.lod > MAX_LOD_LEVELS {
+            return None;
+        }
+
+        let scale = key.scale();
+
+        Some(Vector3::new(
+            key.offset.x.checked_mul(scale)?.div_euclid(REGION_CHUNKS),
+            key.offset.y.checked_mul(scale)?.div_euclid(REGION_CHUNKS),
+            key.offset.z.checked_mul(scale)?.div_euclid(REGION_CHUNKS)
+        ))
+    }
+
+    // gets the path of a region's file
+    pub fn region_path(&self, region: Vector3<i32>) -> PathBuf {
+        self.directory.join(format!("r.{}.{}.{}.region", region.x, region.y, region.z))
+    }
+
+    //--------------------
+
+    // reads the edited samples out of every region file. a region that can't
+    // be read is added to the errors and skipped, so its chunks are generated
+    // without its edits, and isn't read again for its samples
+    pub fn read_edits(&mut self, errors: &mut Vec<String>) -> Result<HashMap<Vector3<i32>, f32>, String> {
+        let mut edits = HashMap::new();
+
+        for (region, path) in self.region_files()? {
+            match self.read_region_file(&path, region) {
+                Ok(data) => edits.extend(data.edits),
+                Err(error) => {
+                    errors.push(error);
+                    self.read_regions.insert(region);
+                }
+            }
+        }
+
+        Ok(edits)
+    }
+
+    // takes the saved samples of a chunk, reading its region the first time
+    // one of its chunks is asked for. a region that can't be read is only
+    // reported once, and its chunks are generated instead
+    pub fn take_samples(&mut self, key: ChunkKey) -> Result<Option<ChunkSamples>, String> {
+        let region = RegionStore::region_of(key);
+
+        if self.read_regions.insert(region) {
+            let path = self.region_path(region);
+
+            if path.exists() {
+                let data = self.read_region_file(&path, region)?;
+
+                for (samples_key, samples) in data.samples {
+                    if !self.discarded_samples.contains(&samples_key) {
+                        self.loaded_samples.insert(samples_key, samples);
+                    }
+                }
+            }
+        }
+
+        Ok(self.loaded_samples.remove(&key))
+    }
+
+    // forgets the saved samples of a chunk that's been edited
+    pub fn discard_samples(&mut self, key: ChunkKey) {
+        self.loaded_samples.remove(&key);
+        self.discarded_samples.insert(key);
+    }
+
+    // writes the edits and chunk samples into their regions, keeping the
+    // samples already on disk that are still up to date. a region whose file
+    // can't be read is added to the errors and left as it is, rather than
+    // writing over whatever's in it. gives back how many files were written
+    pub fn save(
+        &mut self,
+        edits: &HashMap<Vector3<i32>, f32>,
+        samples: &[(ChunkKey, &ChunkSamples)],
+        errors: &mut Vec<String>
+    ) -> Result<usize, String> {
+        let cells = chunk_space::cells_per_chunk(self.world_config.chunk_bounds);
+
+        let mut regions: HashMap<Vector3<i32>, RegionData> = HashMap::new();
+
+        for (region, _) in self.region_files()? {
+            regions.entry(region).or_default();
+        }
+
+        for (sample, value) in edits.iter() {
+            let chunk = sample.map(|value| value.div_euclid(cells));
+
+            regions.entry(RegionStore::region_of(ChunkKey::base(chunk))).or_default().edits.push((*sample, *value));
+        }
+
+        // reads the samples already saved in each region, dropping the edited ones
+        let mut unreadable_regions = HashSet::new();
+
+        for (region, data) in regions.iter_mut() {
+            let path = self.region_path(*region);
+
+            if !path.exists() {
+                continue;
+            }
+
+            match self.read_region_file(&path, *region) {
+                Ok(saved_data) => {
+                    for (key, saved) in saved_data.samples {
+                        if !self.discarded_samples.contains(&key) {
+                            data.samples.insert(key, saved);
+                        }
+                    }
+                }
+                Err(error) => {
+                    errors.push(format!("{}, so it wasn't saved over", error));
+                    unreadable_regions.insert(*region);
+                }
+            }
+        }
+
+        for (key, chunk_samples) in samples.iter() {
+            regions.entry(RegionStore::region_of(*key)).or_default().samples.insert(*key, (*chunk_samples).clone());
+        }
+
+        let mut written = 0;
+
+        for (region, data) in regions.iter() {
+            if unreadable_regions.contains(region) {
+                continue;
+            }
+
+            let path = self.region_path(*region);
+
+            // regions left with nothing in them are removed
+            if data.edits.is_empty() && data.samples.is_empty() {
+                fs::remove_file(&path)
+                    .map_err(|error| format!("couldn't remove region file '{}': {}", path.display(), error))?;
+
+                continue;
+            }
+
+            self.write_region_file(&path, *region, data)?;
+            written += 1;
+        }
+
+        self.discarded_samples.clear();
+
+        Ok(written)
+    }
+
+    //--------------------
+
+    // reads and checks a whole region file
+    pub fn read_region_file(&self, path: &Path, region: Vector3<i32>) -> Result<RegionData, String> {
+        let bytes = fs::read(path)
+            .map_err(|error| format!("couldn't read region file '{}': {}", path.display(), error))?;
+
+        self.decode_region(&bytes, region)
+            .map_err(|error| format!("region file '{}' {}", path.display(), error))
+    }
+
+    // writes a region file, going through a temporary file so a crash part
+    // way through never leaves a half written region behind
+    fn write_region_file(&self, path: &Path, region: Vector3<i32>, data: &RegionData) -> Result<(), String> {
+        let bytes = self.encode_region(region, data);
+        let temporary_path = path.with_extension("region.tmp");
+
+        fs::write(&temporary_path, &bytes)
+            .and_then(|_| fs::rename(&temporary_path, path))
+            .map_err(|error| format!("couldn't write region file '{}': {}", path.display(), error))
+    }
+
+    // finds every region file in the directory along with its region
+    fn region_files(&self) -> Result<Vec<(Vector3<i32>, PathBuf)>, String> {
+        let entries = fs::read_dir(&self.directory)
+            .map_err(|error| format!("couldn't read world directory '{}': {}", self.directory.display(), error))?;
+
+        let mut files = Vec::new();
+
+        for entry in entries {
+            let path = entry
+                .map_err(|error| format!("couldn't read world directory '{}': {}", self.directory.display(), error))?
+                .path();
+
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+            let parts: Vec<&str> = name.split('.').collect();
+
+            if parts.len() != 5 || parts[0] != "r" || parts[4] != "region" {
+                continue;
+            }
+
+            let coordinates: Vec<i32> = parts[1..4].iter().filter_map(|part| part.parse().ok()).collect();
+
+            if coordinates.len() == 3 {
+                files.push((Vector3::new(coordinates[0], coordinates[1], coordinates[2]), path));
+            }
+        }
+
+        Ok(files)
+    }
+
+    //--------------------
+
+    // lays a region out as the magic, version and density options, then its
+    // entries and a checksum of everything before it
+    fn encode_region(&self, region: Vector3<i32>, data: &RegionData) -> Vec<u8> {
+        let cells = chunk_space::cells_per_chunk(self.world_config.chunk_bounds);
+
+        let mut writer = ByteWriter::new();
+
+        writer.put_bytes(REGION_MAGIC);
+        writer.put_u16(REGION_VERSION);
+        writer.put_string(&self.header);
+        writer.put_vector(region);
+
+        // groups the edits by the full detail chunk they're in, each chunk's
+        // samples ordered so their offsets can be stored as small steps
+        let mut chunk_edits: HashMap<Vector3<i32>, Vec<(u64, f32)>> = HashMap::new();
+
+        for (sample, value) in data.edits.iter() {
+            let chunk = sample.map(|value| value.div_euclid(cells));
+            let local = sample - chunk * cells;
+            let index = ((local.x * cells + local.y) * cells + local.z) as u64;
+
+            chunk_edits.entry(chunk).or_default().push((index, *value));
+        }
+
+        writer.put_u32((chunk_edits.len() + data.samples.len()) as u32);
+
+        for (chunk, mut edits) in chunk_edits {
+            edits.sort_unstable_by_key(|(index, _)| *index);
+
+            let mut payload = ByteWriter::new();
+            payload.put_varint(edits.len() as u64);
+
+            let mut last_index = 0;
+
+            for (index, _) in edits.iter() {
+                payload.put_varint(index - last_index);
+                last_index = *index;
+            }
+
+            payload.put_floats(edits.iter().map(|(_, value)| *value));
+
+            writer.put_entry(EDITS_ENTRY, ChunkKey::base(chunk), &payload.bytes);
+        }
+
+        for (key, chunk_samples) in data.samples.iter() {
+            let mut payload = ByteWriter::new();
+
+            let grid = match chunk_samples {
+                ChunkSamples::Uniform { mask, padding } => {
+                    payload.put_varint(*padding as u64);
+                    payload.put_u8(*mask as u8);
+
+                    writer.put_entry(UNIFORM_ENTRY, *key, &payload.bytes);
+                    continue;
+                }
+                ChunkSamples::Mixed(grid) => grid
+            };
+
+            payload.put_varint(grid.padding() as u64);
+
+            let mut packed = ByteWriter::new();
+            packed.put_floats(grid.weights().to_vec().into_iter());
+
+            if packed.bytes.len() < grid.weights().len() * 4 {
+                payload.put_u8(PACKED_DENSITIES);
+                payload.put_bytes(&packed.bytes);
+            }
+            else {
+                payload.put_u8(RAW_DENSITIES);
+
+                for weight in grid.weights().to_vec() {
+                    payload.put_bytes(&weight.to_le_bytes());
+                }
+            }
+
+            writer.put_entry(GRID_ENTRY, *key, &payload.bytes);
+        }
+
+        let checksum = fnv1a(&writer.bytes);
+        writer.put_u32(checksum);
+
+        writer.bytes
+    }
+
+    fn decode_region(&self, bytes: &[u8], region: Vector3<i32>) -> Result<RegionData, String> {
+        let cells = chunk_space::cells_per_chunk(self.world_config.chunk_bounds);
+
+        let mut reader = ByteReader::new(bytes);
+
+        if reader.take(REGION_MAGIC.len()).ok() != Some(&REGION_MAGIC[..]) {
+            return Err(String::from("isn't a region file"));
+        }
+
+        let version = reader.u16().map_err(|_| String::from("is truncated"))?;
+
+        if version == 0 || version > REGION_VERSION {
+            return Err(format!("has version {}, but only versions up to {} can be read", version, REGION_VERSION));
+        }
+
+        // checks the whole file made it to disk intact before trusting any of
+        // the lengths inside it
+        if bytes.len() < reader.position + 4 {
+            return Err(String::from("is truncated"));
+        }
+
+        let (contents, checksum) = bytes.split_at(bytes.len() - 4);
+
+        if fnv1a(contents) != u32::from_le_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]) {
+            return Err(String::from("is corrupt or truncated, its checksum doesn't match"));
+        }
+
+        let mut reader = ByteReader::new(contents);
+        reader.position = REGION_MAGIC.len() + 2;
+
+        let corrupt = |error: String| format!("is corrupt: {}", error);
+
+        let header = reader.string().map_err(corrupt)?;
+
+        if header != self.header {
+            return Err(format!("was saved for a different world, '{}' rather than '{}'", header, self.header));
+        }
+
+        let file_region = reader.vector().map_err(corrupt)?;
+
+        if file_region != region {
+            return Err(format!("holds region {:?} rather than {:?}", file_region, region));
+        }
+
+        let mut data = RegionData::default();
+
+        for _ in 0..reader.u32().map_err(corrupt)? {
+            let kind = reader.u8().map_err(corrupt)?;
+            let lod = reader.u8().map_err(corrupt)? as u32;
+            let offset = reader.vector().map_err(corrupt)?;
+            let length = reader.u32().map_err(corrupt)? as usize;
+
+            // a file with a good checksum can still have been written wrongly,
+            // so nothing is trusted that could overflow working out where it goes
+            if lod > MAX_LOD_LEVELS {
+                return Err(corrupt(format!("chunk {:?} has level of detail {}", offset, lod)));
+            }
+
+            let key = ChunkKey::new(lod, offset);
+
+            if RegionStore::checked_region_of(key) != Some(region) {
+                return Err(corrupt(format!("chunk {:?} at level of detail {} isn't in the region", offset, lod)));
+            }
+            let mut payload = ByteReader::new(reader.take(length).map_err(corrupt)?);
+
+            match kind {
+                EDITS_ENTRY => {
+                    if lod != 0 {
+                        return Err(corrupt(format!("the edits of chunk {:?} are at level of detail {}", offset, lod)));
+                    }
+
+                    let count = payload.varint().map_err(corrupt)? as usize;
+                    let volume = (cells * cells * cells) as u64;
+
+                    let mut indices = Vec::with_capacity(count.min(volume as usize));
+                    let mut index = 0_u64;
+
+                    for _ in 0..count {
+                        let step = payload.varint().map_err(corrupt)?;
+
+                        index = match index.checked_add(step) {
+                            Some(index) => index,
+                            None => return Err(corrupt(format!("an edit in chunk {:?} is outside it", offset)))
+                        };
+
+                        if index >= volume {
+                            return Err(corrupt(format!("an edit in chunk {:?} is outside it", offset)));
+                        }
+
+                        indices.push(index as i32);
+                    }
+
+                    let values = payload.floats(count).map_err(corrupt)?;
+
+                    // the last sample of the chunk has to fit as well as its first
+                    let origin_of = |value: i32| value.checked_mul(cells).filter(|origin| origin.checked_add(cells).is_some());
+
+                    let origin = match (origin_of(offset.x), origin_of(offset.y), origin_of(offset.z)) {
+                        (Some(x), Some(y), Some(z)) => Vector3::new(x, y, z),
+                        _ => return Err(corrupt(format!("the edits of chunk {:?} are too far out", offset)))
+                    };
+
+                    for (index, value) in indices.into_iter().zip(values) {
+                        let local = Vector3::new(index / (cells * cells), (index / cells) % cells, index % cells);
+
+                        data.edits.push((origin + local, value));
+                    }
+                }
+                GRID_ENTRY => {
+                    let padding = payload.varint().map_err(corrupt)? as usize;
+
+                    if padding > self.world_config.chunk_bounds {
+                        return Err(corrupt(format!("the grid of chunk {:?} has padding {}", offset, padding)));
+                    }
+
+                    let size = self.world_config.chunk_bounds + padding * 2;
+
+                    let count = size * size * size;
+
+                    let values = match payload.u8().map_err(corrupt)? {
+                        PACKED_DENSITIES => payload.floats(count).map_err(corrupt)?,
+                        RAW_DENSITIES => payload.take(count * 4).map_err(corrupt)?
+                            .chunks(4)
+                            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                            .collect(),
+                        encoding => return Err(corrupt(format!("unknown density encoding {}", encoding)))
+                    };
+                    let weights = Array3::from_shape_vec((size, size, size), values).unwrap();
+
+                    data.samples.insert(key, ChunkSamples::Mixed(SampledGrid::from_weights(
+                        weights,
+                        padding,
+                        offset,
+                        self.world_config.chunk_bounds,
+                        key.spacing(self.world_config.spacing_per_vertex),
+                        self.world_config.isovalue
+                    )));
+                }
+                UNIFORM_ENTRY => {
+                    let padding = payload.varint().map_err(corrupt)? as usize;
+
+                    let mask = match payload.u8().map_err(corrupt)? {
+                        0 => Mask::Minus,
+                        1 => Mask::Plus,
+                        value => return Err(corrupt(format!("chunk {:?} is marked as uniform side {}", offset, value)))
+                    };
+
+                    data.samples.insert(key, ChunkSamples::Uniform { mask, padding });
+                }
+                _ => return Err(corrupt(format!("unknown entry kind {}", kind)))
+            }
+        }
+
+        Ok(data)
+    }
+}
+
+//------------------------
+
+// builds up the bytes of a file, all numbers little endian
+struct ByteWriter {
+    bytes: Vec<u8>
+}
+
+impl ByteWriter {
+    fn new() -> ByteWriter {
+        ByteWriter {
+            bytes: Vec::new()
+        }
+    }
+
+    fn put_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    fn put_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn put_u16(&mut self, value: u16) {
+        self.put_bytes(&value.to_le_bytes());
+    }
+
+    fn put_u32(&mut self, value: u32) {
+        self.put_bytes(&value.to_le_bytes());
+    }
+
+    fn put_vector(&mut self, vector: Vector3<i32>) {
+        for axis in 0..3 {
+            self.put_bytes(&vector[axis].to_le_bytes());
+        }
+    }
+
+    fn put_string(&mut self, value: &str) {
+        self.put_u32(value.len() as u32);
+        self.put_bytes(value.as_bytes());
+    }
+
+    // writes a number seven bits at a time, so small ones take one byte
+    fn put_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.put_u8((value as u8 & 0x7f) | 0x80);
+            value >>= 7;
+        }
+
+        self.put_u8(value as u8);
+    }
+
+    // writes densities without losing any bits, as each one xored with the
+    // one before. neighbouring densities share their sign and exponent so the
+    // results are small, and runs of the same density are stored as a zero
+    // followed by how many more zeros there are
+    fn put_floats<I: Iterator<Item = f32>>(&mut self, values: I) {
+        let mut last_bits = 0;
+        let mut zero_run = 0;
+
+        for value in values {
+            let bits = value.to_bits();
+            let difference = bits ^ last_bits;
+            last_bits = bits;
+
+            if difference == 0 {
+                zero_run += 1;
+                continue;
+            }
+
+            self.put_zero_run(zero_run);
+            zero_run = 0;
+
+            self.put_varint(difference as u64);
+        }
+
+        self.put_zero_run(zero_run);
+    }
+
+    fn put_zero_run(&mut self, zero_run: u64) {
+        if zero_run > 0 {
+            self.put_varint(0);
+            self.put_varint(zero_run - 1);
+        }
+    }
+
+    fn put_entry(&mut self, kind: u8, key: ChunkKey, payload: &[u8]) {
+        self.put_u8(kind);
+        self.put_u8(key.lod as u8);
+        self.put_vector(key.offset);
+        self.put_u32(payload.len() as u32);
+        self.put_bytes(payload);
+    }
+}
+
+// reads back what a byte writer wrote, failing rather than reading past the end
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(bytes: &'a [u8]) -> ByteReader<'a> {
+        ByteReader {
+            bytes,
+            position: 0
+        }
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8], String> {
+        if self.bytes.len() - self.position < length {
+            return Err(format!("expected {} more bytes at byte {}", length, self.position));
+        }
+
+        let bytes = &self.bytes[self.position..(self.position + length)];
+        self.position += length;
+
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let bytes = self.take(2)?;
+
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn vector(&mut self) -> Result<Vector3<i32>, String> {
+        let x = self.u32()? as i32;
+        let y = self.u32()? as i32;
+        let z = self.u32()? as i32;
+
+        Ok(Vector3::new(x, y, z))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let length = self.u32()? as usize;
+
+        String::from_utf8(self.take(length)?.to_vec()).map_err(|_| String::from("a string isn't valid utf-8"))
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(format!("a number runs on too long at byte {}", self.position))
+    }
+
+    fn floats(&mut self, count: usize) -> Result<Vec<f32>, String> {
+        let mut values = Vec::with_capacity(count.min(self.bytes.len()));
+        let mut last_bits = 0_u32;
+
+        while values.len() < count {
+            let difference = self.varint()?;
+
+            if difference == 0 {
+                // the run holds one more density than it says
+                let run = self.varint()?;
+
+                if run >= (count - values.len()) as u64 {
+                    return Err(String::from("a run of densities goes past the end of its entry"));
+                }
+
+                values.extend(std::iter::repeat_n(f32::from_bits(last_bits), run as usize + 1));
+            }
+            else {
+                if difference > u32::MAX as u64 {
+                    return Err(String::from("a density is out of range"));
+                }
+
+                last_bits ^= difference as u32;
+                values.push(f32::from_bits(last_bits));
+            }
+        }
+
+        Ok(values)
+    }
+}
+
+//------------------------
+
+// a simple hash of the bytes of a file, enough to spot damage
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ *byte as u32).wrapping_mul(0x0100_0193))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::density_field::DensityField;
+
+    // a fresh directory for a test to save a world in
+    fn world_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("zendemo-region-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+
+        directory
+    }
+
+    // saves a few edits, a sampled chunk and a uniform chunk, all in region
+    // 0 0 0, and gives back the path of the region file
+    fn save_world(directory: &Path, world_config: &WorldConfig) -> (HashMap<Vector3<i32>, f32>, SampledGrid, PathBuf) {
+        let density = world_config.create_density_field();
+
+        let grid = SampledGrid::sample(
+            Vector3::new(1, 2, 3),
+            world_config.chunk_bounds,
+            world_config.spacing_per_vertex,
+            world_config.isovalue,
+            1,
+            density.as_ref() as &dyn DensityField
+        );
+
+        let edits: HashMap<Vector3<i32>, f32> = vec![
+            (Vector3::new(0, 0, 0), 0.5),
+            (Vector3::new(3, 4, 5), -1.25),
+            (Vector3::new(6, 6, 6), f32::MIN_POSITIVE),
+            (Vector3::new(20, 9, 1), 2.0)
+        ].into_iter().collect();
+
+        let mixed = ChunkSamples::Mixed(grid.clone());
+        let uniform = ChunkSamples::Uniform { mask: Mask::Plus, padding: 1 };
+
+        let mut store = RegionStore::open(directory, world_config).unwrap();
+        let mut errors = Vec::new();
+        let written = store.save(&edits, &[
+            (ChunkKey::base(Vector3::new(1, 2, 3)), &mixed),
+            (ChunkKey::new(1, Vector3::new(2, 0, 1)), &uniform)
+        ], &mut errors).unwrap();
+
+        assert_eq!(written, 1);
+        assert!(errors.is_empty());
+
+        (edits, grid, store.region_path(Vector3::new(0, 0, 0)))
+    }
+
+    // reads the edits of a saved world, along with the problems reading it
+    fn read_edits(directory: &Path, world_config: &WorldConfig) -> (HashMap<Vector3<i32>, f32>, Vec<String>) {
+        let mut errors = Vec::new();
+        let edits = RegionStore::open(directory, world_config).unwrap().read_edits(&mut errors).unwrap();
+
+        (edits, errors)
+    }
+
+    #[test]
+    fn saved_worlds_read_back_exactly() {
+        let directory = world_directory("round-trip");
+        let world_config = WorldConfig::new();
+        let (edits, grid, _) = save_world(&directory, &world_config);
+
+        let mut store = RegionStore::open(&directory, &world_config).unwrap();
+        let mut errors = Vec::new();
+        let read_edits = store.read_edits(&mut errors).unwrap();
+
+        assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+        assert_eq!(read_edits.len(), edits.len());
+
+        for (sample, value) in edits.iter() {
+            assert_eq!(read_edits[sample].to_bits(), value.to_bits());
+        }
+
+        match store.take_samples(ChunkKey::base(Vector3::new(1, 2, 3))).unwrap() {
+            Some(ChunkSamples::Mixed(read_grid)) => {
+                assert_eq!(read_grid.padding(), grid.padding());
+
+                let bits = |grid: &SampledGrid| grid.weights().to_vec().iter().map(|weight| weight.to_bits()).collect::<Vec<u32>>();
+                assert_eq!(bits(&read_grid), bits(&grid));
+            }
+            _ => panic!("the sampled chunk wasn't read back")
+        }
+
+        match store.take_samples(ChunkKey::new(1, Vector3::new(2, 0, 1))).unwrap() {
+            Some(ChunkSamples::Uniform { mask, padding }) => assert_eq!((mask, padding), (Mask::Plus, 1)),
+            _ => panic!("the uniform chunk wasn't read back")
+        }
+
+        assert!(store.take_samples(ChunkKey::base(Vector3::new(0, 0, 0))).unwrap().is_none());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn truncated_files_are_reported() {
+        let directory = world_directory("truncated");
+        let world_config = WorldConfig::new();
+        let (_, _, path) = save_world(&directory, &world_config);
+
+        let bytes = fs::read(&path).unwrap();
+
+        for length in [bytes.len() - 1, bytes.len() / 2, 7, 3, 0].iter() {
+            fs::write(&path, &bytes[..*length]).unwrap();
+
+            let (edits, errors) = read_edits(&directory, &world_config);
+
+            assert!(edits.is_empty());
+            assert_eq!(errors.len(), 1, "a file cut to {} bytes was read", length);
+        }
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn flipped_bits_are_reported() {
+        let directory = world_directory("bit-flipped");
+        let world_config = WorldConfig::new();
+        let (_, _, path) = save_world(&directory, &world_config);
+
+        let bytes = fs::read(&path).unwrap();
+
+        for position in [REGION_MAGIC.len() + 2, bytes.len() / 2, bytes.len() - 5, bytes.len() - 1].iter() {
+            let mut flipped = bytes.clone();
+            flipped[*position] ^= 0x10;
+            fs::write(&path, &flipped).unwrap();
+
+            let (edits, errors) = read_edits(&directory, &world_config);
+
+            assert!(edits.is_empty());
+            assert_eq!(errors.len(), 1);
+            assert!(errors[0].contains("checksum"), "unexpected error for a bit flipped at {}: {}", position, errors[0]);
+        }
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn files_from_another_world_are_reported() {
+        let directory = world_directory("wrong-header");
+        let world_config = WorldConfig::new();
+        save_world(&directory, &world_config);
+
+        let mut other_config = world_config.clone();
+        other_config.seed += 1;
+
+        let (edits, errors) = read_edits(&directory, &other_config);
+
+        assert!(edits.is_empty());
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("different world"), "unexpected error: {}", errors[0]);
+
+        let mut store = RegionStore::open(&directory, &other_config).unwrap();
+        assert!(store.take_samples(ChunkKey::base(Vector3::new(1, 2, 3))).is_err());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn unreadable_regions_are_skipped_and_left_as_they_are() {
+        let directory = world_directory("skipped");
+        let world_config = WorldConfig::new();
+        let (mut all_edits, _, bad_path) = save_world(&directory, &world_config);
+
+        // an edit in another region, which should still load
+        let cells = chunk_space::cells_per_chunk(world_config.chunk_bounds);
+        let far_sample = Vector3::new(REGION_CHUNKS * cells * 3, 0, 0);
+
+        all_edits.insert(far_sample, 0.75);
+
+        let mut errors = Vec::new();
+        RegionStore::open(&directory, &world_config).unwrap().save(&all_edits, &[], &mut errors).unwrap();
+
+        let mut bad_bytes = fs::read(&bad_path).unwrap();
+        bad_bytes.truncate(bad_bytes.len() / 2);
+        fs::write(&bad_path, &bad_bytes).unwrap();
+
+        let (edits, errors) = read_edits(&directory, &world_config);
+
+        assert_eq!(edits, vec![(far_sample, 0.75)].into_iter().collect());
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains(&bad_path.display().to_string()), "unexpected error: {}", errors[0]);
+
+        // saving writes the readable region and doesn't touch the other
+        let mut store = RegionStore::open(&directory, &world_config).unwrap();
+        let mut errors = Vec::new();
+        let written = store.save(&all_edits, &[], &mut errors).unwrap();
+
+        assert_eq!(written, 1);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(fs::read(&bad_path).unwrap(), bad_bytes);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn bad_entries_with_good_checksums_are_reported() {
+        let world_config = WorldConfig::new();
+        let store = RegionStore::open(&world_directory("bad-entries"), &world_config).unwrap();
+        let region = Vector3::new(0, 0, 0);
+
+        // a level of detail far too big to scale by
+        let mut data = RegionData::default();
+        data.samples.insert(ChunkKey::new(40, Vector3::new(0, 0, 0)), ChunkSamples::Uniform { mask: Mask::Minus, padding: 1 });
+
+        let error = store.decode_region(&store.encode_region(region, &data), region).err().unwrap();
+        assert!(error.contains("level of detail"), "unexpected error: {}", error);
+
+        // a chunk saved in the wrong region
+        let mut data = RegionData::default();
+        data.samples.insert(ChunkKey::base(Vector3::new(REGION_CHUNKS, 0, 0)), ChunkSamples::Uniform { mask: Mask::Minus, padding: 1 });
+
+        assert!(store.decode_region(&store.encode_region(region, &data), region).is_err());
+
+        // edit offsets that step past the end of a u64
+        let mut payload = ByteWriter::new();
+        payload.put_varint(2);
+        payload.put_varint(1);
+        payload.put_varint(u64::MAX);
+        payload.put_floats([1.0, 2.0].iter().copied());
+
+        // a run of densities longer than any count
+        let mut run_payload = ByteWriter::new();
+        run_payload.put_varint(1);
+        run_payload.put_varint(0);
+        run_payload.put_varint(0);
+        run_payload.put_varint(u64::MAX);
+
+        for payload in [payload, run_payload].iter() {
+            let mut writer = ByteWriter::new();
+            writer.put_bytes(REGION_MAGIC);
+            writer.put_u16(REGION_VERSION);
+            writer.put_string(&store.header);
+            writer.put_vector(region);
+            writer.put_u32(1);
+            writer.put_entry(EDITS_ENTRY, ChunkKey::base(Vector3::new(0, 0, 0)), &payload.bytes);
+
+            let checksum = fnv1a(&writer.bytes);
+            writer.put_u32(checksum);
+
+            let error = store.decode_region(&writer.bytes, region).err().unwrap();
+            assert!(error.starts_with("is corrupt"), "unexpected error: {}", error);
+        }
+
+        fs::remove_dir_all(store.directory()).unwrap();
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+
+use cgmath::*;
+
+use super::mesher::{Mesh, Mesher, SampledGrid};
+
+//----------------------
+
+// the corners of a cell, as offsets from its lowest sample
+pub const CELL_CORNERS: [[i32; 3]; 8] = [
+    [0, 0, 0], [1, 0, 0], [0, 1, 0], [1, 1, 0],
+    [0, 0, 1], [1, 0, 1], [0, 1, 1], [1, 1, 1]
+];
+
+// naive surface nets, placing one vertex in each cell the surface passes
+// through at the average of its edge crossings and joining the vertices of
+// the four cells around every crossed edge with a quad. smoother than marching
+// cubes with fewer triangles, but the mesh no longer ends on the chunk faces
+pub struct SurfaceNetsMesher;
+
+//----------------------
+
+impl SurfaceNetsMesher {
+    pub fn new() -> SurfaceNetsMesher {
+        SurfaceNetsMesher
+    }
+}
+
+impl Mesher for SurfaceNetsMesher {
+    // the cells along the low faces start one sample outside the chunk
+    fn padding(&self) -> usize {
+        1
+    }
+
+    fn meshes_to_chunk_faces(&self) -> bool {
+        false
+    }
+
+    fn mesh(&self, grid: &SampledGrid) -> Mesh {
+        mesh_dual_grid(grid, |cell| {
+            // averages the points the surface crosses the cell's edges
+            let crossings = cell_crossings(grid, cell);
+            let position = crossings.iter().fold(Vector3::new(0.0, 0.0, 0.0), |sum, crossing| sum + crossing) /
+                crossings.len() as f32;
+
+            (position, cell_normal(grid, cell))
+        })
+    }
+}
+
+//----------------------
+
+// builds the quads of a dual mesh, leaving where each cell's vertex sits to
+// the mesher. every edge is owned by the chunk its lower sample falls in, going
+// by the same half open sample ranges chunks are spaced by, so the quads
+// bridging two chunks are built once and from the same samples on either side
+pub fn mesh_dual_grid<F>(grid: &SampledGrid, place_vertex: F) -> Mesh
+where
+    F: Fn(Vector3<i32>) -> (Vector3<f32>, Vector3<f32>)
+{
+    let cells = grid.chunk_bounds as i32 - 1;
+
+    let mut mesh = Mesh::new();
+
+    // caches the index of each cell's vertex, keyed by the cell's lowest sample
+    let mut cell_vertices = HashMap::<[i32; 3], u32>::new();
+
+    for x in 0..cells {
+        for y in 0..cells {
+            for z in 0..cells {
+                let low = Vector3::new(x, y, z);
+                let low_solid = grid.is_solid(x, y, z);
+
+                for axis in 0..3 {
+                    let mut high = low;
+                    high[axis] += 1;
+
+                    if grid.is_solid(high.x, high.y, high.z) == low_solid {
+                        continue;
+                    }
+
+                    // gets the four cells around the edge, anticlockwise looking
+                    // down the axis
+                    let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+
+                    let quad = [(1, 1), (0, 1), (0, 0), (1, 0)].map(|(du, dv)| {
+                        let mut cell = low;
+                        cell[u] -= du;
+                        cell[v] -= dv;
+
+                        *cell_vertices.entry([cell.x, cell.y, cell.z]).or_insert_with(|| {
+                            let (position, normal) = place_vertex(cell);
+
+                            mesh.push_vertex(position, normal)
+                        })
+                    });
+
+                    // faces the quad towards the air
+                    if low_solid {
+                        mesh.indices.extend_from_slice(&[quad[0], quad[1], quad[2], quad[0], quad[2], quad[3]]);
+                    }
+                    else {
+                        mesh.indices.extend_from_slice(&[quad[0], quad[2], quad[1], quad[0], quad[3], quad[2]]);
+                    }
+                }
+            }
+        }
+    }
+
+    mesh
+}
+
+// gets the points the surface crosses each edge of a cell, along with the edge
+pub fn cell_crossing_edges(grid: &SampledGrid, cell: Vector3<i32>) -> Vec<(Vector3<i32>, usize, Vector3<f32>)> {
+    let mut crossings = Vec::new();
+
+    for axis in 0..3 {
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+
+        for (du, dv) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            let mut low = cell;
+            low[u] += du;
+            low[v] += dv;
+
+            let mut high = low;
+            high[axis] += 1;
+
+            if grid.is_solid(low.x, low.y, low.z) != grid.is_solid(high.x, high.y, high.z) {
+                crossings.push((low, axis, grid.edge_crossing(low, axis).1));
+            }
+        }
+    }
+
+    crossings
+}
+
+fn cell_crossings(grid: &SampledGrid, cell: Vector3<i32>) -> Vec<Vector3<f32>> {
+    cell_crossing_edges(grid, cell).into_iter().map(|(_, _, point)| point).collect()
+}
+
+// gets the normal of a cell from the differences across its corners, pointing
+// away from the solid side
+pub fn cell_normal(grid: &SampledGrid, cell: Vector3<i32>) -> Vector3<f32> {
+    let mut gradient = Vector3::new(0.0, 0.0, 0.0);
+
+    for corner in CELL_CORNERS.iter() {
+        let weight = grid.weight(cell.x + corner[0], cell.y + corner[1], cell.z + corner[2]);
+
+        for axis in 0..3 {
+            gradient[axis] += if corner[axis] == 1 { weight } else { -weight };
+        }
+    }
+
+    if gradient.magnitude2() > 0.0 {
+        -gradient.normalize()
+    }
+    else {
+        Vector3::unit_y()
+    }
+}
+
+//----------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::entities::{
+        density_field::DensityField,
+        dual_contouring::DualContouringMesher
+    };
+
+    const CHUNK_BOUNDS: usize = 12;
+
+    // solid inside a sphere, falling off with the distance from its surface
+    struct SphereDensity {
+        centre: Vector3<f64>,
+        radius: f64
+    }
+
+    impl DensityField for SphereDensity {
+        fn sample(&self, world_pos: Vector3<f64>) -> f32 {
+            (self.radius - (world_pos - self.centre).magnitude()) as f32
+        }
+    }
+
+    // meshes the chunks with their offsets, welding their vertices by
+    // position, and checks every edge is walked once each way so the surface
+    // is closed with nothing left open between the chunks
+    fn assert_closed(mesher: &dyn Mesher, density: &SphereDensity, offsets: &[Vector3<i32>]) {
+        let mut vertex_lookup = HashMap::<[u32; 3], u32>::new();
+        let mut edges = HashMap::<(u32, u32), usize>::new();
+
+        for offset in offsets.iter() {
+            let grid = SampledGrid::sample(*offset, CHUNK_BOUNDS, 1.0, 0.0, mesher.padding(), density);
+            let mesh = mesher.mesh(&grid);
+
+            assert!(!mesh.indices.is_empty(), "chunk {:?} should have part of the sphere", offset);
+
+            let welded: Vec<u32> = (0..mesh.vertex_count() as u32).map(|index| {
+                let position = mesh.position(index);
+                let next_index = vertex_lookup.len() as u32;
+
+                *vertex_lookup.entry([position.x.to_bits(), position.y.to_bits(), position.z.to_bits()]).or_insert(next_index)
+            }).collect();
+
+            for triangle in mesh.indices.chunks(3) {
+                let corners = [welded[triangle[0] as usize], welded[triangle[1] as usize], welded[triangle[2] as usize]];
+
+                assert!(corners[0] != corners[1] && corners[1] != corners[2] && corners[2] != corners[0]);
+
+                for corner in 0..3 {
+                    *edges.entry((corners[corner], corners[(corner + 1) % 3])).or_insert(0) += 1;
+                }
+            }
+        }
+
+        for (&(a, b), &count) in edges.iter() {
+            assert_eq!(count, 1, "edge {} {} is walked the same way by {} triangles", a, b, count);
+            assert!(edges.contains_key(&(b, a)), "edge {} {} only has one triangle", a, b);
+        }
+    }
+
+    #[test]
+    fn a_sphere_inside_a_chunk_is_closed() {
+        let density = SphereDensity { centre: Vector3::new(5.3, 5.6, 5.45), radius: 3.2 };
+
+        assert_closed(&SurfaceNetsMesher::new(), &density, &[Vector3::new(0, 0, 0)]);
+        assert_closed(&DualContouringMesher::new(), &density, &[Vector3::new(0, 0, 0)]);
+    }
+
+    #[test]
+    fn a_sphere_across_chunk_borders_has_no_cracks() {
+        // centred near the corner four chunks share, so the quads bridging
+        // each border are only built by one side
+        let cells = CHUNK_BOUNDS as f64 - 1.0;
+        let density = SphereDensity { centre: Vector3::new(cells + 0.3, cells - 0.4, 5.45), radius: 3.2 };
+
+        let offsets = [
+            Vector3::new(0, 0, 0),
+            Vector3::new(1, 0, 0),
+            Vector3::new(0, 1, 0),
+            Vector3::new(1, 1, 0)
+        ];
+
+        assert_closed(&SurfaceNetsMesher::new(), &density, &offsets);
+        assert_closed(&DualContouringMesher::new(), &density, &offsets);
+    }
+}
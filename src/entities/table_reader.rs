@@ -1,16 +1,63 @@
 use cgmath::*;
 
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufReader, BufRead};
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
 //------------------------
 
+// the tables are built into the binary so it can run from any directory
+const BUILT_IN_TABLE: &str = include_str!("../../assets/triangulation/triangulation_table.txt");
+const BUILT_IN_EXTENDED_TABLE: &str = include_str!("../../assets/triangulation/mc33_table.txt");
+
+// the corners of a cube in the order of the bits of a cube configuration
+pub const CUBE_CORNERS: [[i32; 3]; 8] = [
+    [0, 0, 0], [1, 0, 0], [1, 0, 1], [0, 0, 1],
+    [0, 1, 0], [1, 1, 0], [1, 1, 1], [0, 1, 1]
+];
+
+// the corners at each end of each edge of a cube
+pub const CUBE_EDGES: [[usize; 2]; 12] = [
+    [0, 1], [1, 2], [2, 3], [3, 0],
+    [4, 5], [5, 6], [6, 7], [7, 4],
+    [0, 4], [1, 5], [2, 6], [3, 7]
+];
+
+// the vertex some marching cubes 33 cases add inside the cube, numbered after
+// the edges
+pub const CUBE_CENTRE: u16 = 12;
+
+// the corners around each face of a cube, in the order of the occlusion face
+// directions, which is also the order of the face bits in the extended table
+pub const CUBE_FACES: [[usize; 4]; 6] = [
+    [0, 3, 7, 4], [1, 2, 6, 5],
+    [0, 1, 2, 3], [4, 5, 6, 7],
+    [0, 1, 5, 4], [3, 2, 6, 7]
+];
+
+// the triangles for a cube once its ambiguous faces have been decided, along
+// with the triangles to use instead if the inside of the cube joins up two
+// separate corners of the same side
+pub struct ExtendedCase {
+    pub triangles: Vec<u16>,
+    pub tunnels: Vec<TunnelCase>
+}
+
+// a tube through the cube joining two groups of corners, given as corner bits
+pub struct TunnelCase {
+    pub corners_a: u8,
+    pub corners_b: u8,
+    pub triangles: Vec<u16>
+}
+
 // hashmaps are fine for implementation since they scale well w/ O(1) gets
 pub struct TriangulationTable {
     pub table: HashMap<u16, Vec<u16>>,
 
+    // the marching cubes 33 cases, keyed by the cube configuration and a bit
+    // for each ambiguous face that's set when the face joins its rock corners.
+    // empty unless they've been loaded
+    pub extended_table: HashMap<(u8, u8), ExtendedCase>,
+
     pub corner_index_a_from_edge: Vec<Vector3<f32>>,
     pub corner_index_b_from_edge: Vec<Vector3<f32>>
 }
@@ -18,41 +65,39 @@ pub struct TriangulationTable {
 //------------------------
 
 impl TriangulationTable {
+    // creates the table from the cases built into the binary, which are checked
+    // when they're parsed like any other table so can't fail
     pub fn new() -> TriangulationTable {
-        // creates a new table for the triangulation table
-        let mut table = HashMap::new();
+        TriangulationTable::from_text(BUILT_IN_TABLE).unwrap()
+    }
 
-        // loads up the triangulation table from the files
-        let triangulation_file = File::open("assets/triangulation/triangulation_table.txt");
+    // reads the cases from a file instead, one line per configuration
+    pub fn from_file(path: &Path) -> Result<TriangulationTable, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|error| format!("couldn't read triangulation table '{}': {}", path.display(), error))?;
 
-        // creates an iterator over the file
-        let line_iterator = BufReader::new(triangulation_file.unwrap()).lines();
+        TriangulationTable::from_text(&text)
+            .map_err(|error| format!("invalid triangulation table '{}': {}", path.display(), error))
+    }
 
-        // sets an initial line counter
-        let mut line_counter = 0;
+    // parses the cases, each line being the comma separated edges of a
+    // configuration's triangles or N for none, then validates them
+    pub fn from_text(text: &str) -> Result<TriangulationTable, String> {
+        // creates a new table for the triangulation table
+        let mut table = HashMap::new();
 
-        // loops over the lines to add to the hashmap
-        for line in line_iterator {
-            // unwraps the line
-            let line_result = line.unwrap();
+        let lines: Vec<&str> = text.lines().map(|line| line.trim()).collect();
+        let case_count = lines.iter().rposition(|line| !line.is_empty()).map_or(0, |last| last + 1);
 
-            if line_result != "N" {
-                // creates a vector across the line
-                let str_vector: Vec<&str> = line_result.split(",").collect();
+        if case_count != 256 {
+            return Err(format!("expected 256 cases, found {}", case_count));
+        }
 
-                // converts the num vector
-                let num_vector: Vec<u16> = str_vector.into_iter().map(|e| e.parse::<u16>().unwrap()).collect();
+        // loops over the lines to add to the hashmap, counting each line
+        for (line_counter, line) in (0_u16..).zip(lines[..case_count].iter()) {
+            let edges = parse_edges(line).map_err(|error| format!("case {}: {}", line_counter, error))?;
 
-                // inserts to the table
-                table.insert(line_counter, num_vector);
-            }
-            else {
-                // fills with an empty entry
-                table.insert(line_counter, Vec::new());
-            }
-
-            // adds to the line counter
-            line_counter += 1;
+            table.insert(line_counter, edges);
         }
 
         // initialises corner_index_a_from_edge and the other corner index table
@@ -86,10 +131,356 @@ impl TriangulationTable {
             Vector3::new(0.0, 1.0, 1.0)
         ];
 
-        TriangulationTable {
+        let tri_table = TriangulationTable {
             table,
+            extended_table: HashMap::new(),
             corner_index_a_from_edge,
             corner_index_b_from_edge
+        };
+
+        tri_table.validate()?;
+
+        Ok(tri_table)
+    }
+
+    // checks every case can be meshed. each case needs whole triangles of real
+    // edges, and has to use exactly the edges its corners cross, which also
+    // means complementary cases share their edges. complementary cases without
+    // ambiguous faces have to be the same triangles facing the other way, the
+    // ambiguous ones can split their faces differently. the marching cubes 33
+    // cases are checked too if they've been loaded
+    pub fn validate(&self) -> Result<(), String> {
+        for cube_config in 0..=255_u8 {
+            let edges = self.table.get(&(cube_config as u16))
+                .ok_or(format!("case {} is missing", cube_config))?;
+
+            check_triangles(cube_config, edges, false)
+                .map_err(|error| format!("case {} {}", cube_config, error))?;
+
+            let complement = !cube_config;
+
+            if ambiguous_faces(cube_config) == 0 {
+                let flipped: Vec<u16> = self.table[&(complement as u16)]
+                    .chunks(3)
+                    .flat_map(|triangle| vec![triangle[0], triangle[2], triangle[1]])
+                    .collect();
+
+                if sorted_triangles(edges) != sorted_triangles(&flipped) {
+                    return Err(format!("cases {} and {} don't mirror each other", cube_config, complement));
+                }
+            }
+        }
+
+        if self.has_extended() {
+            validate_extended(&self.extended_table)?;
+        }
+
+        Ok(())
+    }
+
+    // loads the marching cubes 33 cases built into the binary
+    pub fn load_extended(&mut self) {
+        self.load_extended_text(BUILT_IN_EXTENDED_TABLE).unwrap();
+    }
+
+    pub fn load_extended_file(&mut self, path: &Path) -> Result<(), String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|error| format!("couldn't read extended table '{}': {}", path.display(), error))?;
+
+        self.load_extended_text(&text)
+            .map_err(|error| format!("invalid extended table '{}': {}", path.display(), error))
+    }
+
+    // reads the marching cubes 33 cases. each line holds the configuration,
+    // the face bits and the triangles, then any tunnels as the two corner
+    // groups they join followed by their triangles, all separated by spaces.
+    // the table is only kept if it has every case and they all check out
+    pub fn load_extended_text(&mut self, text: &str) -> Result<(), String> {
+        let mut extended_table = HashMap::new();
+
+        for (line_counter, line) in text.lines().map(|line| line.trim()).enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+
+            let error = |message: String| format!("line {}: {}", line_counter + 1, message);
+            let fields: Vec<&str> = line.split(' ').collect();
+
+            if fields.len() < 3 || !(fields.len() - 3).is_multiple_of(3) {
+                return Err(error(format!("expected 3 fields and 3 more per tunnel, found {}", fields.len())));
+            }
+
+            let parse_byte = |field: &str| field.parse::<u8>().map_err(|_| error(format!("invalid number '{}'", field)));
+
+            let tunnels = fields[3..].chunks(3).map(|tunnel| Ok(TunnelCase {
+                corners_a: parse_byte(tunnel[0])?,
+                corners_b: parse_byte(tunnel[1])?,
+                triangles: parse_edges(tunnel[2]).map_err(error)?
+            })).collect::<Result<Vec<TunnelCase>, String>>()?;
+
+            let key = (parse_byte(fields[0])?, parse_byte(fields[1])?);
+
+            let case = ExtendedCase {
+                triangles: parse_edges(fields[2]).map_err(error)?,
+                tunnels
+            };
+
+            if extended_table.insert(key, case).is_some() {
+                return Err(error(format!("case {} with face bits {} is listed more than once", key.0, key.1)));
+            }
+        }
+
+        validate_extended(&extended_table)?;
+
+        self.extended_table = extended_table;
+
+        Ok(())
+    }
+
+    pub fn has_extended(&self) -> bool {
+        !self.extended_table.is_empty()
+    }
+}
+
+//------------------------
+
+// reads a comma separated list of edges, where N is no edges
+fn parse_edges(value: &str) -> Result<Vec<u16>, String> {
+    if value == "N" {
+        return Ok(Vec::new());
+    }
+
+    value.split(',')
+        .map(|e| e.trim().parse::<u16>().map_err(|_| format!("invalid edge '{}'", e)))
+        .collect()
+}
+
+// checks the marching cubes 33 cases the same way as the basic ones, and that
+// there's a case for every way the ambiguous faces of each configuration can
+// be decided. tunnels have to join two groups of corners on the same side
+fn validate_extended(extended_table: &HashMap<(u8, u8), ExtendedCase>) -> Result<(), String> {
+    let mut case_count = 0;
+
+    for cube_config in 0..=255_u8 {
+        let ambiguous = ambiguous_faces(cube_config);
+
+        for face_bits in (0..64_u8).filter(|face_bits| face_bits & !ambiguous == 0) {
+            let case = extended_table.get(&(cube_config, face_bits))
+                .ok_or(format!("case {} with face bits {} is missing", cube_config, face_bits))?;
+
+            case_count += 1;
+
+            check_triangles(cube_config, &case.triangles, true)
+                .map_err(|error| format!("case {} with face bits {} {}", cube_config, face_bits, error))?;
+
+            for (tunnel_counter, tunnel) in case.tunnels.iter().enumerate() {
+                let error = |message: String| {
+                    format!("case {} with face bits {} tunnel {} {}", cube_config, face_bits, tunnel_counter + 1, message)
+                };
+
+                let corners = tunnel.corners_a | tunnel.corners_b;
+                let same_side = corners & cube_config == corners || corners & !cube_config == corners;
+
+                if tunnel.corners_a == 0 || tunnel.corners_b == 0 || tunnel.corners_a & tunnel.corners_b != 0 || !same_side {
+                    return Err(error(String::from("has to join two separate groups of corners on the same side")));
+                }
+
+                check_triangles(cube_config, &tunnel.triangles, true).map_err(error)?;
+            }
         }
     }
-}
\ No newline at end of file
+
+    // anything left over is keyed by faces that aren't ambiguous
+    if extended_table.len() != case_count {
+        let (cube_config, face_bits) = extended_table.keys()
+            .filter(|(cube_config, face_bits)| face_bits & !ambiguous_faces(*cube_config) != 0)
+            .min()
+            .unwrap();
+
+        return Err(format!("case {} has face bits {}, which aren't all ambiguous faces", cube_config, face_bits));
+    }
+
+    Ok(())
+}
+
+// checks a case is whole triangles that use exactly the edges its corners
+// cross, along with the centre of the cube if it's allowed
+fn check_triangles(cube_config: u8, edges: &[u16], allow_centre: bool) -> Result<(), String> {
+    if !edges.len().is_multiple_of(3) {
+        return Err(format!("has {} edges, which isn't a multiple of three", edges.len()));
+    }
+
+    let past_the_cube = |edge: u16| edge >= 12 && !(allow_centre && edge == CUBE_CENTRE);
+
+    if let Some(edge) = edges.iter().find(|&&edge| past_the_cube(edge)) {
+        return Err(format!("uses edge {}, but a cube only has 12", edge));
+    }
+
+    let used: HashSet<u16> = edges.iter().copied().filter(|&edge| edge != CUBE_CENTRE).collect();
+    let crossed: HashSet<u16> = (0..12_u16).filter(|&edge| {
+        let [a, b] = CUBE_EDGES[edge as usize];
+        (cube_config >> a) & 1 != (cube_config >> b) & 1
+    }).collect();
+
+    if used != crossed {
+        return Err(String::from("doesn't use exactly the edges its corners cross"));
+    }
+
+    Ok(())
+}
+
+// gets a bit for each face with its rock corners on one diagonal and air on
+// the other, in the order of the faces
+fn ambiguous_faces(cube_config: u8) -> u8 {
+    let mut face_bits = 0;
+
+    for (face, corners) in CUBE_FACES.iter().enumerate() {
+        let solid = corners.map(|corner| (cube_config >> corner) & 1 == 1);
+
+        if solid[0] == solid[2] && solid[1] == solid[3] && solid[0] != solid[1] {
+            face_bits |= 1 << face;
+        }
+    }
+
+    face_bits
+}
+
+// gets the triangles of a case in an order that doesn't depend on where each
+// triangle starts or the order they're listed in
+fn sorted_triangles(edges: &[u16]) -> Vec<[u16; 3]> {
+    let mut triangles: Vec<[u16; 3]> = edges.chunks(3).map(|triangle| {
+        let first = (0..3).min_by_key(|&corner| triangle[corner]).unwrap();
+
+        [triangle[first], triangle[(first + 1) % 3], triangle[(first + 2) % 3]]
+    }).collect();
+
+    triangles.sort_unstable();
+
+    triangles
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the built in table with one case's line swapped out
+    fn with_case(cube_config: usize, edges: &str) -> String {
+        BUILT_IN_TABLE.lines()
+            .enumerate()
+            .map(|(line_counter, line)| if line_counter == cube_config { edges } else { line })
+            .collect::<Vec<&str>>()
+            .join("\n")
+    }
+
+    // the built in marching cubes 33 cases with the line for one case swapped
+    // out, or taken out if there's nothing to swap in
+    fn with_extended_case(cube_config: u8, face_bits: u8, line: Option<&str>) -> String {
+        let key = format!("{} {} ", cube_config, face_bits);
+
+        BUILT_IN_EXTENDED_TABLE.lines()
+            .filter_map(|old_line| if old_line.starts_with(&key) { line } else { Some(old_line) })
+            .collect::<Vec<&str>>()
+            .join("\n")
+    }
+
+    // loads marching cubes 33 cases into the built in table, giving back the
+    // error if there is one
+    fn load_extended_error(text: &str) -> String {
+        let mut tri_table = TriangulationTable::new();
+        let error = tri_table.load_extended_text(text).err().unwrap();
+
+        assert!(!tri_table.has_extended());
+
+        error
+    }
+
+    #[test]
+    fn the_built_in_table_is_valid() {
+        assert!(TriangulationTable::from_text(BUILT_IN_TABLE).is_ok());
+        assert!(TriangulationTable::new().validate().is_ok());
+    }
+
+    #[test]
+    fn partial_triangles_are_rejected() {
+        let error = TriangulationTable::from_text(&with_case(1, "0,8,3,0")).err().unwrap();
+
+        assert!(error.contains("multiple of three"), "unexpected error: {}", error);
+    }
+
+    #[test]
+    fn edges_past_the_cube_are_rejected() {
+        let error = TriangulationTable::from_text(&with_case(1, "0,8,12")).err().unwrap();
+
+        assert!(error.contains("edge 12"), "unexpected error: {}", error);
+    }
+
+    #[test]
+    fn cases_that_dont_mirror_their_complement_are_rejected() {
+        // the same edges as case 1, but facing the wrong way
+        let error = TriangulationTable::from_text(&with_case(1, "0,3,8")).err().unwrap();
+
+        assert!(error.contains("mirror"), "unexpected error: {}", error);
+    }
+
+    #[test]
+    fn cases_missing_a_crossed_edge_are_rejected() {
+        let error = TriangulationTable::from_text(&with_case(3, "1,8,3,1,2,8")).err().unwrap();
+
+        assert!(error.contains("exactly the edges"), "unexpected error: {}", error);
+    }
+
+    #[test]
+    fn the_built_in_extended_table_is_valid() {
+        let mut tri_table = TriangulationTable::new();
+
+        assert!(tri_table.load_extended_text(BUILT_IN_EXTENDED_TABLE).is_ok());
+        assert!(tri_table.validate().is_ok());
+    }
+
+    #[test]
+    fn extended_tables_missing_a_case_are_rejected() {
+        let error = load_extended_error(&with_extended_case(5, 4, None));
+
+        assert!(error.contains("case 5 with face bits 4 is missing"), "unexpected error: {}", error);
+
+        // cases taken out after loading are caught when the table is validated
+        let mut tri_table = TriangulationTable::new();
+        tri_table.load_extended();
+        tri_table.extended_table.remove(&(5, 4));
+
+        assert!(tri_table.validate().is_err());
+    }
+
+    #[test]
+    fn extended_edges_past_the_centre_are_rejected() {
+        let error = load_extended_error(&with_extended_case(1, 0, Some("1 0 0,8,13")));
+
+        assert!(error.contains("edge 13"), "unexpected error: {}", error);
+    }
+
+    #[test]
+    fn extended_cases_for_faces_that_arent_ambiguous_are_rejected() {
+        let text = format!("{}\n1 1 0,8,3", BUILT_IN_EXTENDED_TABLE);
+        let error = load_extended_error(&text);
+
+        assert!(error.contains("aren't all ambiguous"), "unexpected error: {}", error);
+    }
+
+    #[test]
+    fn extended_cases_listed_twice_are_rejected() {
+        let text = format!("{}\n1 0 0,8,3", BUILT_IN_EXTENDED_TABLE);
+        let error = load_extended_error(&text);
+
+        assert!(error.contains("more than once"), "unexpected error: {}", error);
+    }
+
+    #[test]
+    fn tunnels_between_different_sides_are_rejected() {
+        // case 5 with its faces split, where corners 0 and 2 are rock and 1 is air
+        let line = "5 0 0,8,3,1,2,10 1 2 0,8,3,1,2,10";
+        let error = load_extended_error(&with_extended_case(5, 0, Some(line)));
+
+        assert!(error.contains("tunnel 1"), "unexpected error: {}", error);
+    }
+}
@@ -0,0 +1,800 @@
+// transition cells, joining the surface of a chunk to the surface of the finer
+// chunks across its faces the way transvoxel does. the chunk's surface is
+// pulled back from the face by part of a cell, and each square of the face gets
+// a cell filling the gap, with the finer level's samples on the face side and
+// the chunk's own samples on the side the surface was pulled back to
+use std::cell::Cell;
+use std::collections::HashMap;
+
+use cgmath::*;
+
+use crate::{
+    entities::{
+        basic_marching_cubes::ChunkSamples,
+        chunk_lod::ChunkKey,
+        compact_grid,
+        density_field::DensityField,
+        mesher::{self, Mask, Mesh, Mesher},
+        table_reader::CUBE_CORNERS
+    },
+    utils::{
+        chunk_space,
+        world_config::WorldConfig
+    }
+};
+
+//----------------------
+
+// how far the chunk's surface is pulled back from a face with transition
+// cells, as a fraction of a cell
+const TRANSITION_CELL_WIDTH: f32 = 0.5;
+
+// the transition cells along one of a chunk's faces
+#[derive(Clone, Debug, Default)]
+pub struct TransitionStrip {
+    pub mesh: Mesh,
+
+    // the vertices on the face itself come first. they line up with the finer
+    // chunks' surface, so they stay where they are when the rest are pulled back
+    pub face_vertex_count: usize
+}
+
+// pulls a chunk's vertices back from the faces in a transition mask
+pub struct TransitionSpace {
+    low: Vector3<f32>,
+    high: Vector3<f32>,
+    cell_size: f32,
+    transition_mask: u8
+}
+
+// the samples of the finer level along one face of a chunk, reaching a sample
+// past the face on either side and past its edges, for the gradients and for
+// the cubes of the finer chunks that touch the face. only the face itself is
+// sampled up front, the rest is sampled the first time it's needed
+struct FaceSlab<'a> {
+    density: &'a dyn DensityField,
+    weights: Vec<Cell<Option<f32>>>,
+    face: usize,
+    width: i32,
+    face_depth: i32,
+
+    // the global sample of the chunk's first sample at the finer level
+    origin: Vector3<i32>,
+    spacing_per_vertex: f32,
+    isovalue: f32
+}
+
+// a corner of one of the polygons around a transition cell, as a sample local
+// to the chunk at the finer level or at the chunk's own
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum CellCorner {
+    Fine(Vector3<i32>),
+    Coarse(Vector3<i32>)
+}
+
+// a vertex of a transition cell, on the line between two corners that's
+// always walked from the lower one
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct CellVertex {
+    low: CellCorner,
+    high: CellCorner
+}
+
+// one side of a transition cell, with its corners anticlockwise seen from
+// outside the cell. squares give the cube they belong to and which of its
+// faces they are, to split them the same way as the cube does
+struct CellPolygon {
+    corners: Vec<CellCorner>,
+    cube: Option<(CellCorner, usize)>
+}
+
+// where a vertex of a transition cell is kept, in the vertices on the face or
+// the ones inside the chunk
+#[derive(Clone, Copy, Debug)]
+enum VertexSlot {
+    Face(u32),
+    Inner(u32)
+}
+
+// gathers the vertices and triangles of the transition cells along a face
+struct StripBuilder<'a> {
+    slab: &'a FaceSlab<'a>,
+    samples: &'a ChunkSamples,
+    mesher: &'a dyn Mesher,
+
+    face: usize,
+    cell_size: f32,
+
+    // the positions and normals of the vertices in the order they were first
+    // used, on the face and inside the chunk, with where each one is kept
+    face_vertices: Vec<(Vector3<f32>, Vector3<f32>)>,
+    inner_vertices: Vec<(Vector3<f32>, Vector3<f32>)>,
+    vertex_slots: HashMap<CellVertex, VertexSlot>,
+
+    triangles: Vec<[VertexSlot; 3]>
+}
+
+//----------------------
+
+impl TransitionStrip {
+    // builds the transition cells for each face of a chunk at a coarser level
+    // of detail, in the order of the occlusion face directions. the finer
+    // level is sampled along the faces from the density field
+    pub fn build_all(
+        key: ChunkKey,
+        world_config: &WorldConfig,
+        samples: &ChunkSamples,
+        density: &dyn DensityField,
+        mesher: &dyn Mesher
+    ) -> Vec<TransitionStrip> {
+        assert!(key.lod > 0, "full detail chunks have no finer level to join to");
+
+        (0..6).map(|face| {
+            // faces with every finer sample on one side have no surface to join
+            let slab = match FaceSlab::sample(key, face, world_config, density) {
+                Some(slab) => slab,
+                None => return TransitionStrip::default()
+            };
+
+            let mut builder = StripBuilder {
+                slab: &slab,
+                samples,
+                mesher,
+
+                face,
+                cell_size: key.spacing(world_config.spacing_per_vertex),
+
+                face_vertices: Vec::new(),
+                inner_vertices: Vec::new(),
+                vertex_slots: HashMap::new(),
+
+                triangles: Vec::new()
+            };
+
+            let cells = chunk_space::cells_per_chunk(world_config.chunk_bounds);
+            let polygons = builder.cell_polygons();
+
+            for i in 0..cells {
+                for j in 0..cells {
+                    builder.add_cell(&polygons, i, j);
+                }
+            }
+
+            builder.finish()
+        }).collect()
+    }
+
+    // gets roughly how many bytes the strip takes up
+    pub fn memory_size(&self) -> usize {
+        std::mem::size_of_val(self.mesh.vertices.as_slice()) +
+            std::mem::size_of_val(self.mesh.normals.as_slice()) +
+            std::mem::size_of_val(self.mesh.indices.as_slice())
+    }
+}
+
+impl TransitionSpace {
+    pub fn new(offset: Vector3<i32>, chunk_bounds: usize, spacing_per_vertex: f32, transition_mask: u8) -> TransitionSpace {
+        let origin = chunk_space::chunk_origin_sample(offset, chunk_bounds);
+        let cells = chunk_space::cells_per_chunk(chunk_bounds);
+
+        TransitionSpace {
+            low: chunk_space::global_sample_to_world(origin, spacing_per_vertex),
+            high: chunk_space::global_sample_to_world(origin + Vector3::new(cells, cells, cells), spacing_per_vertex),
+            cell_size: spacing_per_vertex,
+            transition_mask
+        }
+    }
+
+    // gets where a face of the chunk lies along its axis
+    fn plane(&self, face: usize) -> f32 {
+        if face.is_multiple_of(2) { self.low[face / 2] } else { self.high[face / 2] }
+    }
+
+    // pulls a vertex in the cells along the faces in the mask back from the
+    // face to make room for the transition cells. vertices on the chunk's
+    // other faces stay put, since the neighbours across them have them there
+    pub fn pull_back(&self, position: Vector3<f32>) -> Vector3<f32> {
+        for face in 0..6 {
+            if self.transition_mask & (1 << face) == 0 && position[face / 2] == self.plane(face) {
+                return position;
+            }
+        }
+
+        let mut pulled = position;
+
+        for face in 0..6 {
+            if self.transition_mask & (1 << face) == 0 {
+                continue;
+            }
+
+            let axis = face / 2;
+            let depth = (position[axis] - self.plane(face)).abs();
+
+            if depth < self.cell_size {
+                let inward = if face.is_multiple_of(2) { 1.0 } else { -1.0 };
+                let pulled_depth = TRANSITION_CELL_WIDTH * self.cell_size + depth * (1.0 - TRANSITION_CELL_WIDTH);
+
+                pulled[axis] = self.plane(face) + inward * pulled_depth;
+            }
+        }
+
+        pulled
+    }
+}
+
+impl<'a> FaceSlab<'a> {
+    // samples the level below a chunk along one of its faces, or gives back
+    // None if every sample on the face is on the same side of the isovalue
+    fn sample(key: ChunkKey, face: usize, world_config: &WorldConfig, density: &'a dyn DensityField) -> Option<FaceSlab<'a>> {
+        let cells = chunk_space::cells_per_chunk(world_config.chunk_bounds);
+        let fine_key = ChunkKey::new(key.lod - 1, key.offset * 2);
+        let width = cells * 2 + 3;
+
+        let slab = FaceSlab {
+            density,
+            weights: vec![Cell::new(None); (3 * width * width) as usize],
+            face,
+            width,
+            face_depth: if face.is_multiple_of(2) { 0 } else { cells * 2 },
+
+            origin: chunk_space::chunk_origin_sample(key.offset, world_config.chunk_bounds) * 2,
+            spacing_per_vertex: fine_key.spacing(world_config.spacing_per_vertex),
+            isovalue: world_config.isovalue
+        };
+
+        // checks the face itself first, since most faces don't have a surface
+        let first_solid = slab.weight(slab.local(0, 0, 0)) >= slab.isovalue;
+
+        let has_surface = (0..=(cells * 2)).any(|i| {
+            (0..=(cells * 2)).any(|j| (slab.weight(slab.local(0, i, j)) >= slab.isovalue) != first_solid)
+        });
+
+        if has_surface { Some(slab) } else { None }
+    }
+
+    // gets the local sample at a depth from the face and a position across it
+    fn local(&self, depth: i32, i: i32, j: i32) -> Vector3<i32> {
+        let axis = self.face / 2;
+
+        let mut local = Vector3::new(0, 0, 0);
+        local[axis] = self.face_depth + depth;
+        local[(axis + 1) % 3] = i;
+        local[(axis + 2) % 3] = j;
+
+        local
+    }
+
+    fn weight(&self, local: Vector3<i32>) -> f32 {
+        let axis = self.face / 2;
+
+        let depth = local[axis] - self.face_depth + 1;
+        let (i, j) = (local[(axis + 1) % 3] + 1, local[(axis + 2) % 3] + 1);
+
+        let weight = &self.weights[((depth * self.width + i) * self.width + j) as usize];
+
+        weight.get().unwrap_or_else(|| {
+            // rounded like the finer chunks' own samples, so the face lines up with them
+            let sampled = compact_grid::round_density(
+                self.density.sample(chunk_space::global_sample_to_density_pos(self.origin + local, self.spacing_per_vertex))
+            );
+            weight.set(Some(sampled));
+
+            sampled
+        })
+    }
+
+    // gets the gradient the same way a sampled grid does, so normals match
+    // the finer chunks'
+    fn gradient(&self, local: Vector3<i32>) -> Vector3<f32> {
+        let step = |axis: usize, direction: i32| {
+            let mut stepped = local;
+            stepped[axis] += direction;
+
+            self.weight(stepped)
+        };
+
+        Vector3::new(
+            step(0, 1) - step(0, -1),
+            step(1, 1) - step(1, -1),
+            step(2, 1) - step(2, -1)
+        )
+    }
+
+    // gets the point and normal where the surface crosses between two samples
+    fn crossing(&self, low: Vector3<i32>, high: Vector3<i32>) -> (Vector3<f32>, Vector3<f32>) {
+        let (interp_value, point) = mesher::crossing_point(
+            self.origin + low,
+            high - low,
+            (self.weight(low), self.weight(high)),
+            self.isovalue,
+            self.spacing_per_vertex
+        );
+
+        (point, mesher::interpolated_normal(self.gradient(low), self.gradient(high), interp_value))
+    }
+}
+
+impl<'a> StripBuilder<'a> {
+    // gets the polygons around the first cell of the face, each turned
+    // anticlockwise seen from outside the cell. the other cells' are the same
+    // moved along the face
+    fn cell_polygons(&self) -> Vec<CellPolygon> {
+        let axis = self.face / 2;
+        let low_face = self.face.is_multiple_of(2);
+
+        let square = [(0, 0), (1, 0), (1, 1), (0, 1)];
+        let diagonals = self.mesher.splits_faces_diagonally();
+
+        // the cubes the face squares belong to are outside the chunk for the
+        // finer level and inside it for the chunk's own
+        let (outside, inside) = if low_face { (-1, 0) } else { (0, -1) };
+
+        let fine = |du: i32, dv: i32, depth: i32| CellCorner::Fine(self.slab.local(depth, du, dv));
+        let coarse = |du: i32, dv: i32, depth: i32| {
+            let mut corner = Vector3::new(0, 0, 0);
+            corner[axis] = self.slab.face_depth / 2 + depth;
+            corner[(axis + 1) % 3] = du;
+            corner[(axis + 2) % 3] = dv;
+
+            CellCorner::Coarse(corner)
+        };
+
+        let mut polygons = Vec::new();
+
+        let mut add_square = |corners: [CellCorner; 4], cube: (CellCorner, usize)| {
+            if diagonals {
+                polygons.push(CellPolygon { corners: vec![corners[0], corners[1], corners[2]], cube: None });
+                polygons.push(CellPolygon { corners: vec![corners[0], corners[2], corners[3]], cube: None });
+            }
+            else {
+                polygons.push(CellPolygon { corners: corners.to_vec(), cube: Some(cube) });
+            }
+        };
+
+        // the face side is split into the four squares of the finer level
+        for &(su, sv) in square.iter() {
+            let corners = square.map(|(du, dv)| fine(su + du, sv + dv, 0));
+            add_square(corners, (fine(su, sv, outside), self.face ^ 1));
+        }
+
+        // the inner side is a single square of the chunk's own
+        let corners = square.map(|(du, dv)| coarse(du, dv, 0));
+        add_square(corners, (coarse(0, 0, inside), self.face));
+
+        // each side between them has three finer samples along the face and
+        // two of the chunk's own, which are at the same places as the finer
+        // samples at its ends
+        for side in 0..4 {
+            let (a, b) = (square[side], square[(side + 1) % 4]);
+
+            polygons.push(CellPolygon {
+                corners: vec![
+                    fine(a.0 * 2, a.1 * 2, 0),
+                    fine(a.0 + b.0, a.1 + b.1, 0),
+                    fine(b.0 * 2, b.1 * 2, 0),
+                    coarse(b.0, b.1, 0),
+                    coarse(a.0, a.1, 0)
+                ],
+                cube: None
+            });
+        }
+
+        for polygon in polygons.iter_mut() {
+            self.orient(polygon);
+        }
+
+        polygons
+    }
+
+    // adds the transition cell for a square of the face, from the polygons
+    // around the first cell
+    fn add_cell(&mut self, polygons: &[CellPolygon], i: i32, j: i32) {
+        let axis = self.face / 2;
+
+        let moved = |corner: &CellCorner| match *corner {
+            CellCorner::Fine(mut local) => {
+                local[(axis + 1) % 3] += i * 2;
+                local[(axis + 2) % 3] += j * 2;
+
+                CellCorner::Fine(local)
+            }
+            CellCorner::Coarse(mut local) => {
+                local[(axis + 1) % 3] += i;
+                local[(axis + 2) % 3] += j;
+
+                CellCorner::Coarse(local)
+            }
+        };
+
+        // the surface can only cross a cell with corners on both sides of it
+        let first_solid = self.is_solid(&moved(&polygons[0].corners[0]));
+
+        let crossed = polygons.iter().any(|polygon| {
+            polygon.corners.iter().any(|corner| self.is_solid(&moved(corner)) != first_solid)
+        });
+
+        if !crossed {
+            return;
+        }
+
+        let polygons: Vec<CellPolygon> = polygons.iter().map(|polygon| CellPolygon {
+            corners: polygon.corners.iter().map(moved).collect(),
+            cube: polygon.cube.map(|(low, face)| (moved(&low), face))
+        }).collect();
+
+        // the surface crosses the sides of the cell in segments, each with the
+        // rock on its left, which link up into outlines around the cell
+        let mut segments = Vec::new();
+
+        for polygon in polygons.iter() {
+            self.add_segments(polygon, &mut segments);
+        }
+
+        // a cell only has a few segments, so they're searched for each link
+        while let Some((start, mut current)) = segments.pop() {
+            let mut outline = vec![start];
+
+            while current != start {
+                outline.push(current);
+
+                current = match segments.iter().position(|&(from, _)| from == current) {
+                    Some(index) => segments.swap_remove(index).1,
+                    None => break
+                };
+            }
+
+            self.add_outline(&outline);
+        }
+    }
+
+    // turns a polygon of the first cell's corners anticlockwise seen from
+    // outside the cell, from where they'd be if the cell were a fine cell deep
+    fn orient(&self, polygon: &mut CellPolygon) {
+        let axis = self.face / 2;
+        let inward = if self.face.is_multiple_of(2) { 1.0 } else { -1.0 };
+
+        let position = |corner: &CellCorner| match corner {
+            CellCorner::Fine(local) => local.cast::<f32>().unwrap(),
+            CellCorner::Coarse(local) => {
+                let mut position = (local * 2).cast::<f32>().unwrap();
+                position[axis] += inward;
+
+                position
+            }
+        };
+
+        let mut centre = Vector3::new(1.0, 1.0, 1.0);
+        centre[axis] = self.slab.face_depth as f32 + inward * 0.5;
+
+        let points: Vec<Vector3<f32>> = polygon.corners.iter().map(position).collect();
+
+        let mut normal = Vector3::new(0.0, 0.0, 0.0);
+        let mut middle = Vector3::new(0.0, 0.0, 0.0);
+
+        for (index, point) in points.iter().enumerate() {
+            normal += point.cross(points[(index + 1) % points.len()]);
+            middle += *point;
+        }
+
+        middle /= points.len() as f32;
+
+        if normal.dot(middle - centre) < 0.0 {
+            polygon.corners.reverse();
+        }
+    }
+
+    // adds the segments the surface cuts across a polygon, going from where
+    // it leaves the rock to where it enters it
+    fn add_segments(&self, polygon: &CellPolygon, segments: &mut Vec<(CellVertex, CellVertex)>) {
+        let corners = &polygon.corners;
+
+        // the sides of the cells have at most five corners
+        let mut solid = [false; 5];
+
+        for (index, corner) in corners.iter().enumerate() {
+            solid[index] = self.is_solid(corner);
+        }
+
+        // gets each crossing in order around the polygon, and whether the walk
+        // leaves the rock there
+        let mut crossings = Vec::new();
+
+        for index in 0..corners.len() {
+            let next = (index + 1) % corners.len();
+
+            if solid[index] != solid[next] {
+                crossings.push((CellVertex::between(corners[index], corners[next]), solid[index]));
+            }
+        }
+
+        // only squares with rock on one diagonal are crossed four times, which
+        // can either cut off the rock corners or the air corners
+        let joined = match polygon.cube {
+            Some((CellCorner::Fine(low), face)) if crossings.len() == 4 => {
+                self.mesher.joins_rock_across_face(&self.cube_values(low, true), face)
+            }
+            Some((CellCorner::Coarse(low), face)) if crossings.len() == 4 => {
+                self.mesher.joins_rock_across_face(&self.cube_values(low, false), face)
+            }
+            _ => false
+        };
+
+        for (index, &(vertex, leaves_rock)) in crossings.iter().enumerate() {
+            if !leaves_rock {
+                continue;
+            }
+
+            let entry = if crossings.len() == 2 || joined {
+                (index + 1) % crossings.len()
+            }
+            else {
+                (index + crossings.len() - 1) % crossings.len()
+            };
+
+            segments.push((vertex, crossings[entry].0));
+        }
+    }
+
+    // gets the densities of a cube relative to the isovalue, in the order of
+    // the cube corners, from its lowest sample
+    fn cube_values(&self, low: Vector3<i32>, fine: bool) -> [f32; 8] {
+        CUBE_CORNERS.map(|corner| {
+            let local = low + Vector3::new(corner[0], corner[1], corner[2]);
+            let corner = if fine { CellCorner::Fine(local) } else { CellCorner::Coarse(local) };
+
+            self.weight(&corner) - self.slab.isovalue
+        })
+    }
+
+    fn weight(&self, corner: &CellCorner) -> f32 {
+        match (corner, self.samples) {
+            (CellCorner::Fine(local), _) => self.slab.weight(*local),
+            (CellCorner::Coarse(local), ChunkSamples::Mixed(grid)) => grid.weight(local.x, local.y, local.z),
+            _ => unreachable!("uniform chunks don't keep their densities")
+        }
+    }
+
+    fn is_solid(&self, corner: &CellCorner) -> bool {
+        match (corner, self.samples) {
+            (CellCorner::Coarse(_), ChunkSamples::Uniform { mask, .. }) => *mask == Mask::Plus,
+            _ => self.weight(corner) >= self.slab.isovalue
+        }
+    }
+
+    // splits an outline into triangles facing out of the rock, picking the
+    // split with the least area so the triangles follow the outline closely
+    fn add_outline(&mut self, outline: &[CellVertex]) {
+        let count = outline.len();
+
+        if count < 3 {
+            return;
+        }
+
+        let slots: Vec<VertexSlot> = outline.iter().map(|vertex| self.vertex(*vertex)).collect();
+
+        // the outlines go around the rock, so the triangles are wound the
+        // other way round to face the air
+        if count == 3 {
+            self.triangles.push([slots[0], slots[2], slots[1]]);
+            return;
+        }
+
+        let points: Vec<Vector3<f32>> = slots.iter().map(|slot| self.cell_position(*slot)).collect();
+        let area = |a: usize, b: usize, c: usize| (points[b] - points[a]).cross(points[c] - points[a]).magnitude();
+
+        // gets the least area for the outline between each pair of points,
+        // and the point that triangle is made with
+        let mut least_area = vec![0.0; count * count];
+        let mut best_middle = vec![0; count * count];
+
+        for span in 2..count {
+            for start in 0..(count - span) {
+                let end = start + span;
+
+                least_area[start * count + end] = f32::INFINITY;
+
+                for middle in (start + 1)..end {
+                    let total = least_area[start * count + middle] + least_area[middle * count + end] + area(start, middle, end);
+
+                    if total < least_area[start * count + end] {
+                        least_area[start * count + end] = total;
+                        best_middle[start * count + end] = middle;
+                    }
+                }
+            }
+        }
+
+        let mut spans = vec![(0, count - 1)];
+
+        while let Some((start, end)) = spans.pop() {
+            if end - start < 2 {
+                continue;
+            }
+
+            let middle = best_middle[start * count + end];
+
+            self.triangles.push([slots[start], slots[end], slots[middle]]);
+
+            spans.push((start, middle));
+            spans.push((middle, end));
+        }
+    }
+
+    // gets a vertex's position in the cell, with the chunk's own vertices
+    // pulled back from the face
+    fn cell_position(&self, slot: VertexSlot) -> Vector3<f32> {
+        match slot {
+            VertexSlot::Face(index) => self.face_vertices[index as usize].0,
+            VertexSlot::Inner(index) => {
+                let mut position = self.inner_vertices[index as usize].0;
+
+                let axis = self.face / 2;
+                let inward = if self.face.is_multiple_of(2) { 1.0 } else { -1.0 };
+
+                position[axis] += inward * TRANSITION_CELL_WIDTH * self.cell_size;
+
+                position
+            }
+        }
+    }
+
+    // gets where a vertex is kept, working out its position and normal from
+    // the finer samples or the chunk's own the first time it's used
+    fn vertex(&mut self, vertex: CellVertex) -> VertexSlot {
+        if let Some(slot) = self.vertex_slots.get(&vertex) {
+            return *slot;
+        }
+
+        let slot = match (vertex.low, vertex.high, self.samples) {
+            (CellCorner::Fine(low), CellCorner::Fine(high), _) => {
+                self.face_vertices.push(self.slab.crossing(low, high));
+
+                VertexSlot::Face(self.face_vertices.len() as u32 - 1)
+            }
+            (CellCorner::Coarse(low), CellCorner::Coarse(high), ChunkSamples::Mixed(grid)) => {
+                // the same calls the chunk's mesher makes, so the vertices
+                // land exactly on the ones in its mesh
+                let (interp_value, point) = grid.crossing_between(low, high);
+                self.inner_vertices.push((point, grid.normal_between(low, high, interp_value)));
+
+                VertexSlot::Inner(self.inner_vertices.len() as u32 - 1)
+            }
+            _ => unreachable!("the surface only crosses between samples at the same level in a mixed chunk")
+        };
+
+        self.vertex_slots.insert(vertex, slot);
+
+        slot
+    }
+
+    fn finish(self) -> TransitionStrip {
+        let mut mesh = Mesh::new();
+
+        for &(position, normal) in self.face_vertices.iter().chain(self.inner_vertices.iter()) {
+            mesh.push_vertex(position, normal);
+        }
+
+        let face_vertex_count = self.face_vertices.len();
+
+        for triangle in self.triangles.iter() {
+            mesh.indices.extend(triangle.iter().map(|slot| match *slot {
+                VertexSlot::Face(index) => index,
+                VertexSlot::Inner(index) => face_vertex_count as u32 + index
+            }));
+        }
+
+        TransitionStrip {
+            mesh,
+            face_vertex_count
+        }
+    }
+}
+
+impl CellVertex {
+    // gets the vertex between two corners of a polygon, which are always at
+    // the same level since the corners at each level line up
+    fn between(a: CellCorner, b: CellCorner) -> CellVertex {
+        let sum = |corner: CellCorner| match corner {
+            CellCorner::Fine(local) | CellCorner::Coarse(local) => local.x + local.y + local.z
+        };
+
+        if sum(a) < sum(b) {
+            CellVertex { low: a, high: b }
+        }
+        else {
+            CellVertex { low: b, high: a }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    use crate::entities::{
+        basic_marching_cubes::{MarchingCubesMesher, MCChunk},
+        chunk_lod,
+        density_field::NoiseDensity,
+        marching_tetrahedra::MarchingTetrahedraMesher,
+        table_reader::TriangulationTable
+    };
+
+    // builds every chunk picked around a camera, joined up with transition
+    // cells, and checks the surfaces meet without gaps well inside the view
+    fn check_levels_meet(mesher: &dyn Mesher) -> usize {
+        // perlin noise is zero on its lattice, so the frequency keeps samples
+        // off it where the surface would pass exactly through them
+        let world_config = WorldConfig::new();
+        let density = NoiseDensity::perlin(0.0713);
+
+        let selected = chunk_lod::select_chunks(Vector3::new(1, 1, 1), 2, 2);
+        let masks = chunk_lod::transition_masks(&selected);
+
+        // welds the meshes of every chunk together by position
+        let mut vertex_lookup = HashMap::<[u32; 3], u32>::new();
+        let mut positions = Vec::new();
+        let mut edges = HashMap::<(u32, u32), usize>::new();
+
+        for key in selected.iter() {
+            let chunk = MCChunk::new(*key, &world_config, &density, mesher);
+            let (vertices, _, indices) = chunk.mesh_with_transitions(masks[key]);
+
+            let welded: Vec<u32> = vertices.chunks(3).map(|vertex| {
+                *vertex_lookup.entry([vertex[0].to_bits(), vertex[1].to_bits(), vertex[2].to_bits()]).or_insert_with(|| {
+                    positions.push(Vector3::new(vertex[0], vertex[1], vertex[2]));
+                    positions.len() as u32 - 1
+                })
+            }).collect();
+
+            for triangle in indices.chunks(3) {
+                for corner in 0..3 {
+                    let (a, b) = (welded[triangle[corner] as usize], welded[triangle[(corner + 1) % 3] as usize]);
+                    *edges.entry((a, b)).or_insert(0) += 1;
+                }
+            }
+        }
+
+        // the view reaches at least a whole coarsest chunk past the camera's,
+        // so only edges inside that can be checked
+        let chunk_size = chunk_space::chunk_world_size(world_config.chunk_bounds, world_config.spacing_per_vertex);
+        let inside = |point: Vector3<f32>| (0..3).all(|axis| point[axis] > -3.5 * chunk_size && point[axis] < 7.5 * chunk_size);
+
+        let mut checked = HashSet::new();
+
+        for (&(a, b), &count) in edges.iter() {
+            if a == b || !inside(positions[a as usize]) || !inside(positions[b as usize]) {
+                continue;
+            }
+
+            assert_eq!(count, 1, "edge from {:?} to {:?} is walked the same way twice", positions[a as usize], positions[b as usize]);
+            assert!(edges.contains_key(&(b, a)), "edge from {:?} to {:?} is open", positions[a as usize], positions[b as usize]);
+
+            checked.insert((a.min(b), a.max(b)));
+        }
+
+        checked.len()
+    }
+
+    #[test]
+    fn marching_cubes_levels_meet_without_gaps() {
+        let mesher = MarchingCubesMesher::new(Arc::new(TriangulationTable::new()));
+
+        assert!(check_levels_meet(&mesher) > 0);
+    }
+
+    #[test]
+    fn marching_cubes_33_levels_meet_without_gaps() {
+        let mut tri_table = TriangulationTable::new();
+        tri_table.load_extended();
+
+        assert!(check_levels_meet(&MarchingCubesMesher::with_mc33(Arc::new(tri_table))) > 0);
+    }
+
+    #[test]
+    fn marching_tetrahedra_levels_meet_without_gaps() {
+        assert!(check_levels_meet(&MarchingTetrahedraMesher::new()) > 0);
+    }
+}
@@ -0,0 +1,390 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use cgmath::*;
+
+use crate::utils::{
+    chunk_space,
+    constants::*
+};
+
+use super::density_field::DensityField;
+
+//------------------------
+
+// a sample and the six next to it, which smoothing averages over
+const NEIGHBOUR_OFFSETS: [Vector3<i32>; 7] = [
+    Vector3::new(0, 0, 0),
+    Vector3::new(-1, 0, 0), Vector3::new(1, 0, 0),
+    Vector3::new(0, -1, 0), Vector3::new(0, 1, 0),
+    Vector3::new(0, 0, -1), Vector3::new(0, 0, 1)
+];
+
+// the shape of a brush, in world units
+#[derive(Clone, Copy, Debug)]
+pub enum BrushShape {
+    Sphere { centre: Vector3<f32>, radius: f32 },
+    Box { centre: Vector3<f32>, half_extents: Vector3<f32> },
+    Capsule { start: Vector3<f32>, end: Vector3<f32>, radius: f32 }
+}
+
+// what a brush does to the density inside it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BrushMode {
+    Add,
+    Subtract,
+    Smooth
+}
+
+// an edit to the density field. the strength blends between the density
+// that was there and the brush's, from 0 for no change to 1 for all brush
+#[derive(Clone, Copy, Debug)]
+pub struct Brush {
+    pub shape: BrushShape,
+    pub mode: BrushMode,
+    pub strength: f32
+}
+
+// a sample changed by an edit, with the edited value it held before if it
+// had one and the value the edit left there
+#[derive(Clone, Copy, Debug)]
+pub struct SampleChange {
+    pub sample: Vector3<i32>,
+    pub old_value: Option<f32>,
+    pub new_value: f32
+}
+
+// the samples one brush stroke changed, enough to take it back or do it again
+#[derive(Clone, Debug)]
+pub struct EditOperation {
+    pub changes: Vec<SampleChange>,
+    pub min_sample: Vector3<i32>,
+    pub max_sample: Vector3<i32>
+}
+
+// a density field with hand edits layered over it. edits are kept for each
+// full detail sample they change, coarser levels of detail sample every
+// second, fourth and so on full detail sample so they see the same edits
+pub struct EditableDensity {
+    base: Box<dyn DensityField>,
+    edits: RwLock<HashMap<Vector3<i32>, f32>>,
+
+    spacing_per_vertex: f32,
+    isovalue: f32
+}
+
+//------------------------
+
+impl BrushShape {
+    // gets the signed distance from a point to the surface of the shape,
+    // negative inside it
+    pub fn distance(&self, point: Vector3<f32>) -> f32 {
+        match *self {
+            BrushShape::Sphere { centre, radius } => (point - centre).magnitude() - radius,
+            BrushShape::Box { centre, half_extents } => {
+                let offset = point - centre;
+                let outside = Vector3::new(
+                    offset.x.abs() - half_extents.x,
+                    offset.y.abs() - half_extents.y,
+                    offset.z.abs() - half_extents.z
+                );
+
+                let clamped = Vector3::new(outside.x.max(0.0), outside.y.max(0.0), outside.z.max(0.0));
+
+                clamped.magnitude() + outside.x.max(outside.y).max(outside.z).min(0.0)
+            }
+            BrushShape::Capsule { start, end, radius } => {
+                let along = end - start;
+                let length2 = along.magnitude2();
+
+                // finds the closest point on the line between the ends
+                let t = if length2 > 0.0 {
+                    ((point - start).dot(along) / length2).clamp(0.0, 1.0)
+                }
+                else {
+                    0.0
+                };
+
+                (point - (start + along * t)).magnitude() - radius
+            }
+        }
+    }
+
+    // gets the corners of the box around the shape
+    pub fn bounds(&self) -> (Vector3<f32>, Vector3<f32>) {
+        match *self {
+            BrushShape::Sphere { centre, radius } => {
+                let extent = Vector3::new(radius, radius, radius);
+
+                (centre - extent, centre + extent)
+            }
+            BrushShape::Box { centre, half_extents } => (centre - half_extents, centre + half_extents),
+            BrushShape::Capsule { start, end, radius } => {
+                let extent = Vector3::new(radius, radius, radius);
+
+                (
+                    Vector3::new(start.x.min(end.x), start.y.min(end.y), start.z.min(end.z)) - extent,
+                    Vector3::new(start.x.max(end.x), start.y.max(end.y), start.z.max(end.z)) + extent
+                )
+            }
+        }
+    }
+}
+
+impl EditOperation {
+    // gets roughly how many bytes the operation takes up
+    pub fn memory_size(&self) -> usize {
+        std::mem::size_of::<EditOperation>() + std::mem::size_of_val(self.changes.as_slice())
+    }
+}
+
+impl Brush {
+    pub fn new(shape: BrushShape, mode: BrushMode) -> Brush {
+        Brush {
+            shape,
+            mode,
+            strength: 1.0
+        }
+    }
+}
+
+//------------------------
+
+impl EditableDensity {
+    pub fn new(base: Box<dyn DensityField>, spacing_per_vertex: f32, isovalue: f32) -> EditableDensity {
+        EditableDensity {
+            base,
+            edits: RwLock::new(HashMap::new()),
+
+            spacing_per_vertex,
+            isovalue
+        }
+    }
+
+    // gets the density at a full detail sample, edited or not
+    pub fn sample_at(&self, sample: Vector3<i32>) -> f32 {
+        if let Some(value) = self.edits.read().unwrap().get(&sample) {
+            return *value;
+        }
+
+        self.base.sample(chunk_space::global_sample_to_density_pos(sample, self.spacing_per_vertex))
+    }
+
+    // gets the number of samples that have been edited
+    pub fn edited_sample_count(&self) -> usize {
+        self.edits.read().unwrap().len()
+    }
+
+    // gets a copy of every edited sample, to be saved
+    pub fn edits(&self) -> HashMap<Vector3<i32>, f32> {
+        self.edits.read().unwrap().clone()
+    }
+
+    // replaces the edits with ones that were saved
+    pub fn load_edits(&self, edits: HashMap<Vector3<i32>, f32>) {
+        *self.edits.write().unwrap() = edits;
+    }
+
+    // applies a brush to every full detail sample near it, giving back what
+    // it changed so it can be undone
+    pub fn apply_brush(&self, brush: &Brush) -> Option<EditOperation> {
+        // reaches a little past the shape so the density falls off smoothly
+        // outside it rather than stepping at its surface
+        let margin = BRUSH_FALLOFF_SAMPLES * self.spacing_per_vertex;
+        let (min_world, max_world) = brush.shape.bounds();
+
+        let to_sample = |world: f32, round: fn(f32) -> f32| round(world / self.spacing_per_vertex) as i32;
+
+        let min_sample = Vector3::new(
+            to_sample(min_world.x - margin, f32::floor),
+            to_sample(min_world.y - margin, f32::floor),
+            to_sample(min_world.z - margin, f32::floor)
+        );
+
+        let max_sample = Vector3::new(
+            to_sample(max_world.x + margin, f32::ceil),
+            to_sample(max_world.y + margin, f32::ceil),
+            to_sample(max_world.z + margin, f32::ceil)
+        );
+
+        // works out every new value from the densities before the edit, so
+        // smoothing doesn't depend on the order samples are visited in
+        let mut changes = Vec::new();
+
+        for x in min_sample.x..=max_sample.x {
+            for y in min_sample.y..=max_sample.y {
+                for z in min_sample.z..=max_sample.z {
+                    let sample = Vector3::new(x, y, z);
+                    let distance = brush.shape.distance(chunk_space::global_sample_to_world(sample, self.spacing_per_vertex));
+
+                    if distance > margin {
+                        continue;
+                    }
+
+                    let old_value = self.sample_at(sample);
+
+                    // the density a surface on the brush would have here, rising
+                    // into the shape
+                    let slope = BRUSH_DENSITY_SLOPE / self.spacing_per_vertex;
+
+                    let target = match brush.mode {
+                        BrushMode::Add => old_value.max(self.isovalue - distance * slope),
+                        BrushMode::Subtract => old_value.min(self.isovalue + distance * slope),
+                        BrushMode::Smooth => {
+                            if distance > 0.0 {
+                                continue;
+                            }
+
+                            self.neighbour_average(sample)
+                        }
+                    };
+
+                    let new_value = old_value + (target - old_value) * brush.strength;
+
+                    if new_value != old_value {
+                        changes.push(SampleChange {
+                            sample,
+                            old_value: None,
+                            new_value
+                        });
+                    }
+                }
+            }
+        }
+
+        if changes.is_empty() {
+            return None;
+        }
+
+        let mut min_sample = changes[0].sample;
+        let mut max_sample = changes[0].sample;
+
+        let mut edits = self.edits.write().unwrap();
+
+        for change in changes.iter_mut() {
+            for axis in 0..3 {
+                min_sample[axis] = min_sample[axis].min(change.sample[axis]);
+                max_sample[axis] = max_sample[axis].max(change.sample[axis]);
+            }
+
+            change.old_value = edits.insert(change.sample, change.new_value);
+        }
+
+        Some(EditOperation {
+            changes,
+            min_sample,
+            max_sample
+        })
+    }
+
+    // puts back the values an edit replaced, in reverse so samples changed
+    // more than once end up as they started
+    pub fn undo(&self, operation: &EditOperation) {
+        let mut edits = self.edits.write().unwrap();
+
+        for change in operation.changes.iter().rev() {
+            match change.old_value {
+                Some(value) => edits.insert(change.sample, value),
+                None => edits.remove(&change.sample)
+            };
+        }
+    }
+
+    // makes an undone edit again
+    pub fn redo(&self, operation: &EditOperation) {
+        let mut edits = self.edits.write().unwrap();
+
+        for change in operation.changes.iter() {
+            edits.insert(change.sample, change.new_value);
+        }
+    }
+
+    // averages a sample with the six next to it
+    fn neighbour_average(&self, sample: Vector3<i32>) -> f32 {
+        NEIGHBOUR_OFFSETS.iter().map(|offset| self.sample_at(sample + offset)).sum::<f32>() / NEIGHBOUR_OFFSETS.len() as f32
+    }
+}
+
+impl DensityField for EditableDensity {
+    // looks up edits for positions on a full detail sample, which every chunk
+    // samples at whatever its level of detail
+    fn sample(&self, world_pos: Vector3<f64>) -> f32 {
+        let spacing = self.spacing_per_vertex as f64;
+        let sample = Vector3::new(
+            (world_pos.x / spacing).round() as i32,
+            (world_pos.y / spacing).round() as i32,
+            (world_pos.z / spacing).round() as i32
+        );
+
+        if chunk_space::global_sample_to_density_pos(sample, self.spacing_per_vertex) == world_pos {
+            if let Some(value) = self.edits.read().unwrap().get(&sample) {
+                return *value;
+            }
+        }
+
+        self.base.sample(world_pos)
+    }
+}
+
+//------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_distance(shape: &BrushShape, point: Vector3<f32>, expected: f32) {
+        let distance = shape.distance(point);
+
+        assert!((distance - expected).abs() < 1e-5, "{:?} is {} from {:?}, expected {}", point, distance, shape, expected);
+    }
+
+    #[test]
+    fn sphere_distance_is_negative_inside() {
+        let sphere = BrushShape::Sphere { centre: Vector3::new(1.0, 2.0, 3.0), radius: 2.0 };
+
+        assert_distance(&sphere, Vector3::new(1.0, 2.0, 3.0), -2.0);
+        assert_distance(&sphere, Vector3::new(2.0, 2.0, 3.0), -1.0);
+        assert_distance(&sphere, Vector3::new(1.0, 4.0, 3.0), 0.0);
+        assert_distance(&sphere, Vector3::new(1.0, 2.0, 8.0), 3.0);
+    }
+
+    #[test]
+    fn box_distance_is_negative_inside() {
+        let shape = BrushShape::Box { centre: Vector3::new(0.0, 0.0, 0.0), half_extents: Vector3::new(1.0, 2.0, 3.0) };
+
+        // inside it's the distance to the nearest face
+        assert_distance(&shape, Vector3::new(0.0, 0.0, 0.0), -1.0);
+        assert_distance(&shape, Vector3::new(0.5, 0.0, 2.5), -0.5);
+
+        // zero on the faces, edges and corners
+        assert_distance(&shape, Vector3::new(1.0, 0.0, 0.0), 0.0);
+        assert_distance(&shape, Vector3::new(0.0, -2.0, 1.0), 0.0);
+        assert_distance(&shape, Vector3::new(1.0, 2.0, 3.0), 0.0);
+
+        // outside a face it's straight out, and past a corner it's to the corner
+        assert_distance(&shape, Vector3::new(0.0, 0.0, -5.0), 2.0);
+        assert_distance(&shape, Vector3::new(2.0, 3.0, 3.0), 2.0_f32.sqrt());
+    }
+
+    #[test]
+    fn capsule_distance_is_negative_inside() {
+        let capsule = BrushShape::Capsule { start: Vector3::new(0.0, 0.0, 0.0), end: Vector3::new(0.0, 4.0, 0.0), radius: 1.0 };
+
+        assert_distance(&capsule, Vector3::new(0.0, 2.0, 0.0), -1.0);
+        assert_distance(&capsule, Vector3::new(0.0, 4.0, 0.5), -0.5);
+
+        // zero along the side and around the rounded ends
+        assert_distance(&capsule, Vector3::new(1.0, 2.0, 0.0), 0.0);
+        assert_distance(&capsule, Vector3::new(0.0, -1.0, 0.0), 0.0);
+        assert_distance(&capsule, Vector3::new(0.0, 5.0, 0.0), 0.0);
+
+        // past the ends it's measured from the end, not the line through them
+        assert_distance(&capsule, Vector3::new(3.0, 8.0, 0.0), 4.0);
+
+        // a capsule with both ends together is a sphere
+        let point = BrushShape::Capsule { start: Vector3::new(1.0, 1.0, 1.0), end: Vector3::new(1.0, 1.0, 1.0), radius: 1.0 };
+
+        assert_distance(&point, Vector3::new(1.0, 1.0, 1.0), -1.0);
+        assert_distance(&point, Vector3::new(1.0, 1.0, 4.0), 2.0);
+    }
+}
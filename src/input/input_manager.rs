@@ -30,13 +30,13 @@ impl InputManager {
         let key = self.captured_keys.get(&key_input);
 
         // if the key is not none, remove the key
-        if key != None {
+        if key.is_some() {
             self.captured_keys.remove(&key_input);
         }
     }
 
     // polls for whether or not a key is being held down
     pub fn poll_key(&mut self, key_input: VirtualKeyCode) -> bool {
-        self.captured_keys.get(&key_input) != None
+        self.captured_keys.contains(&key_input)
     }
 }
\ No newline at end of file
@@ -1,3 +1,7 @@
+#![allow(clippy::new_without_default)]
+// noise 0.7 glob-exports two structs named Perlin
+#![allow(ambiguous_glob_imports)]
+
 pub mod rendering;
 pub mod models;
 pub mod shaders;
@@ -1,34 +1,97 @@
-#[derive(Clone, Debug, Copy)]
+use std::cell::Cell;
+use std::rc::Rc;
+
+use crate::utils::bounds::Aabb;
+
+//-----------------------
+
+// counts of the GL objects that are currently alive, shared between the
+// loader and every model it creates so leaks show up in the totals
+#[derive(Debug, Default)]
+pub struct GpuObjectCounts {
+    vaos: Cell<usize>,
+    buffers: Cell<usize>
+}
+
+// a mesh that lives on the GPU, owning its VAO and buffers and deleting
+// them when it's dropped
+#[derive(Debug)]
 pub struct RawModel {
     vao_id: u32,
+    vbo_ids: Vec<u32>,
     vertex_count: i32,
+
+    // the box around the mesh's vertices, none when the mesh is empty
+    bounds: Option<Aabb>,
+
+    object_counts: Rc<GpuObjectCounts>
+}
+
+//-----------------------
+
+impl GpuObjectCounts {
+    pub fn vaos(&self) -> usize {
+        self.vaos.get()
+    }
+
+    pub fn buffers(&self) -> usize {
+        self.buffers.get()
+    }
 }
 
 //-----------------------
 
 impl RawModel {
-    pub fn new(vao_id: u32, vertex_count: i32) -> RawModel {
+    // takes ownership of a VAO and the buffers bound to it
+    pub fn new(
+        vao_id: u32,
+        vbo_ids: Vec<u32>,
+        vertex_count: i32,
+        bounds: Option<Aabb>,
+        object_counts: Rc<GpuObjectCounts>
+    ) -> RawModel {
+        // counts the new objects
+        object_counts.vaos.set(object_counts.vaos.get() + 1);
+        object_counts.buffers.set(object_counts.buffers.get() + vbo_ids.len());
+
         RawModel {
             vao_id,
-            vertex_count
+            vbo_ids,
+            vertex_count,
+
+            bounds,
+
+            object_counts
         }
     }
 
-    pub fn get_vao_id(&mut self) -> u32 {
+    pub fn get_vao_id(&self) -> u32 {
         self.vao_id
     }
 
-    pub fn get_vertex_count(&mut self) -> i32 {
+    pub fn get_vertex_count(&self) -> i32 {
         self.vertex_count
     }
 
-    pub fn clean_model(&mut self) {
-        // removes the model
-        // unsafe {
-        //     gl::DeleteVertexArrays(1, self.vao_id as *const u32);
-        // }
+    pub fn bounds(&self) -> Option<Aabb> {
+        self.bounds
+    }
+}
+
+//-----------------------
+
+impl Drop for RawModel {
+    fn drop(&mut self) {
+        // removes the model's objects from the GPU
+        unsafe {
+            gl::DeleteBuffers(self.vbo_ids.len() as i32, self.vbo_ids.as_ptr());
+            gl::DeleteVertexArrays(1, &self.vao_id);
+        }
+
+        // takes them off the live counts
+        let counts = &self.object_counts;
 
-        // drops the data
-        drop(self);
+        counts.vaos.set(counts.vaos.get() - 1);
+        counts.buffers.set(counts.buffers.get() - self.vbo_ids.len());
     }
 }
\ No newline at end of file
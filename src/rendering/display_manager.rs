@@ -1,13 +1,10 @@
 
 
-use std::sync::mpsc::{Receiver};
-
 use glutin::{
     ContextBuilder,
     PossiblyCurrent,
     WindowedContext,
-    event::{Event, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
+    event_loop::EventLoop,
     window::WindowBuilder
 };
 
@@ -1,66 +1,73 @@
 
-use raw_model::RawModel;
-
 use super::super::{
-    models::raw_model
+    models::raw_model::{self, GpuObjectCounts},
+    utils::bounds::Aabb
 };
 
-use std::convert::TryFrom;
 use std::ptr;
+use std::rc::Rc;
 
 //----------------------
 
+// the loader defaults to having no live objects or textures. models own their
+// own VAOs and buffers, the loader only keeps count of them
+#[derive(Default)]
 pub struct Loader {
-    vaos: Vec<u32>,
-    vbos: Vec<u32>,
+    object_counts: Rc<GpuObjectCounts>,
     textures: Vec<u32>
 }
 
 //----------------------
 
 impl Loader {
-    // defaults for the loader
-    pub fn default() -> Loader {
-        Loader {
-            vaos: Vec::new(),
-            vbos: Vec::new(),
-            textures: Vec::new(),
-        }
-    }
-
-    // loads a set of vertices to a VAO
+    // loads a set of vertices and their normals to a VAO
     pub fn load_to_vao(
         &mut self,
-        vertices: &Vec<f32>,
-        indices: &Vec<u32>
+        vertices: &[f32],
+        normals: &[f32],
+        indices: &[u32]
     ) -> raw_model::RawModel {
 
         // gets the vertex count
-        let vertex_count = (indices.len() as i32);
+        let vertex_count = indices.len() as i32;
 
         // gets a vao id by creating a vao
-        let vao_id = self.create_vao();
+        let vao_id = Loader::create_vao();
 
         // binds the index buffer
-        self.bind_index_buffer(indices);
+        let index_vbo = Loader::bind_index_buffer(indices);
 
         // stores the vertices in an attribute list
-        self.store_attb_list_data(0, vertices, 3);
+        let vertex_vbo = Loader::store_attb_list_data(0, vertices, 3);
+
+        // stores the normals in the next attribute list
+        let normal_vbo = Loader::store_attb_list_data(1, normals, 3);
 
         // unbinds the vao
         Loader::unbind_vao();
 
-        // creates the raw model
-        let raw_model = raw_model::RawModel::new(
+        // creates the raw model, which now owns the vao and buffers
+        raw_model::RawModel::new(
             vao_id,
-            vertex_count
-        );
+            vec![index_vbo, vertex_vbo, normal_vbo],
+            vertex_count,
+            Aabb::from_vertices(vertices),
+            Rc::clone(&self.object_counts)
+        )
+    }
 
-        raw_model
+    // gets the number of VAOs owned by models that are still alive
+    pub fn live_vao_count(&self) -> usize {
+        self.object_counts.vaos()
+    }
+
+    // gets the number of buffers owned by models that are still alive
+    pub fn live_buffer_count(&self) -> usize {
+        self.object_counts.buffers()
     }
 
     // creates a new vao
-    fn create_vao(&mut self) -> u32 {
+    fn create_vao() -> u32 {
         // creates a new uint for use in generating a vao
         let mut vao_id = 0;
 
@@ -70,9 +77,6 @@ impl Loader {
             gl::BindVertexArray(vao_id);
         }
 
-        // adds the vao id to the vaos
-        self.vaos.push(vao_id);
-
         // returns the vao id
         vao_id
     }
@@ -87,7 +91,7 @@ impl Loader {
     //-------------------
 
     // stores a set of data into a VBO of a certain index
-    fn store_attb_list_data(&mut self, attribute_num: u32, data: &Vec<f32>, coordinate_size: i32) {
+    fn store_attb_list_data(attribute_num: u32, data: &[f32], coordinate_size: i32) -> u32 {
         // creates a new uint for use in generating a vbo
         let mut vbo_id = 0;
 
@@ -101,7 +105,7 @@ impl Loader {
             // generates buffer data that opengl can use
             gl::BufferData(
                 gl::ARRAY_BUFFER,
-                (data.len() * std::mem::size_of::<f32>()) as gl::types::GLsizeiptr,
+                std::mem::size_of_val(data) as gl::types::GLsizeiptr,
                 data.as_ptr() as *const gl::types::GLvoid,
                 gl::STATIC_DRAW,
             );
@@ -119,12 +123,11 @@ impl Loader {
             gl::BindBuffer(gl::ARRAY_BUFFER, 0);
         }
 
-        // adds the vbo id to the vaos
-        self.vbos.push(vbo_id);
+        vbo_id
     }
 
     // function specifically for generating an index buffer
-    fn bind_index_buffer(&mut self, data: &Vec<u32>) {
+    fn bind_index_buffer(data: &[u32]) -> u32 {
         // creates a new uint for use in generating a vbo
         let mut vbo_id = 0;
 
@@ -138,29 +141,20 @@ impl Loader {
             // generates buffer data that opengl can use
             gl::BufferData(
                 gl::ELEMENT_ARRAY_BUFFER,
-                (data.len() * std::mem::size_of::<f32>()) as gl::types::GLsizeiptr,
+                std::mem::size_of_val(data) as gl::types::GLsizeiptr,
                 data.as_ptr() as *const gl::types::GLvoid,
                 gl::STATIC_DRAW,
             );
         }
 
-        // adds the vbo id to the vaos
-        self.vbos.push(vbo_id);
+        vbo_id
     }
 
     //-------------------
 
     pub fn clean_up(&mut self) {
-        // loops over the vaos, vbos and textures for cleanup
+        // loops over the textures for cleanup, models clean up after themselves
         unsafe {
-            for vao in self.vaos.iter() {
-                gl::DeleteVertexArrays(1, vao);
-            }
-
-            for vbo in self.vbos.iter() {
-                gl::DeleteBuffers(1, vbo);
-            }
-
             for texture in self.textures.iter() {
                 gl::DeleteTextures(1, texture);
             }
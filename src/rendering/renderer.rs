@@ -2,22 +2,35 @@
 use super::super::{
     shaders::static_shader::*,
     models::raw_model::*,
-    utils::math::*,
+    utils::{bounds::Frustum, math::*},
     entities::camera::*
 };
 
-use cgmath::{Deg, Matrix4, SquareMatrix, Vector3};
+use cgmath::{Deg, Matrix4, Vector3};
 
 use std::ptr;
 
+// the view distance before one is set, matching the old far plane
+const DEFAULT_VIEW_DISTANCE: f32 = 100.0;
+
 //----------------------
 
 pub struct Renderer {
     pub shader: StaticShader,
 
+    // how far the camera can see, which sets the far plane and the fog
+    view_distance: f32,
+
     bloggus: f32
 }
 
+// how many models the last frame drew and how many were outside the view
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RenderStats {
+    pub drawn: usize,
+    pub culled: usize
+}
+
 //-----------------------
 
 impl Renderer {
@@ -30,7 +43,7 @@ impl Renderer {
         shader.shader_program.start();
                 
         // generates a projection matrix
-        let projection_matrix = Renderer::generate_projection_matrix();
+        let projection_matrix = Renderer::generate_projection_matrix(DEFAULT_VIEW_DISTANCE);
 
         // injects the projection matrix
         shader.set_projection(projection_matrix);
@@ -47,13 +60,27 @@ impl Renderer {
         // creates the renderer
         Renderer {
             shader,
+
+            view_distance: DEFAULT_VIEW_DISTANCE,
+
             bloggus: 0.0
         }
     }
 
     //-----------------------
 
-    pub fn render(&mut self, models: &mut Vec<RawModel>, camera: &mut Camera) {
+    pub fn view_distance(&self) -> f32 {
+        self.view_distance
+    }
+
+    // changes how far the camera can see in world units
+    pub fn set_view_distance(&mut self, view_distance: f32) {
+        self.view_distance = view_distance;
+    }
+
+    //-----------------------
+
+    pub fn render(&mut self, models: &[&RawModel], camera: &mut Camera) -> RenderStats {
         // prepares the renderer
         self.prepare();
 
@@ -61,11 +88,14 @@ impl Renderer {
         self.shader.shader_program.start();
         
         // generates a projection matrix
-        let projection_matrix = Renderer::generate_projection_matrix();
+        let projection_matrix = Renderer::generate_projection_matrix(self.view_distance);
 
         // injects the projection matrix
         self.shader.set_projection(projection_matrix);
 
+        // thickens the fog so it hides everything by the far plane
+        self.shader.set_fog_density(Renderer::fog_density(self.view_distance));
+
         // gets a view matrix from the camera
         let view_matrix = camera.get_view_matrix();
 
@@ -85,8 +115,26 @@ impl Renderer {
         self.shader.set_transformation(t_matrix);
         //--------------------------
 
+        // gets the frustum the models are drawn into, in model space
+        let frustum = Frustum::from_matrix(projection_matrix * view_matrix * t_matrix);
+
+        let mut stats = RenderStats::default();
+
         // iterates over the models in the model array
-        for model in models.iter_mut() {
+        for model in models.iter() {
+            // skips models that can't be seen, empty models have nothing to draw
+            let visible = match model.bounds() {
+                Some(bounds) => frustum.intersects_aabb(&bounds),
+                None => false
+            };
+
+            if !visible {
+                stats.culled += 1;
+                continue;
+            }
+
+            stats.drawn += 1;
+
             // binds the rawmodel
             self.bind_model(model);
 
@@ -106,6 +154,8 @@ impl Renderer {
 
         // ends the shader
         self.shader.shader_program.stop();
+
+        stats
     }
 
     //-----------------------
@@ -124,20 +174,22 @@ impl Renderer {
 
     //-----------------------
 
-    fn bind_model(&mut self, model: &mut RawModel) {
+    fn bind_model(&mut self, model: &RawModel) {
         unsafe {
             // binds to the model's vao
             gl::BindVertexArray(model.get_vao_id());
 
-            // enables the vertex array 0
+            // enables the vertex arrays for positions and normals
             gl::EnableVertexAttribArray(0);
+            gl::EnableVertexAttribArray(1);
         }
     }
 
     fn unbind_model(&mut self) {
         unsafe {
-            // disable the vertex array 0
+            // disables the vertex arrays for positions and normals
             gl::DisableVertexAttribArray(0);
+            gl::DisableVertexAttribArray(1);
 
             // unbinds the model's vao
             gl::BindVertexArray(0);
@@ -146,18 +198,21 @@ impl Renderer {
 
     //-----------------------
 
-    // helper to help generate a projection matrix
-    fn generate_projection_matrix() -> Matrix4<f32> {
+    // helper to help generate a projection matrix reaching out to the view distance
+    fn generate_projection_matrix(view_distance: f32) -> Matrix4<f32> {
         // temp until a redraw function can be added
-        let matrix = cgmath::perspective(
+        cgmath::perspective(
             Deg(70.0),
-            800 as f32
-                / 600 as f32,
+            800.0 / 600.0,
             0.1,
-            100.0
-        );
-        
-        matrix
+            view_distance
+        )
+    }
+
+    // gets the fog density that leaves 1% visibility at the view distance,
+    // solving exp(-(distance * density)^2) = 0.01 to match the shader
+    fn fog_density(view_distance: f32) -> f32 {
+        100.0_f32.ln().sqrt() / view_distance
     }
 
     //-----------------------
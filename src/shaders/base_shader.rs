@@ -44,21 +44,27 @@ impl ShaderProgram {
             fragment_shader
         };
 
-        // links the shaders to the opengl program
+        // attaches the shaders to the opengl program
         unsafe {
             gl::AttachShader(program_id, vertex_shader);
             gl::AttachShader(program_id, fragment_shader);
         }
 
-        unsafe {
-            // links and validates the shader program
-            gl::LinkProgram(program_id);
-            gl::ValidateProgram(program_id);
-        }
+        // links the program straight away, shaders that bind attributes
+        // relink once they've been bound
+        shader_program.link();
         
         shader_program
     }
 
+    // links and validates the shader program
+    pub fn link(&mut self) {
+        unsafe {
+            gl::LinkProgram(self.program_id);
+            gl::ValidateProgram(self.program_id);
+        }
+    }
+
     //--------------------------
 
     // binds attributes to the shader
@@ -156,7 +162,7 @@ impl ShaderProgram {
         }
 
         // returns the shader id if everything was okay
-        return Ok(shader_id)
+        Ok(shader_id)
     }
 
     //--------------------------
@@ -182,18 +188,18 @@ impl ShaderProgram {
             match data.len() {
                 2 => gl::Uniform2f(
                     location,
-                    *data.get(0).unwrap(),
+                    *data.first().unwrap(),
                     *data.get(1).unwrap()
                 ),
                 3 => gl::Uniform3f(
                     location,
-                    *data.get(0).unwrap(),
+                    *data.first().unwrap(),
                     *data.get(1).unwrap(),
                     *data.get(2).unwrap()
                 ),
                 4 => gl::Uniform4f(
                     location,
-                    *data.get(0).unwrap(),
+                    *data.first().unwrap(),
                     *data.get(1).unwrap(),
                     *data.get(2).unwrap(),
                     *data.get(3).unwrap()
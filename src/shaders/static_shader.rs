@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use super::base_shader::*;
 
-use cgmath::{Matrix, Matrix4};
+use cgmath::Matrix4;
 
 //------------------------
 
@@ -29,8 +29,11 @@ impl StaticShader {
             uniform_locations
         };
 
-        // binds the attributes and gets the locations of everything
+        // binds the attributes, which only take effect once the program is linked
         static_shader.bind_attributes();
+        static_shader.shader_program.link();
+
+        // gets the locations of everything
         static_shader.get_uniform_locations();
 
         static_shader
@@ -44,6 +47,12 @@ impl StaticShader {
             0,
             "position"
         );
+
+        // binds the second VAO list to the normals
+        self.shader_program.bind_attribute(
+            1,
+            "normal"
+        );
     }
     
     //-----------------------
@@ -64,6 +73,11 @@ impl StaticShader {
             String::from("view_matrix"),
             self.shader_program.get_uniform_location("view_matrix")
         );
+
+        self.uniform_locations.insert(
+            String::from("fog_density"),
+            self.shader_program.get_uniform_location("fog_density")
+        );
     }
     
     //-----------------------
@@ -91,4 +105,12 @@ impl StaticShader {
 
         self.shader_program.load_matrix(*location, data);
     }
+
+    pub fn set_fog_density(&mut self, data: f32) {
+        let location = self.uniform_locations.get(
+            &String::from("fog_density")
+        ).unwrap();
+
+        self.shader_program.load_float(*location, data);
+    }
 }
\ No newline at end of file
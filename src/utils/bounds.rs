@@ -0,0 +1,141 @@
+use cgmath::*;
+
+//-------------------------
+
+// an axis aligned box around a mesh
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>
+}
+
+// the six planes of a view frustum, each stored as (normal, distance) with
+// the normal pointing into the frustum
+#[derive(Clone, Copy, Debug)]
+pub struct Frustum {
+    planes: [Vector4<f32>; 6]
+}
+
+//-------------------------
+
+impl Aabb {
+    pub fn new(min: Vector3<f32>, max: Vector3<f32>) -> Aabb {
+        Aabb { min, max }
+    }
+
+    // gets the box around a flat list of xyz positions, or none if there aren't any
+    pub fn from_vertices(vertices: &[f32]) -> Option<Aabb> {
+        let mut positions = vertices.chunks_exact(3).map(|v| Vector3::new(v[0], v[1], v[2]));
+
+        let first = positions.next()?;
+
+        Some(positions.fold(Aabb::new(first, first), |bounds, position| {
+            Aabb::new(
+                Vector3::new(bounds.min.x.min(position.x), bounds.min.y.min(position.y), bounds.min.z.min(position.z)),
+                Vector3::new(bounds.max.x.max(position.x), bounds.max.y.max(position.y), bounds.max.z.max(position.z))
+            )
+        }))
+    }
+}
+
+//-------------------------
+
+impl Frustum {
+    // pulls the planes out of a combined projection * view (* model) matrix
+    pub fn from_matrix(matrix: Matrix4<f32>) -> Frustum {
+        // cgmath is column major, so gathers the rows first
+        let row = |i: usize| Vector4::new(matrix.x[i], matrix.y[i], matrix.z[i], matrix.w[i]);
+
+        let (row_x, row_y, row_z, row_w) = (row(0), row(1), row(2), row(3));
+
+        let planes = [
+            row_w + row_x, // left
+            row_w - row_x, // right
+            row_w + row_y, // bottom
+            row_w - row_y, // top
+            row_w + row_z, // near
+            row_w - row_z  // far
+        ];
+
+        Frustum { planes }
+    }
+
+    // gets whether any of the box could be inside the frustum. boxes near the
+    // corners can pass when they're just outside, which only costs a draw call
+    pub fn intersects_aabb(&self, bounds: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // the corner of the box furthest along the plane's normal
+            let corner = Vector3::new(
+                if plane.x >= 0.0 { bounds.max.x } else { bounds.min.x },
+                if plane.y >= 0.0 { bounds.max.y } else { bounds.min.y },
+                if plane.z >= 0.0 { bounds.max.z } else { bounds.min.z }
+            );
+
+            plane.x * corner.x + plane.y * corner.y + plane.z * corner.z + plane.w >= 0.0
+        })
+    }
+}
+
+//-------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a camera at (10, 5, 10) looking down +x, with the renderer's projection
+    fn frustum() -> Frustum {
+        let projection = perspective(Deg(70.0), 800.0 / 600.0, 0.1, 100.0);
+        let view = Matrix4::look_to_rh(Point3::new(10.0, 5.0, 10.0), Vector3::unit_x(), Vector3::unit_y());
+
+        Frustum::from_matrix(projection * view)
+    }
+
+    // a unit box centred on the given point
+    fn box_at(x: f32, y: f32, z: f32) -> Aabb {
+        Aabb::new(Vector3::new(x - 0.5, y - 0.5, z - 0.5), Vector3::new(x + 0.5, y + 0.5, z + 0.5))
+    }
+
+    #[test]
+    fn boxes_in_front_of_the_camera_are_kept() {
+        let frustum = frustum();
+
+        assert!(frustum.intersects_aabb(&box_at(20.0, 5.0, 10.0)));
+        assert!(frustum.intersects_aabb(&box_at(60.0, 10.0, 20.0)));
+
+        // straddling the near plane still counts
+        assert!(frustum.intersects_aabb(&box_at(10.0, 5.0, 10.0)));
+    }
+
+    #[test]
+    fn boxes_behind_and_beside_the_camera_are_culled() {
+        let frustum = frustum();
+
+        // behind
+        assert!(!frustum.intersects_aabb(&box_at(0.0, 5.0, 10.0)));
+
+        // well off to either side, above and below
+        assert!(!frustum.intersects_aabb(&box_at(20.0, 5.0, 40.0)));
+        assert!(!frustum.intersects_aabb(&box_at(20.0, 5.0, -20.0)));
+        assert!(!frustum.intersects_aabb(&box_at(20.0, 40.0, 10.0)));
+        assert!(!frustum.intersects_aabb(&box_at(20.0, -30.0, 10.0)));
+
+        // past the far plane
+        assert!(!frustum.intersects_aabb(&box_at(120.0, 5.0, 10.0)));
+    }
+
+    #[test]
+    fn a_big_box_around_the_camera_is_kept() {
+        let bounds = Aabb::new(Vector3::new(-100.0, -100.0, -100.0), Vector3::new(100.0, 100.0, 100.0));
+
+        assert!(frustum().intersects_aabb(&bounds));
+    }
+
+    #[test]
+    fn vertex_bounds_cover_every_position() {
+        let bounds = Aabb::from_vertices(&[1.0, -2.0, 3.0, -4.0, 5.0, 0.0, 2.0, 2.0, 2.0]).unwrap();
+
+        assert_eq!(bounds.min, Vector3::new(-4.0, -2.0, 0.0));
+        assert_eq!(bounds.max, Vector3::new(2.0, 5.0, 3.0));
+        assert!(Aabb::from_vertices(&[]).is_none());
+    }
+}
@@ -0,0 +1,112 @@
+use cgmath::*;
+
+//-------------------------
+
+// neighbouring chunks share the samples along their common face, so a chunk
+// with `chunk_bounds` samples per axis only advances by `chunk_bounds - 1`
+// samples (one cell less than its sample count) over its neighbour.
+//
+// every position is worked out from an integer global sample coordinate so
+// that two chunks looking at the same sample always produce the same bits
+
+// gets the number of cells along each axis of a chunk
+pub fn cells_per_chunk(chunk_bounds: usize) -> i32 {
+    chunk_bounds as i32 - 1
+}
+
+// gets the global sample coordinate of the first sample in a chunk
+pub fn chunk_origin_sample(chunk: Vector3<i32>, chunk_bounds: usize) -> Vector3<i32> {
+    chunk * cells_per_chunk(chunk_bounds)
+}
+
+// gets the global sample coordinate of a sample inside a chunk, where the
+// local coordinate may step into the padding outside the chunk
+pub fn local_to_global_sample(
+    chunk: Vector3<i32>,
+    local: Vector3<i32>,
+    chunk_bounds: usize
+) -> Vector3<i32> {
+    chunk_origin_sample(chunk, chunk_bounds) + local
+}
+
+// gets the world position of a global sample coordinate
+pub fn global_sample_to_world(sample: Vector3<i32>, spacing_per_vertex: f32) -> Vector3<f32> {
+    Vector3::new(
+        sample.x as f32 * spacing_per_vertex,
+        sample.y as f32 * spacing_per_vertex,
+        sample.z as f32 * spacing_per_vertex
+    )
+}
+
+// gets the world position of a global sample at double precision, which is
+// what density fields are sampled with
+pub fn global_sample_to_density_pos(sample: Vector3<i32>, spacing_per_vertex: f32) -> Vector3<f64> {
+    Vector3::new(
+        sample.x as f64 * spacing_per_vertex as f64,
+        sample.y as f64 * spacing_per_vertex as f64,
+        sample.z as f64 * spacing_per_vertex as f64
+    )
+}
+
+// gets the world position of a point partway along the edge leaving a global
+// sample in the direction of the axis given
+pub fn edge_point_to_world(
+    sample: Vector3<i32>,
+    axis: usize,
+    interp_value: f32,
+    spacing_per_vertex: f32
+) -> Vector3<f32> {
+    let mut step = Vector3::new(0, 0, 0);
+    step[axis] = 1;
+
+    step_point_to_world(sample, step, interp_value, spacing_per_vertex)
+}
+
+// gets the world position of a point partway along the edge from a global
+// sample to the sample a step away, which may cut diagonally across cells
+pub fn step_point_to_world(
+    sample: Vector3<i32>,
+    step: Vector3<i32>,
+    interp_value: f32,
+    spacing_per_vertex: f32
+) -> Vector3<f32> {
+    // the sample coordinates are whole numbers, so the only rounding happens
+    // once the interpolated value is added and the point is scaled
+    let point = Vector3::new(
+        sample.x as f32 + step.x as f32 * interp_value,
+        sample.y as f32 + step.y as f32 * interp_value,
+        sample.z as f32 + step.z as f32 * interp_value
+    );
+
+    point * spacing_per_vertex
+}
+
+// gets the world position of the first sample in a chunk
+pub fn chunk_origin_world(chunk: Vector3<i32>, chunk_bounds: usize, spacing_per_vertex: f32) -> Vector3<f32> {
+    global_sample_to_world(chunk_origin_sample(chunk, chunk_bounds), spacing_per_vertex)
+}
+
+// gets the chunk that contains a world position
+pub fn world_to_chunk(position: Vector3<f32>, chunk_bounds: usize, spacing_per_vertex: f32) -> Vector3<i32> {
+    let chunk_size = chunk_world_size(chunk_bounds, spacing_per_vertex);
+
+    Vector3::new(
+        (position.x / chunk_size).floor() as i32,
+        (position.y / chunk_size).floor() as i32,
+        (position.z / chunk_size).floor() as i32
+    )
+}
+
+// gets the world size of a chunk along one axis
+pub fn chunk_world_size(chunk_bounds: usize, spacing_per_vertex: f32) -> f32 {
+    cells_per_chunk(chunk_bounds) as f32 * spacing_per_vertex
+}
+
+// gets whether a chunk lies within a sphere of chunks around another
+pub fn chunk_within_radius(chunk: Vector3<i32>, centre: Vector3<i32>, radius: i32) -> bool {
+    // widened so chunks far from the centre can't overflow
+    let distance = chunk.cast::<i64>().unwrap() - centre.cast::<i64>().unwrap();
+    let radius = radius as i64;
+
+    distance.x * distance.x + distance.y * distance.y + distance.z * distance.z <= radius * radius
+}
@@ -1,9 +1,43 @@
+// defaults for the runtime chunk settings
+pub const DEFAULT_CHUNK_BOUNDS: usize = 8;
 
-pub const CHUNK_BOUNDS: usize = 8;
-pub const CHUNK_VIEW_LIMIT: i32 = 3;
+// chunks are loaded within the view distance of the camera's chunk and only
+// dropped once they're the margin further out, so edge chunks don't flicker
+pub const DEFAULT_VIEW_DISTANCE: i32 = 4;
+pub const DEFAULT_UNLOAD_MARGIN: i32 = 1;
 
-pub const SPACING_PER_VERTEX: f32 = 1.0;
-pub const TEST_ISOVALUE: f32 = 0.2;
+// past these the number of chunks loaded is far more than can be built or drawn
+pub const MAX_VIEW_DISTANCE: i32 = 32;
+pub const MAX_UNLOAD_MARGIN: i32 = 8;
+
+// coarser levels of detail past the full detail chunks, each reaching twice
+// as far as the last
+pub const DEFAULT_LOD_LEVELS: u32 = 2;
+pub const MAX_LOD_LEVELS: u32 = 6;
+
+// bytes of built chunks kept on the CPU once they leave the view
+pub const DEFAULT_CHUNK_CACHE_BYTES: usize = 64 * 1024 * 1024;
+
+// saved worlds group chunks into regions this many full detail chunks across
+pub const REGION_CHUNKS: i32 = 8;
+
+// bytes of replaced densities kept so edits can be undone
+pub const DEFAULT_EDIT_HISTORY_BYTES: usize = 16 * 1024 * 1024;
+
+pub const DEFAULT_SPACING_PER_VERTEX: f32 = 1.0;
+pub const DEFAULT_ISOVALUE: f32 = 0.2;
+
+// one noise cycle per chunk width, as the caves were originally tuned
+pub const DEFAULT_NOISE_FREQUENCY: f64 = 0.125;
+
+// brushes reach this many samples past their shape, with the density
+// changing by the slope for every sample across their surface
+pub const BRUSH_FALLOFF_SAMPLES: f32 = 2.0;
+pub const BRUSH_DENSITY_SLOPE: f32 = 0.5;
+
+// the sphere the sculpting keys use, placed this far in front of the camera
+pub const SCULPT_RADIUS: f32 = 2.5;
+pub const SCULPT_REACH: f32 = 6.0;
 
 pub const CAMERA_SPEED: f32 = 4.0;
 pub const CAMERA_ROT: f32 = 3.0;
\ No newline at end of file
@@ -24,7 +24,5 @@ pub fn generate_transformation_matrix(
     let translation_matrix = Matrix4::from_translation(translation);
 
     // returns a final result
-    let final_matrix = translation_matrix * scale_matrix * full_rot_matrix;
-
-    final_matrix
+    translation_matrix * scale_matrix * full_rot_matrix
 }
\ No newline at end of file
@@ -1,3 +1,6 @@
 pub mod math;
 pub mod constants;
-pub mod fps;
\ No newline at end of file
+pub mod fps;
+pub mod chunk_space;
+pub mod world_config;
+pub mod bounds;
\ No newline at end of file
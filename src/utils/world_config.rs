@@ -0,0 +1,353 @@
+use noise::{
+    Billow,
+    Fbm,
+    MultiFractal,
+    OpenSimplex,
+    Perlin,
+    RangeFunction,
+    RidgedMulti,
+    Seedable,
+    Worley
+};
+
+use std::sync::Arc;
+
+use crate::entities::{
+    basic_marching_cubes::MarchingCubesMesher,
+    density_expr::DensityExpr,
+    density_field::{DensityField, NoiseDensity},
+    dual_contouring::DualContouringMesher,
+    marching_tetrahedra::MarchingTetrahedraMesher,
+    mesher::Mesher,
+    surface_nets::SurfaceNetsMesher,
+    table_reader::TriangulationTable
+};
+
+use super::constants::*;
+
+//------------------------
+
+// the noise generators a world can be built from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoiseType {
+    Perlin,
+    OpenSimplex,
+    Worley,
+    Billow,
+    RidgedMulti,
+    Fbm,
+
+    // a preset expression, worley tunnels warped by perlin
+    WarpedTunnels
+}
+
+// the ways a chunk's samples can be turned into a mesh
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MesherType {
+    MarchingCubes,
+    MarchingCubes33,
+    MarchingTetrahedra,
+    SurfaceNets,
+    DualContouring
+}
+
+// everything needed to reproduce the same caves, the defaults match the
+// original hard-coded worley sampler
+#[derive(Clone, Debug)]
+pub struct WorldConfig {
+    pub seed: u32,
+    pub noise_type: NoiseType,
+    pub frequency: f64,
+
+    // worley settings, the distance function measures the distance to the
+    // nearest seed point and enabling the range outputs that distance
+    // instead of the value of the cell
+    pub displacement: f64,
+    pub distance_function: RangeFunction,
+    pub enable_range: bool,
+
+    // fractal settings, used by billow, ridged multi and fbm
+    pub octaves: usize,
+    pub lacunarity: f64,
+    pub persistence: f64,
+
+    // ridged multi only
+    pub attenuation: f64,
+
+    // how chunks sample the density, the number of samples along each axis
+    // of a chunk, the world distance between samples and the density at
+    // which the surface sits
+    pub chunk_bounds: usize,
+    pub spacing_per_vertex: f32,
+    pub isovalue: f32,
+
+    // how the samples are meshed, which doesn't change the caves themselves
+    pub mesher_type: MesherType
+}
+
+pub const WORLD_CONFIG_USAGE: &str = "world options:
+    --seed <u32>                 seed for the noise generator
+    --noise <type>               perlin, open-simplex, worley, billow, ridged-multi, fbm
+                                 or warped-tunnels
+    --frequency <f64>            noise cycles per world unit
+    --displacement <f64>         worley cell value range
+    --distance <function>        worley distance: euclidean, euclidean-squared, manhattan, chebyshev or quadratic
+    --enable-range               worley outputs the distance to the nearest seed point
+    --octaves <usize>            fractal octave count
+    --lacunarity <f64>           fractal frequency multiplier per octave
+    --persistence <f64>          fractal amplitude multiplier per octave
+    --attenuation <f64>          ridged multi attenuation
+    --chunk-bounds <usize>       samples along each axis of a chunk, at least 2
+    --spacing <f32>              world distance between samples
+    --isovalue <f32>             density at which the cave surface sits
+    --mesher <type>              marching-cubes, marching-cubes-33, marching-tetrahedra,
+                                 surface-nets or dual-contouring";
+
+//------------------------
+
+impl WorldConfig {
+    pub fn new() -> WorldConfig {
+        WorldConfig {
+            seed: Worley::DEFAULT_SEED,
+            noise_type: NoiseType::Worley,
+            frequency: DEFAULT_NOISE_FREQUENCY,
+
+            displacement: Worley::DEFAULT_DISPLACEMENT,
+            distance_function: Worley::DEFAULT_RANGEFUNCTION,
+            enable_range: false,
+
+            octaves: Fbm::DEFAULT_OCTAVE_COUNT,
+            lacunarity: Fbm::DEFAULT_LACUNARITY,
+            persistence: Fbm::DEFAULT_PERSISTENCE,
+
+            attenuation: RidgedMulti::DEFAULT_ATTENUATION,
+
+            chunk_bounds: DEFAULT_CHUNK_BOUNDS,
+            spacing_per_vertex: DEFAULT_SPACING_PER_VERTEX,
+            isovalue: DEFAULT_ISOVALUE,
+
+            mesher_type: MesherType::MarchingCubes
+        }
+    }
+
+    // reads a config from command line arguments, failing on anything it doesn't know
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<WorldConfig, String> {
+        let mut world_config = WorldConfig::new();
+
+        while let Some(flag) = args.next() {
+            if !world_config.apply_flag(&flag, &mut args)? {
+                return Err(format!("unknown option '{}'", flag));
+            }
+        }
+
+        Ok(world_config)
+    }
+
+    // applies one command line flag, taking its value from the arguments when it
+    // has one, and returns false if the flag isn't a world option
+    pub fn apply_flag<I: Iterator<Item = String>>(&mut self, flag: &str, args: &mut I) -> Result<bool, String> {
+        match flag {
+            "--seed" => self.seed = parse_value(flag, args)?,
+            "--noise" => self.noise_type = parse_noise_type(&next_value(flag, args)?)?,
+            "--frequency" => self.frequency = parse_value(flag, args)?,
+            "--displacement" => self.displacement = parse_value(flag, args)?,
+            "--distance" => self.distance_function = parse_distance_function(&next_value(flag, args)?)?,
+            "--enable-range" => self.enable_range = true,
+            "--octaves" => self.octaves = parse_value(flag, args)?,
+            "--lacunarity" => self.lacunarity = parse_value(flag, args)?,
+            "--persistence" => self.persistence = parse_value(flag, args)?,
+            "--attenuation" => self.attenuation = parse_value(flag, args)?,
+            "--chunk-bounds" => self.chunk_bounds = parse_value(flag, args)?,
+            "--spacing" => self.spacing_per_vertex = parse_value(flag, args)?,
+            "--isovalue" => self.isovalue = parse_value(flag, args)?,
+            "--mesher" => self.mesher_type = parse_mesher_type(&next_value(flag, args)?)?,
+            _ => return Ok(false)
+        };
+
+        self.validate()?;
+
+        Ok(true)
+    }
+
+    // checks the chunk settings can actually be meshed
+    pub fn validate(&self) -> Result<(), String> {
+        if self.chunk_bounds < 2 {
+            return Err(format!("chunk bounds must be at least 2, got {}", self.chunk_bounds));
+        }
+
+        if self.spacing_per_vertex <= 0.0 || !self.spacing_per_vertex.is_finite() {
+            return Err(format!("spacing must be a positive number, got {}", self.spacing_per_vertex));
+        }
+
+        Ok(())
+    }
+
+    // writes out the options that decide the density, in the same form they're
+    // read from the command line, so saved worlds can be checked against the
+    // config they're loaded with. the mesher is left out as it doesn't change
+    // the density
+    pub fn density_args(&self) -> String {
+        let mut args = vec![
+            format!("--seed {}", self.seed),
+            format!("--noise {}", noise_type_name(self.noise_type)),
+            format!("--frequency {}", self.frequency),
+            format!("--displacement {}", self.displacement),
+            format!("--distance {}", distance_function_name(self.distance_function))
+        ];
+
+        if self.enable_range {
+            args.push(String::from("--enable-range"));
+        }
+
+        args.extend(vec![
+            format!("--octaves {}", self.octaves),
+            format!("--lacunarity {}", self.lacunarity),
+            format!("--persistence {}", self.persistence),
+            format!("--attenuation {}", self.attenuation),
+            format!("--chunk-bounds {}", self.chunk_bounds),
+            format!("--spacing {}", self.spacing_per_vertex),
+            format!("--isovalue {}", self.isovalue)
+        ]);
+
+        args.join(" ")
+    }
+
+    //--------------------
+
+    // builds the density field described by the config
+    pub fn create_density_field(&self) -> Box<dyn DensityField> {
+        match self.noise_type {
+            NoiseType::Perlin => Box::new(NoiseDensity::new(
+                Perlin::new().set_seed(self.seed),
+                self.frequency
+            )),
+            NoiseType::OpenSimplex => Box::new(NoiseDensity::new(
+                OpenSimplex::new().set_seed(self.seed),
+                self.frequency
+            )),
+            NoiseType::Worley => Box::new(NoiseDensity::new(
+                Worley::new()
+                    .set_seed(self.seed)
+                    .set_displacement(self.displacement)
+                    .set_range_function(self.distance_function)
+                    .enable_range(self.enable_range),
+                self.frequency
+            )),
+            // the fractal generators are sampled at the world frequency through
+            // the density wrapper, so their own base frequency stays at one
+            NoiseType::Billow => Box::new(NoiseDensity::new(
+                Billow::new()
+                    .set_seed(self.seed)
+                    .set_octaves(self.octaves)
+                    .set_frequency(1.0)
+                    .set_lacunarity(self.lacunarity)
+                    .set_persistence(self.persistence),
+                self.frequency
+            )),
+            NoiseType::RidgedMulti => Box::new(NoiseDensity::new(
+                RidgedMulti::new()
+                    .set_seed(self.seed)
+                    .set_octaves(self.octaves)
+                    .set_frequency(1.0)
+                    .set_lacunarity(self.lacunarity)
+                    .set_persistence(self.persistence)
+                    .set_attenuation(self.attenuation),
+                self.frequency
+            )),
+            NoiseType::Fbm => Box::new(NoiseDensity::new(
+                Fbm::new()
+                    .set_seed(self.seed)
+                    .set_octaves(self.octaves)
+                    .set_frequency(1.0)
+                    .set_lacunarity(self.lacunarity)
+                    .set_persistence(self.persistence),
+                self.frequency
+            )),
+            NoiseType::WarpedTunnels => Box::new(DensityExpr::warped_tunnels(self.seed, self.frequency))
+        }
+    }
+
+    // builds the mesher described by the config, marching cubes reading its
+    // triangles from the table, which needs its extended cases loaded for
+    // marching cubes 33
+    pub fn create_mesher(&self, tri_table: Arc<TriangulationTable>) -> Box<dyn Mesher> {
+        match self.mesher_type {
+            MesherType::MarchingCubes => Box::new(MarchingCubesMesher::new(tri_table)),
+            MesherType::MarchingCubes33 => Box::new(MarchingCubesMesher::with_mc33(tri_table)),
+            MesherType::MarchingTetrahedra => Box::new(MarchingTetrahedraMesher::new()),
+            MesherType::SurfaceNets => Box::new(SurfaceNetsMesher::new()),
+            MesherType::DualContouring => Box::new(DualContouringMesher::new())
+        }
+    }
+}
+
+//------------------------
+
+// gets the value that follows a flag
+fn next_value<I: Iterator<Item = String>>(flag: &str, args: &mut I) -> Result<String, String> {
+    args.next().ok_or(format!("option '{}' needs a value", flag))
+}
+
+// gets and parses the value that follows a flag
+fn parse_value<T: std::str::FromStr, I: Iterator<Item = String>>(flag: &str, args: &mut I) -> Result<T, String> {
+    let value = next_value(flag, args)?;
+
+    value.parse::<T>().map_err(|_| format!("invalid value '{}' for option '{}'", value, flag))
+}
+
+fn parse_noise_type(value: &str) -> Result<NoiseType, String> {
+    match value {
+        "perlin" => Ok(NoiseType::Perlin),
+        "open-simplex" => Ok(NoiseType::OpenSimplex),
+        "worley" => Ok(NoiseType::Worley),
+        "billow" => Ok(NoiseType::Billow),
+        "ridged-multi" => Ok(NoiseType::RidgedMulti),
+        "fbm" => Ok(NoiseType::Fbm),
+        "warped-tunnels" => Ok(NoiseType::WarpedTunnels),
+        _ => Err(format!("unknown noise type '{}'", value))
+    }
+}
+
+fn parse_mesher_type(value: &str) -> Result<MesherType, String> {
+    match value {
+        "marching-cubes" => Ok(MesherType::MarchingCubes),
+        "marching-cubes-33" => Ok(MesherType::MarchingCubes33),
+        "marching-tetrahedra" => Ok(MesherType::MarchingTetrahedra),
+        "surface-nets" => Ok(MesherType::SurfaceNets),
+        "dual-contouring" => Ok(MesherType::DualContouring),
+        _ => Err(format!("unknown mesher '{}'", value))
+    }
+}
+
+fn parse_distance_function(value: &str) -> Result<RangeFunction, String> {
+    match value {
+        "euclidean" => Ok(RangeFunction::Euclidean),
+        "euclidean-squared" => Ok(RangeFunction::EuclideanSquared),
+        "manhattan" => Ok(RangeFunction::Manhattan),
+        "chebyshev" => Ok(RangeFunction::Chebyshev),
+        "quadratic" => Ok(RangeFunction::Quadratic),
+        _ => Err(format!("unknown distance function '{}'", value))
+    }
+}
+
+fn noise_type_name(noise_type: NoiseType) -> &'static str {
+    match noise_type {
+        NoiseType::Perlin => "perlin",
+        NoiseType::OpenSimplex => "open-simplex",
+        NoiseType::Worley => "worley",
+        NoiseType::Billow => "billow",
+        NoiseType::RidgedMulti => "ridged-multi",
+        NoiseType::Fbm => "fbm",
+        NoiseType::WarpedTunnels => "warped-tunnels"
+    }
+}
+
+fn distance_function_name(distance_function: RangeFunction) -> &'static str {
+    match distance_function {
+        RangeFunction::Euclidean => "euclidean",
+        RangeFunction::EuclideanSquared => "euclidean-squared",
+        RangeFunction::Manhattan => "manhattan",
+        RangeFunction::Chebyshev => "chebyshev",
+        RangeFunction::Quadratic => "quadratic"
+    }
+}